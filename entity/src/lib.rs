@@ -2,6 +2,13 @@
 
 pub mod prelude;
 
+pub mod kitty_entry;
 pub mod list;
 pub mod list_item;
 pub mod payment;
+pub mod payment_revision;
+pub mod pending_item;
+pub mod recurring_bill;
+pub mod reminder_settings;
+pub mod sent_statement;
+pub mod settlement;
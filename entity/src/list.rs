@@ -20,6 +20,11 @@ pub struct Model {
     pub creation_message_id: i64,
     pub creation_message_channel_id: i64,
     pub creation_message_guild_id: Option<i64>,
+    pub active: bool,
+    pub archived_at: Option<DateTime>,
+    pub completed_at: Option<DateTime>,
+    pub board_message_id: Option<i64>,
+    pub board_channel_id: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -31,6 +36,11 @@ pub enum Column {
     CreationMessageId,
     CreationMessageChannelId,
     CreationMessageGuildId,
+    Active,
+    ArchivedAt,
+    CompletedAt,
+    BoardMessageId,
+    BoardChannelId,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -61,6 +71,11 @@ impl ColumnTrait for Column {
             Self::CreationMessageId => ColumnType::BigInteger.def(),
             Self::CreationMessageChannelId => ColumnType::BigInteger.def(),
             Self::CreationMessageGuildId => ColumnType::BigInteger.def().null(),
+            Self::Active => ColumnType::Boolean.def(),
+            Self::ArchivedAt => ColumnType::DateTime.def().null(),
+            Self::CompletedAt => ColumnType::DateTime.def().null(),
+            Self::BoardMessageId => ColumnType::BigInteger.def().null(),
+            Self::BoardChannelId => ColumnType::BigInteger.def().null(),
         }
     }
 }
@@ -24,6 +24,9 @@ pub struct Model {
     pub personal: bool,
     pub store: Option<String>,
     pub notes: Option<String>,
+    pub channel_id: Option<i64>,
+    pub bought_by: Option<i64>,
+    pub bought_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -39,6 +42,9 @@ pub enum Column {
     Personal,
     Store,
     Notes,
+    ChannelId,
+    BoughtBy,
+    BoughtAt,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -73,6 +79,9 @@ impl ColumnTrait for Column {
             Self::Personal => ColumnType::Boolean.def(),
             Self::Store => ColumnType::String(None).def().null(),
             Self::Notes => ColumnType::String(None).def().null(),
+            Self::ChannelId => ColumnType::BigInteger.def().null(),
+            Self::BoughtBy => ColumnType::BigInteger.def().null(),
+            Self::BoughtAt => ColumnType::DateTime.def().null(),
         }
     }
 }
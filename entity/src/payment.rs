@@ -21,6 +21,22 @@ pub struct Model {
     pub individual_users: Vec<i64>,
     pub individual_amounts: Vec<i64>,
     pub image_path: String,
+    pub purpose: String,
+    pub account: String,
+    pub receipt_url: String,
+    pub channel_id: i64,
+    pub guild_id: Option<i64>,
+    pub paid_users: Vec<i64>,
+    pub paid_at: Vec<DateTime>,
+    pub reminded_users: Vec<i64>,
+    pub reminded_at: Vec<DateTime>,
+    pub voided_at: Option<DateTime>,
+    pub share_references: Vec<String>,
+    pub period_start: Option<Date>,
+    pub period_end: Option<Date>,
+    pub disputed_users: Vec<i64>,
+    pub dispute_reasons: Vec<String>,
+    pub dispute_outcomes: Vec<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -33,6 +49,22 @@ pub enum Column {
     IndividualUsers,
     IndividualAmounts,
     ImagePath,
+    Purpose,
+    Account,
+    ReceiptUrl,
+    ChannelId,
+    GuildId,
+    PaidUsers,
+    PaidAt,
+    RemindedUsers,
+    RemindedAt,
+    VoidedAt,
+    ShareReferences,
+    PeriodStart,
+    PeriodEnd,
+    DisputedUsers,
+    DisputeReasons,
+    DisputeOutcomes,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -66,6 +98,38 @@ impl ColumnTrait for Column {
                 ColumnType::Array(sea_orm::sea_query::SeaRc::new(ColumnType::BigInteger)).def()
             }
             Self::ImagePath => ColumnType::String(None).def(),
+            Self::Purpose => ColumnType::String(None).def(),
+            Self::Account => ColumnType::String(None).def(),
+            Self::ReceiptUrl => ColumnType::String(None).def(),
+            Self::ChannelId => ColumnType::BigInteger.def(),
+            Self::GuildId => ColumnType::BigInteger.def().null(),
+            Self::PaidUsers => {
+                ColumnType::Array(sea_orm::sea_query::SeaRc::new(ColumnType::BigInteger)).def()
+            }
+            Self::PaidAt => {
+                ColumnType::Array(sea_orm::sea_query::SeaRc::new(ColumnType::DateTime)).def()
+            }
+            Self::RemindedUsers => {
+                ColumnType::Array(sea_orm::sea_query::SeaRc::new(ColumnType::BigInteger)).def()
+            }
+            Self::RemindedAt => {
+                ColumnType::Array(sea_orm::sea_query::SeaRc::new(ColumnType::DateTime)).def()
+            }
+            Self::VoidedAt => ColumnType::DateTime.def().null(),
+            Self::ShareReferences => {
+                ColumnType::Array(sea_orm::sea_query::SeaRc::new(ColumnType::String(None))).def()
+            }
+            Self::PeriodStart => ColumnType::Date.def().null(),
+            Self::PeriodEnd => ColumnType::Date.def().null(),
+            Self::DisputedUsers => {
+                ColumnType::Array(sea_orm::sea_query::SeaRc::new(ColumnType::BigInteger)).def()
+            }
+            Self::DisputeReasons => {
+                ColumnType::Array(sea_orm::sea_query::SeaRc::new(ColumnType::String(None))).def()
+            }
+            Self::DisputeOutcomes => {
+                ColumnType::Array(sea_orm::sea_query::SeaRc::new(ColumnType::String(None))).def()
+            }
         }
     }
 }
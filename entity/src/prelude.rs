@@ -1,5 +1,12 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.10.6
 
+pub use super::kitty_entry::Entity as KittyEntry;
 pub use super::list::Entity as List;
 pub use super::list_item::Entity as ListItem;
 pub use super::payment::Entity as Payment;
+pub use super::payment_revision::Entity as PaymentRevision;
+pub use super::pending_item::Entity as PendingItem;
+pub use super::recurring_bill::Entity as RecurringBill;
+pub use super::reminder_settings::Entity as ReminderSettings;
+pub use super::sent_statement::Entity as SentStatement;
+pub use super::settlement::Entity as Settlement;
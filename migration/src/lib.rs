@@ -1,12 +1,42 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20230512_000002_payment_details;
+mod m20230520_000003_create_settlement_table;
+mod m20230527_000004_create_recurring_bill_table;
+mod m20230603_000005_create_reminder_settings_table;
+mod m20230610_000006_create_payment_revision_table;
+mod m20230617_000007_add_payment_references;
+mod m20230624_000008_create_sent_statement_table;
+mod m20230701_000009_add_payment_period;
+mod m20230708_000010_add_payment_disputes;
+mod m20230715_000011_create_kitty_entry_table;
+mod m20230722_000012_add_list_state;
+mod m20230729_000013_add_shopping_trips;
+mod m20230805_000014_add_list_board;
+mod m20230812_000015_create_pending_item_table;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20230512_000002_payment_details::Migration),
+            Box::new(m20230520_000003_create_settlement_table::Migration),
+            Box::new(m20230527_000004_create_recurring_bill_table::Migration),
+            Box::new(m20230603_000005_create_reminder_settings_table::Migration),
+            Box::new(m20230610_000006_create_payment_revision_table::Migration),
+            Box::new(m20230617_000007_add_payment_references::Migration),
+            Box::new(m20230624_000008_create_sent_statement_table::Migration),
+            Box::new(m20230701_000009_add_payment_period::Migration),
+            Box::new(m20230708_000010_add_payment_disputes::Migration),
+            Box::new(m20230715_000011_create_kitty_entry_table::Migration),
+            Box::new(m20230722_000012_add_list_state::Migration),
+            Box::new(m20230729_000013_add_shopping_trips::Migration),
+            Box::new(m20230805_000014_add_list_board::Migration),
+            Box::new(m20230812_000015_create_pending_item_table::Migration),
+        ]
     }
 }
@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum Payment {
+    Table,
+    Purpose,
+    Account,
+    ReceiptUrl,
+    ChannelId,
+    GuildId,
+    PaidUsers,
+    PaidAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .add_column(
+                        ColumnDef::new(Payment::Purpose)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .add_column(
+                        ColumnDef::new(Payment::Account)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .add_column(
+                        ColumnDef::new(Payment::ReceiptUrl)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .add_column(
+                        ColumnDef::new(Payment::ChannelId)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(ColumnDef::new(Payment::GuildId).big_integer())
+                    .add_column(
+                        ColumnDef::new(Payment::PaidUsers)
+                            .array(ColumnType::BigInteger(None))
+                            .not_null()
+                            .extra("DEFAULT '{}'".to_owned()),
+                    )
+                    .add_column(
+                        ColumnDef::new(Payment::PaidAt)
+                            .array(ColumnType::DateTime(None))
+                            .not_null()
+                            .extra("DEFAULT '{}'".to_owned()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .drop_column(Payment::Purpose)
+                    .drop_column(Payment::Account)
+                    .drop_column(Payment::ReceiptUrl)
+                    .drop_column(Payment::ChannelId)
+                    .drop_column(Payment::GuildId)
+                    .drop_column(Payment::PaidUsers)
+                    .drop_column(Payment::PaidAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum Settlement {
+    Table,
+    Id,
+    GuildId,
+    ChannelId,
+    PlanMessageId,
+    CreatedBy,
+    CreatedAt,
+    Debtor,
+    Creditor,
+    Amount,
+    PaymentIds,
+    SettledAt,
+    Applied,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Settlement::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Settlement::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Settlement::GuildId).big_integer())
+                    .col(
+                        ColumnDef::new(Settlement::ChannelId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Settlement::PlanMessageId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Settlement::CreatedBy)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Settlement::CreatedAt).date_time().not_null())
+                    .col(ColumnDef::new(Settlement::Debtor).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(Settlement::Creditor)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Settlement::Amount).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(Settlement::PaymentIds)
+                            .array(ColumnType::Integer(None))
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Settlement::SettledAt).date_time())
+                    .col(ColumnDef::new(Settlement::Applied).boolean().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Settlement::Table).to_owned())
+            .await
+    }
+}
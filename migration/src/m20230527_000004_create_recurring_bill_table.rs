@@ -0,0 +1,89 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum RecurringBill {
+    Table,
+    Id,
+    GuildId,
+    ChannelId,
+    CreatedBy,
+    CreatedAt,
+    Purpose,
+    Account,
+    TotalAmount,
+    IndividualUsers,
+    IndividualAmounts,
+    Frequency,
+    DayOfMonth,
+    NextRun,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecurringBill::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RecurringBill::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RecurringBill::GuildId).big_integer())
+                    .col(
+                        ColumnDef::new(RecurringBill::ChannelId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RecurringBill::CreatedBy)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RecurringBill::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RecurringBill::Purpose).string().not_null())
+                    .col(ColumnDef::new(RecurringBill::Account).string().not_null())
+                    .col(
+                        ColumnDef::new(RecurringBill::TotalAmount)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RecurringBill::IndividualUsers)
+                            .array(ColumnType::BigInteger(None))
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RecurringBill::IndividualAmounts)
+                            .array(ColumnType::BigInteger(None))
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RecurringBill::Frequency).string().not_null())
+                    .col(ColumnDef::new(RecurringBill::DayOfMonth).integer())
+                    .col(
+                        ColumnDef::new(RecurringBill::NextRun)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RecurringBill::Table).to_owned())
+            .await
+    }
+}
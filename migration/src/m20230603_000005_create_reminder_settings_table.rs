@@ -0,0 +1,104 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum ReminderSettings {
+    Table,
+    GuildId,
+    Enabled,
+    UnpaidAfterHours,
+    IntervalHours,
+    QuietStart,
+    QuietEnd,
+}
+
+#[derive(Iden)]
+enum Payment {
+    Table,
+    RemindedUsers,
+    RemindedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReminderSettings::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ReminderSettings::GuildId)
+                            .big_integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ReminderSettings::Enabled)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ReminderSettings::UnpaidAfterHours)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ReminderSettings::IntervalHours)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ReminderSettings::QuietStart)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ReminderSettings::QuietEnd)
+                            .integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // track who has been reminded about each bill, in the same way as who has paid it
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .add_column(
+                        ColumnDef::new(Payment::RemindedUsers)
+                            .array(ColumnType::BigInteger(None))
+                            .not_null()
+                            .extra("DEFAULT '{}'".to_owned()),
+                    )
+                    .add_column(
+                        ColumnDef::new(Payment::RemindedAt)
+                            .array(ColumnType::DateTime(None))
+                            .not_null()
+                            .extra("DEFAULT '{}'".to_owned()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .drop_column(Payment::RemindedUsers)
+                    .drop_column(Payment::RemindedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ReminderSettings::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,103 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum PaymentRevision {
+    Table,
+    Id,
+    PaymentId,
+    RevisedBy,
+    RevisedAt,
+    Action,
+    Purpose,
+    Account,
+    TotalAmount,
+    IndividualUsers,
+    IndividualAmounts,
+}
+
+#[derive(Iden)]
+enum Payment {
+    Table,
+    VoidedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PaymentRevision::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PaymentRevision::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentRevision::PaymentId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentRevision::RevisedBy)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentRevision::RevisedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PaymentRevision::Action).string().not_null())
+                    .col(ColumnDef::new(PaymentRevision::Purpose).string().not_null())
+                    .col(ColumnDef::new(PaymentRevision::Account).string().not_null())
+                    .col(
+                        ColumnDef::new(PaymentRevision::TotalAmount)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentRevision::IndividualUsers)
+                            .array(ColumnType::BigInteger(None))
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentRevision::IndividualAmounts)
+                            .array(ColumnType::BigInteger(None))
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .add_column(ColumnDef::new(Payment::VoidedAt).date_time())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .drop_column(Payment::VoidedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(PaymentRevision::Table).to_owned())
+            .await
+    }
+}
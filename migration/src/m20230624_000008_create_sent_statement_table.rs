@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum SentStatement {
+    Table,
+    Id,
+    GuildId,
+    UserId,
+    Month,
+    SentAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SentStatement::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SentStatement::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SentStatement::GuildId).big_integer())
+                    .col(
+                        ColumnDef::new(SentStatement::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(SentStatement::Month).date().not_null())
+                    .col(ColumnDef::new(SentStatement::SentAt).date_time().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SentStatement::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum Payment {
+    Table,
+    PeriodStart,
+    PeriodEnd,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .add_column(ColumnDef::new(Payment::PeriodStart).date())
+                    .add_column(ColumnDef::new(Payment::PeriodEnd).date())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .drop_column(Payment::PeriodStart)
+                    .drop_column(Payment::PeriodEnd)
+                    .to_owned(),
+            )
+            .await
+    }
+}
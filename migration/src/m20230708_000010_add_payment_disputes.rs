@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum Payment {
+    Table,
+    DisputedUsers,
+    DisputeReasons,
+    DisputeOutcomes,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .add_column(
+                        ColumnDef::new(Payment::DisputedUsers)
+                            .array(ColumnType::BigInteger(None))
+                            .not_null()
+                            .extra("DEFAULT '{}'".to_owned()),
+                    )
+                    .add_column(
+                        ColumnDef::new(Payment::DisputeReasons)
+                            .array(ColumnType::String(None))
+                            .not_null()
+                            .extra("DEFAULT '{}'".to_owned()),
+                    )
+                    .add_column(
+                        ColumnDef::new(Payment::DisputeOutcomes)
+                            .array(ColumnType::String(None))
+                            .not_null()
+                            .extra("DEFAULT '{}'".to_owned()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .drop_column(Payment::DisputedUsers)
+                    .drop_column(Payment::DisputeReasons)
+                    .drop_column(Payment::DisputeOutcomes)
+                    .to_owned(),
+            )
+            .await
+    }
+}
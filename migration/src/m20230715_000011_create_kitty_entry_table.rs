@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum KittyEntry {
+    Table,
+    Id,
+    GuildId,
+    ChannelId,
+    UserId,
+    Amount,
+    Description,
+    ReceiptUrl,
+    ImagePath,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(KittyEntry::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(KittyEntry::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(KittyEntry::GuildId).big_integer())
+                    .col(
+                        ColumnDef::new(KittyEntry::ChannelId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(KittyEntry::UserId).big_integer().not_null())
+                    .col(ColumnDef::new(KittyEntry::Amount).big_integer().not_null())
+                    .col(ColumnDef::new(KittyEntry::Description).string().not_null())
+                    .col(ColumnDef::new(KittyEntry::ReceiptUrl).string().not_null())
+                    .col(ColumnDef::new(KittyEntry::ImagePath).string().not_null())
+                    .col(ColumnDef::new(KittyEntry::CreatedAt).date_time().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(KittyEntry::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum List {
+    Table,
+    Active,
+    ArchivedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(List::Table)
+                    .add_column(
+                        ColumnDef::new(List::Active)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(ColumnDef::new(List::ArchivedAt).date_time())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(List::Table)
+                    .drop_column(List::Active)
+                    .drop_column(List::ArchivedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,65 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum List {
+    Table,
+    CompletedAt,
+}
+
+#[derive(Iden)]
+enum ListItem {
+    Table,
+    ChannelId,
+    BoughtBy,
+    BoughtAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(List::Table)
+                    .add_column(ColumnDef::new(List::CompletedAt).date_time())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ListItem::Table)
+                    .add_column(ColumnDef::new(ListItem::ChannelId).big_integer())
+                    .add_column(ColumnDef::new(ListItem::BoughtBy).big_integer())
+                    .add_column(ColumnDef::new(ListItem::BoughtAt).date_time())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ListItem::Table)
+                    .drop_column(ListItem::ChannelId)
+                    .drop_column(ListItem::BoughtBy)
+                    .drop_column(ListItem::BoughtAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(List::Table)
+                    .drop_column(List::CompletedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum List {
+    Table,
+    BoardMessageId,
+    BoardChannelId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(List::Table)
+                    .add_column(ColumnDef::new(List::BoardMessageId).big_integer())
+                    .add_column(ColumnDef::new(List::BoardChannelId).big_integer())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(List::Table)
+                    .drop_column(List::BoardMessageId)
+                    .drop_column(List::BoardChannelId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,67 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum PendingItem {
+    Table,
+    Id,
+    ListId,
+    DuplicateId,
+    UserId,
+    CreatedAt,
+    Item,
+    Personal,
+    Quantity,
+    Store,
+    Notes,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PendingItem::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PendingItem::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PendingItem::ListId).integer().not_null())
+                    .col(
+                        ColumnDef::new(PendingItem::DuplicateId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PendingItem::UserId).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(PendingItem::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PendingItem::Item).string().not_null())
+                    .col(ColumnDef::new(PendingItem::Personal).boolean().not_null())
+                    .col(
+                        ColumnDef::new(PendingItem::Quantity)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PendingItem::Store).string())
+                    .col(ColumnDef::new(PendingItem::Notes).string())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PendingItem::Table).to_owned())
+            .await
+    }
+}
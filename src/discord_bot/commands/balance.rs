@@ -0,0 +1,168 @@
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{common::embed::EmbedColor, database::payments::SerenityPaymentDatabase},
+    finance::{ledger::Ledger, money::Money},
+    state::{AppState, CONFIG},
+};
+
+use super::{
+    command::Command,
+    util::{display_name, CommandResponse},
+};
+
+/// discord allows at most this many embeds on a message
+const MAX_EMBEDS: usize = 10;
+
+/// build an embed describing the position of a single flatmate in the ledger
+fn create_balance_embed(ledger: &Ledger, user: u64) -> CreateEmbed {
+    let net = ledger.net(user);
+
+    let mut embed = CreateEmbed::new()
+        .title(format!("Balance for {}", display_name(user)))
+        .description(match net {
+            n if n.is_zero() => String::from("All square, nothing owing either way."),
+            n if n > Money::ZERO => format!("Is owed {} overall.", n),
+            n => format!("Owes {} overall.", -n),
+        })
+        .color(match net {
+            n if n.is_zero() => EmbedColor::Green as u32,
+            n if n > Money::ZERO => EmbedColor::Orange as u32,
+            _ => EmbedColor::Red as u32,
+        });
+
+    for debt in ledger.owed_by(user) {
+        embed = embed.field(
+            format!("Owes {}:", display_name(debt.creditor)),
+            format!("{} since {}", debt.amount, debt.since.format("%d/%m/%y")),
+            false,
+        );
+    }
+
+    for debt in ledger.owed_to(user) {
+        embed = embed.field(
+            format!("Is owed by {}:", display_name(debt.debtor)),
+            format!("{} since {}", debt.amount, debt.since.format("%d/%m/%y")),
+            false,
+        );
+    }
+
+    embed
+}
+
+pub struct BalanceCommand {
+    flatmate: Option<u64>,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for BalanceCommand {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let mut flatmate = None;
+
+        for option in interaction.data.options() {
+            match (option.name, option.value) {
+                ("flatmate", ResolvedValue::User(user, _)) => flatmate = Some(user.id.into()),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self { flatmate })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for BalanceCommand {
+    fn name() -> &'static str {
+        "balance"
+    }
+
+    fn description() -> &'static str {
+        "Show who owes whom, and since when"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::User,
+                "flatmate",
+                "Only show the balance for this flatmate",
+            )
+            .required(false),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: Option<u64> = interaction.guild_id.map(|g| g.0.into());
+
+        let payments = match app_state.get_guild_payments(guild_id).await {
+            Ok(payments) => payments,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        let settlements = match app_state.get_unapplied_settlements(guild_id).await {
+            Ok(settlements) => settlements,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        let ledger = Ledger::new(&payments, &settlements);
+
+        let mut users: Vec<u64> = match self.flatmate {
+            Some(user) => vec![user],
+            None => CONFIG.flatmates.iter().map(|f| f.discord_id).collect(),
+        };
+
+        // when not everyone fits, leave out the flatmates who are all square first
+        let hidden = users.len().saturating_sub(MAX_EMBEDS);
+        if hidden > 0 {
+            users.sort_by_key(|user| ledger.net(*user).is_zero());
+        }
+
+        let mut message = CreateInteractionResponseMessage::new().embeds(
+            users
+                .into_iter()
+                .take(MAX_EMBEDS)
+                .map(|user| create_balance_embed(&ledger, user))
+                .collect(),
+        );
+        if hidden > 0 {
+            message = message.content(format!(
+                "Only {} flatmates can be shown at once, use the `flatmate` option to see the \
+                 other {}.",
+                MAX_EMBEDS, hidden
+            ));
+        }
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(message),
+        ))
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::BTreeSet;
+
+use log::error;
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    model::prelude::Attachment,
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::embed::EmbedColor,
+        database::payments::{PaymentModel, SerenityPaymentDatabase},
+    },
+    finance::{
+        reconcile::{self, OutstandingShare},
+        statement::{self, Transaction},
+    },
+    state::{AppState, CONFIG},
+};
+
+use super::{
+    command::Command,
+    pay::refresh_bill_message,
+    util::{display_name, join_lines, CommandResponse},
+};
+
+/// describe a transaction from the statement in a single line
+fn describe_transaction(transaction: &Transaction) -> String {
+    let reference = [
+        transaction.particulars.as_str(),
+        transaction.code.as_str(),
+        transaction.reference.as_str(),
+    ]
+    .iter()
+    .filter(|s| !s.is_empty())
+    .copied()
+    .collect::<Vec<_>>()
+    .join(" ");
+
+    format!(
+        "{} {} from {} `{}`",
+        transaction.date.format("%d/%m/%y"),
+        transaction.amount,
+        match transaction.payee.as_str() {
+            "" => "unknown",
+            payee => payee,
+        },
+        reference
+    )
+}
+
+/// describe a share of a bill in a single line
+fn describe_share(share: &OutstandingShare, payments: &[PaymentModel]) -> String {
+    let purpose = payments
+        .iter()
+        .find(|p| p.id == share.payment_id)
+        .map(|p| p.purpose.as_str())
+        .unwrap_or_default();
+
+    format!(
+        "{} for {} (bill #{})",
+        display_name(share.user),
+        purpose,
+        share.payment_id
+    )
+}
+
+/// Work with statements from the bank account bills are paid into
+pub enum BankCommand<'a> {
+    Import { statement: &'a Attachment },
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for BankCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let subcommand = interaction
+            .data
+            .options()
+            .into_iter()
+            .next()
+            .ok_or("No subcommand provided")?;
+
+        match (subcommand.name, subcommand.value) {
+            ("import", ResolvedValue::SubCommand(options)) => {
+                let mut statement = None;
+
+                for option in options {
+                    match (option.name, option.value) {
+                        ("statement", ResolvedValue::Attachment(val)) => statement = Some(val),
+                        (opt, val) => {
+                            return Err(format!(
+                                "unexpected option name: `{}` and value `{:?}`",
+                                opt, val
+                            ))
+                        }
+                    }
+                }
+
+                Ok(BankCommand::Import {
+                    statement: statement.ok_or("No statement provided")?,
+                })
+            }
+            (opt, _) => Err(format!("unexpected subcommand `{}`", opt)),
+        }
+    }
+}
+
+impl<'a> BankCommand<'a> {
+    /// mark every share paid by a transaction in the statement as paid, and report anything that
+    /// needs to be checked by hand
+    async fn import(
+        interaction: &CommandInteraction,
+        app_state: &AppState,
+        ctx: &Context,
+        attachment: &Attachment,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let contents = match attachment.download().await {
+            Ok(contents) => contents,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "Failed to download statement: {}",
+                    e
+                )));
+            }
+        };
+
+        let transactions = match statement::parse(&contents) {
+            Ok(transactions) => transactions,
+            Err(e) => return Err(CommandResponse::BasicFailure(e.to_string())),
+        };
+
+        // the statement is for the account of whoever is importing it, so only their bills can
+        // have been paid into it
+        let payments = match app_state
+            .get_user_payments(
+                interaction.guild_id.map(|g| g.0.into()),
+                interaction.user.id.into(),
+            )
+            .await
+        {
+            Ok(payments) => payments,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        let names: Vec<(u64, Vec<String>)> = CONFIG
+            .flatmates
+            .iter()
+            .map(|f| {
+                let mut names: Vec<String> = f
+                    .display_name
+                    .split_whitespace()
+                    .map(|n| n.to_lowercase())
+                    .collect();
+                names.push(f.name.to_lowercase());
+                (f.discord_id, names)
+            })
+            .collect();
+
+        let result = reconcile::reconcile(
+            &transactions,
+            reconcile::outstanding_shares(&payments),
+            &names,
+        );
+
+        let mut updated = BTreeSet::new();
+        for (_, share) in result.matched.iter() {
+            if let Err(e) = app_state
+                .set_payment_share_paid(share.payment_id, share.user)
+                .await
+            {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+            updated.insert(share.payment_id);
+        }
+
+        for payment_id in updated {
+            match app_state.get_payment_by_id(payment_id).await {
+                Ok(Some(payment)) => {
+                    if let Err(e) = refresh_bill_message(ctx, &payment).await {
+                        error!("failed to update bill message: {}", e);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => error!("error communicating with database: {}", e),
+            }
+        }
+
+        let matched = if result.matched.is_empty() {
+            String::from("Nothing in the statement paid an unpaid share.")
+        } else {
+            join_lines(
+                result
+                    .matched
+                    .iter()
+                    .map(|(transaction, share)| {
+                        format!(
+                            "{} → {}",
+                            describe_transaction(transaction),
+                            describe_share(share, &payments)
+                        )
+                    })
+                    .collect(),
+            )
+        };
+        let mut embeds = vec![CreateEmbed::new()
+            .title(format!(
+                "Imported {} transactions, {} shares marked paid",
+                transactions.len(),
+                result.matched.len()
+            ))
+            .description(matched)
+            .color(EmbedColor::Green as u32)];
+
+        let mut review: Vec<String> = result
+            .ambiguous
+            .iter()
+            .map(|(transaction, shares)| {
+                format!(
+                    "{} could be {}",
+                    describe_transaction(transaction),
+                    shares
+                        .iter()
+                        .map(|share| describe_share(share, &payments))
+                        .collect::<Vec<_>>()
+                        .join(", or ")
+                )
+            })
+            .collect();
+        review.extend(result.unmatched.iter().map(|transaction| {
+            format!(
+                "{} doesn't match an unpaid share",
+                describe_transaction(transaction)
+            )
+        }));
+
+        if !review.is_empty() {
+            embeds.push(
+                CreateEmbed::new()
+                    .title("Needs review")
+                    .description(join_lines(review))
+                    .color(EmbedColor::Orange as u32),
+            );
+        }
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().embeds(embeds),
+            ),
+        ))
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for BankCommand<'a> {
+    fn name() -> &'static str {
+        "bank"
+    }
+
+    fn description() -> &'static str {
+        "Reconcile bills against your bank statements"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "import",
+                "Mark shares of your bills as paid from a CSV statement",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Attachment,
+                    "statement",
+                    "A CSV statement exported from your bank",
+                )
+                .required(true),
+            ),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        match self {
+            BankCommand::Import { statement } => {
+                Self::import(interaction, app_state, ctx, statement).await
+            }
+        }
+    }
+}
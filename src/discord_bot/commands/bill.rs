@@ -0,0 +1,1134 @@
+use chrono::NaiveDate;
+use log::error;
+use serenity::{
+    all::{AutocompleteOption, CommandInteraction, CommandOptionType, ResolvedValue, UserId},
+    async_trait,
+    builder::{
+        AutocompleteChoice, CreateAutocompleteResponse, CreateCommand, CreateCommandOption,
+        CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::embed::EmbedColor,
+        database::{
+            payments::{PaymentEdit, PaymentModel, SerenityPaymentDatabase},
+            recurring::{NewRecurringBill, SerenityRecurringBillDatabase},
+        },
+        scheduler::Schedule,
+    },
+    finance::{money::Money, split},
+    state::{AppState, CONFIG},
+};
+
+use super::{
+    command::{paginate, AutocompleteCommand, Command, Page, PaginatedResponse},
+    pay::{handle_autocomplete_for_pay, payout_account, refresh_bill_message, share_references},
+    util::{display_name, CommandResponse},
+};
+
+/// the shares of a stored bill
+fn payment_shares(payment: &PaymentModel) -> Vec<(u64, Money)> {
+    payment
+        .individual_users
+        .iter()
+        .zip(payment.individual_amounts.iter())
+        .map(|(user, amount)| (*user as u64, Money::from_cents(*amount)))
+        .collect()
+}
+
+/// let a flatmate who has already paid a bill know that their share of it has changed
+async fn notify_share_changed(
+    ctx: &Context,
+    payment: &PaymentModel,
+    user: u64,
+    old: Money,
+    new: Money,
+) -> Result<(), serenity::Error> {
+    let creator = display_name(payment.created_by as u64);
+    let difference = if new > old {
+        format!("You owe {} another {}.", creator, new - old)
+    } else {
+        format!("{} owes you {} back.", creator, old - new)
+    };
+
+    UserId::new(user)
+        .direct_message(
+            ctx,
+            CreateMessage::new().embed(
+                CreateEmbed::new()
+                    .title("A bill you paid has changed")
+                    .description(format!(
+                        "{} changed the bill for {}. You paid {}, but your share is now {}. {}",
+                        creator, payment.purpose, old, new, difference
+                    ))
+                    .color(EmbedColor::Orange as u32),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// the number of bills shown on each page of the history
+const HISTORY_PAGE_SIZE: u64 = 10;
+
+/// discord allows at most this many fields on an embed
+const MAX_FIELDS: usize = 25;
+
+/// Every bill posted in a guild, a page at a time
+pub struct BillHistory {
+    guild_id: Option<u64>,
+}
+
+#[async_trait]
+impl PaginatedResponse for BillHistory {
+    fn key() -> &'static str {
+        "bills"
+    }
+
+    fn to_state(&self) -> String {
+        self.guild_id.map(|g| g.to_string()).unwrap_or_default()
+    }
+
+    fn from_state(state: &str) -> Option<Self> {
+        match state {
+            "" => Some(BillHistory { guild_id: None }),
+            guild_id => guild_id
+                .parse()
+                .ok()
+                .map(|g| BillHistory { guild_id: Some(g) }),
+        }
+    }
+
+    async fn get_page(&self, page: u64, app_state: &AppState) -> Result<Page, CommandResponse> {
+        let (payments, count) = match app_state
+            .get_guild_payment_history_page(self.guild_id, page, HISTORY_PAGE_SIZE)
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        let description = if payments.is_empty() {
+            String::from("No bills have been posted yet.")
+        } else {
+            payments
+                .iter()
+                .map(|payment| {
+                    let unpaid = payment
+                        .individual_users
+                        .iter()
+                        .filter(|user| {
+                            **user != payment.created_by && !payment.paid_users.contains(user)
+                        })
+                        .count();
+                    let line = format!(
+                        "`#{}` {} {} for {} by {}",
+                        payment.id,
+                        payment.created_at.format("%d/%m/%y"),
+                        Money::from_cents(payment.total_amount),
+                        payment.purpose,
+                        display_name(payment.created_by as u64)
+                    );
+
+                    match (payment.voided_at, unpaid) {
+                        (Some(_), _) => format!("~~{}~~ (voided)", line),
+                        (None, 0) => format!("{} ✅", line),
+                        (None, unpaid) => format!("{} ({} unpaid)", line, unpaid),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        Ok(Page {
+            embed: CreateEmbed::new()
+                .title("Bill history")
+                .description(description)
+                .color(EmbedColor::Green as u32),
+            count,
+        })
+    }
+}
+
+/// Manage bills after they have been created, and bills which are posted on a schedule
+pub enum BillCommand<'a> {
+    RecurringAdd {
+        purpose: &'a str,
+        amount: f64,
+        frequency: &'a str,
+        day: Option<i64>,
+        start: Option<&'a str>,
+        account: Option<&'a str>,
+    },
+    RecurringList,
+    RecurringRemove {
+        id: i64,
+    },
+    Edit {
+        id: i64,
+        purpose: Option<&'a str>,
+        account: Option<&'a str>,
+        /// new amounts for flatmates, keyed by their flatmate name
+        amounts: Vec<(&'a str, f64)>,
+    },
+    Void {
+        id: i64,
+    },
+    Reopen {
+        id: i64,
+    },
+    Revisions {
+        id: i64,
+    },
+    History,
+}
+
+/// find the bill id given to a subcommand which only takes a bill
+fn bill_option(options: Vec<serenity::all::ResolvedOption>) -> Result<i64, String> {
+    match options.into_iter().next().map(|o| (o.name, o.value)) {
+        Some(("bill", ResolvedValue::Integer(id))) => Ok(id),
+        _ => Err(String::from("bill is required")),
+    }
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for BillCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let group = interaction
+            .data
+            .options()
+            .into_iter()
+            .next()
+            .ok_or("No subcommand provided")?;
+
+        let subcommand = match (group.name, group.value) {
+            ("recurring", ResolvedValue::SubCommandGroup(subcommands)) => subcommands
+                .into_iter()
+                .next()
+                .ok_or("No recurring subcommand provided")?,
+            ("edit", ResolvedValue::SubCommand(options)) => {
+                let mut id = None;
+                let mut purpose = None;
+                let mut account = None;
+                let mut amounts = Vec::new();
+
+                for option in options {
+                    match (option.name, option.value) {
+                        ("bill", ResolvedValue::Integer(val)) => id = Some(val),
+                        ("purpose", ResolvedValue::String(val)) => purpose = Some(val),
+                        ("account", ResolvedValue::String(val)) => account = Some(val),
+                        (name, ResolvedValue::Number(val)) => amounts.push((name, val)),
+                        (opt, val) => {
+                            return Err(format!(
+                                "unexpected option name: `{}` and value `{:?}`",
+                                opt, val
+                            ))
+                        }
+                    }
+                }
+
+                return Ok(BillCommand::Edit {
+                    id: id.ok_or("bill is required")?,
+                    purpose,
+                    account,
+                    amounts,
+                });
+            }
+            ("void", ResolvedValue::SubCommand(options)) => {
+                return Ok(BillCommand::Void {
+                    id: bill_option(options)?,
+                })
+            }
+            ("reopen", ResolvedValue::SubCommand(options)) => {
+                return Ok(BillCommand::Reopen {
+                    id: bill_option(options)?,
+                })
+            }
+            ("revisions", ResolvedValue::SubCommand(options)) => {
+                return Ok(BillCommand::Revisions {
+                    id: bill_option(options)?,
+                })
+            }
+            ("history", ResolvedValue::SubCommand(_)) => return Ok(BillCommand::History),
+            (opt, _) => return Err(format!("unexpected subcommand `{}`", opt)),
+        };
+
+        match (subcommand.name, subcommand.value) {
+            ("add", ResolvedValue::SubCommand(options)) => {
+                let mut purpose = None;
+                let mut amount = None;
+                let mut frequency = None;
+                let mut day = None;
+                let mut start = None;
+                let mut account = None;
+
+                for option in options {
+                    match (option.name, option.value) {
+                        ("purpose", ResolvedValue::String(val)) => purpose = Some(val),
+                        ("amount", ResolvedValue::Number(val)) => amount = Some(val),
+                        ("frequency", ResolvedValue::String(val)) => frequency = Some(val),
+                        ("day", ResolvedValue::Integer(val)) => day = Some(val),
+                        ("start", ResolvedValue::String(val)) => start = Some(val),
+                        ("account", ResolvedValue::String(val)) => account = Some(val),
+                        (opt, val) => {
+                            return Err(format!(
+                                "unexpected option name: `{}` and value `{:?}`",
+                                opt, val
+                            ))
+                        }
+                    }
+                }
+
+                Ok(BillCommand::RecurringAdd {
+                    purpose: purpose.ok_or("purpose is required")?,
+                    amount: amount.ok_or("amount is required")?,
+                    frequency: frequency.ok_or("frequency is required")?,
+                    day,
+                    start,
+                    account,
+                })
+            }
+            ("list", ResolvedValue::SubCommand(_)) => Ok(BillCommand::RecurringList),
+            ("remove", ResolvedValue::SubCommand(options)) => Ok(BillCommand::RecurringRemove {
+                id: bill_option(options)?,
+            }),
+            (opt, _) => Err(format!("unexpected recurring subcommand `{}`", opt)),
+        }
+    }
+}
+
+impl<'a> BillCommand<'a> {
+    /// load a bill which the user running the command created, in this guild
+    async fn own_bill(
+        interaction: &CommandInteraction,
+        app_state: &AppState,
+        id: i64,
+    ) -> Result<PaymentModel, CommandResponse> {
+        let guild_id: Option<i64> = interaction.guild_id.map(|g| u64::from(g.0) as i64);
+        let user: u64 = interaction.user.id.into();
+
+        let payment = match app_state.get_payment_by_id(id as i32).await {
+            Ok(Some(payment)) if payment.guild_id == guild_id => payment,
+            Ok(_) => {
+                return Err(CommandResponse::BasicFailure(format!(
+                    "No bill #{} was found.",
+                    id
+                )));
+            }
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if payment.created_by as u64 != user {
+            return Err(CommandResponse::BasicFailure(format!(
+                "Only {} can change this bill.",
+                display_name(payment.created_by as u64)
+            )));
+        }
+
+        Ok(payment)
+    }
+
+    async fn edit(
+        interaction: &CommandInteraction,
+        app_state: &AppState,
+        ctx: &Context,
+        id: i64,
+        purpose: Option<&str>,
+        account: Option<&str>,
+        amounts: Vec<(&str, f64)>,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let payment = Self::own_bill(interaction, app_state, id).await?;
+
+        if payment.voided_at.is_some() {
+            return Err(CommandResponse::BasicFailure(format!(
+                "Bill #{} has been voided, reopen it before making changes.",
+                id
+            )));
+        }
+
+        if purpose.is_none() && account.is_none() && amounts.is_empty() {
+            return Err(CommandResponse::BasicFailure(String::from(
+                "Nothing to change, give a new purpose, account or amount.",
+            )));
+        }
+        let account = account
+            .map(|account| payout_account(interaction.user.id.into(), Some(account)))
+            .transpose()
+            .map_err(CommandResponse::BasicFailure)?;
+
+        let old_shares = payment_shares(&payment);
+        let shares = if amounts.is_empty() {
+            None
+        } else {
+            let mut shares = old_shares.clone();
+            for (name, amount) in amounts {
+                let flatmate = match CONFIG
+                    .flatmates
+                    .iter()
+                    .find(|f| f.name.to_ascii_lowercase() == name)
+                {
+                    Some(flatmate) => flatmate,
+                    None => {
+                        return Err(CommandResponse::BasicFailure(format!(
+                            "There's no flatmate called {}.",
+                            name
+                        )));
+                    }
+                };
+
+                let amount = Money::from_dollars(amount);
+                if amount < Money::ZERO {
+                    return Err(CommandResponse::BasicFailure(String::from(
+                        "An amount to pay can't be negative.",
+                    )));
+                }
+                match shares.iter().position(|(u, _)| *u == flatmate.discord_id) {
+                    Some(i) => shares[i].1 = amount,
+                    None => shares.push((flatmate.discord_id, amount)),
+                }
+            }
+
+            // an amount of zero takes a flatmate out of the bill
+            shares.retain(|(_, amount)| !amount.is_zero());
+            if shares.is_empty() {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "A bill needs at least one share, use `/bill void` to cancel it instead.",
+                )));
+            }
+            Some(shares)
+        };
+
+        // flatmates keep the reference they were given, anyone added to the bill gets a new one
+        let references = match &shares {
+            Some(shares) => {
+                let existing = |user: u64| {
+                    payment
+                        .individual_users
+                        .iter()
+                        .position(|u| *u as u64 == user)
+                        .and_then(|i| payment.share_references.get(i))
+                        .cloned()
+                };
+                let added: Vec<u64> = shares
+                    .iter()
+                    .map(|(user, _)| *user)
+                    .filter(|user| existing(*user).is_none())
+                    .collect();
+                let mut generated = match share_references(
+                    app_state,
+                    payment.guild_id.map(|g| g as u64),
+                    &payment.purpose,
+                    payment.created_at.date(),
+                    &added,
+                )
+                .await
+                {
+                    Ok(references) => references.into_iter(),
+                    Err(e) => {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "error communicating with database: {}",
+                            e
+                        )));
+                    }
+                };
+
+                Some(
+                    shares
+                        .iter()
+                        .map(|(user, _)| {
+                            existing(*user)
+                                .or_else(|| generated.next())
+                                .unwrap_or_default()
+                        })
+                        .collect(),
+                )
+            }
+            None => None,
+        };
+
+        let payment = match app_state
+            .edit_payment(
+                payment.id,
+                interaction.user.id.into(),
+                PaymentEdit {
+                    purpose,
+                    account: account.as_deref(),
+                    shares,
+                    references,
+                },
+            )
+            .await
+        {
+            Ok(payment) => payment,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if let Err(e) = refresh_bill_message(ctx, &payment).await {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        // anyone who has already paid needs to know their amount has changed
+        let new_shares = payment_shares(&payment);
+        let mut notified = Vec::new();
+        for (user, old) in old_shares.iter() {
+            if !payment.paid_users.contains(&(*user as i64)) {
+                continue;
+            }
+
+            let new = new_shares
+                .iter()
+                .find(|(u, _)| u == user)
+                .map(|(_, amount)| *amount)
+                .unwrap_or_default();
+            if new == *old {
+                continue;
+            }
+
+            match notify_share_changed(ctx, &payment, *user, *old, new).await {
+                Ok(()) => notified.push(display_name(*user)),
+                Err(e) => error!("failed to tell {} about bill {}: {}", user, payment.id, e),
+            }
+        }
+
+        Ok(CommandResponse::BasicSuccess(if notified.is_empty() {
+            format!("Bill #{} updated.", payment.id)
+        } else {
+            format!(
+                "Bill #{} updated, and let {} know their share has changed.",
+                payment.id,
+                notified.join(", ")
+            )
+        }))
+    }
+
+    async fn set_voided(
+        interaction: &CommandInteraction,
+        app_state: &AppState,
+        ctx: &Context,
+        id: i64,
+        voided: bool,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let payment = Self::own_bill(interaction, app_state, id).await?;
+
+        if payment.voided_at.is_some() == voided {
+            return Err(CommandResponse::BasicFailure(format!(
+                "Bill #{} is {}.",
+                id,
+                if voided {
+                    "already voided"
+                } else {
+                    "not voided"
+                }
+            )));
+        }
+
+        let payment = match app_state
+            .set_payment_voided(payment.id, interaction.user.id.into(), voided)
+            .await
+        {
+            Ok(payment) => payment,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if let Err(e) = refresh_bill_message(ctx, &payment).await {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::BasicSuccess(if voided {
+            format!(
+                "Bill #{} for {} voided, nobody needs to pay it.",
+                payment.id, payment.purpose
+            )
+        } else {
+            format!("Bill #{} for {} reopened.", payment.id, payment.purpose)
+        }))
+    }
+
+    async fn revisions(
+        interaction: &CommandInteraction,
+        app_state: &AppState,
+        id: i64,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let payment = Self::own_bill(interaction, app_state, id).await?;
+
+        let revisions = match app_state.get_payment_revisions(payment.id).await {
+            Ok(revisions) => revisions,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if revisions.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(format!(
+                "Bill #{} hasn't been changed since it was posted.",
+                payment.id
+            )));
+        }
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().embed(
+                    CreateEmbed::new()
+                        .title(format!("Changes to bill #{}", payment.id))
+                        .description("Each entry shows the bill as it was before the change.")
+                        .color(EmbedColor::Orange as u32)
+                        .fields(revisions.iter().take(MAX_FIELDS).map(|revision| {
+                            (
+                                format!(
+                                    "{} by {} on {}",
+                                    match revision.action.as_str() {
+                                        "void" => "Voided",
+                                        "reopen" => "Reopened",
+                                        _ => "Edited",
+                                    },
+                                    display_name(revision.revised_by as u64),
+                                    revision.revised_at.format("%d/%m/%y at %I:%M%P")
+                                ),
+                                format!(
+                                    "{} totalling {} into `{}`: {}",
+                                    revision.purpose,
+                                    Money::from_cents(revision.total_amount),
+                                    revision.account,
+                                    revision
+                                        .individual_users
+                                        .iter()
+                                        .zip(revision.individual_amounts.iter())
+                                        .map(|(user, amount)| format!(
+                                            "{} {}",
+                                            display_name(*user as u64),
+                                            Money::from_cents(*amount)
+                                        ))
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                ),
+                                false,
+                            )
+                        })),
+                ),
+            ),
+        ))
+    }
+
+    async fn recurring_add(
+        interaction: &CommandInteraction,
+        app_state: &AppState,
+        bill: NewRecurringBill<'_>,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let purpose = bill.purpose.to_string();
+        let total_amount = bill.total_amount;
+        let schedule = Schedule::from_parts(bill.frequency, bill.day_of_month);
+        let next_run = bill.next_run;
+
+        let bill = match app_state
+            .add_recurring_bill(
+                interaction.user.id.into(),
+                interaction.channel_id.into(),
+                interaction.guild_id.map(|g| g.0.into()),
+                bill,
+            )
+            .await
+        {
+            Ok(bill) => bill,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().embed(
+                    CreateEmbed::new()
+                        .title("Recurring bill added")
+                        .description(format!(
+                            "Bill #{} for {} totalling {} will be posted in this channel {}, starting {}",
+                            bill.id,
+                            purpose,
+                            total_amount,
+                            schedule.map(|s| s.to_string()).unwrap_or_default(),
+                            next_run.format("%d/%m/%y at %I:%M%P"),
+                        ))
+                        .color(EmbedColor::Green as u32),
+                ),
+            ),
+        ))
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for BillCommand<'a> {
+    fn name() -> &'static str {
+        "bill"
+    }
+
+    fn description() -> &'static str {
+        "Manage bills for the flat"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        let cmd = cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommandGroup,
+                "recurring",
+                "Bills which are posted automatically on a schedule",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "add",
+                    "Post a bill, split between all flatmates by their weights, on a schedule",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "purpose",
+                        "What is this bill for?",
+                    )
+                    .required(true)
+                    .set_autocomplete(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Number,
+                        "amount",
+                        "The total amount of the bill each time it is posted",
+                    )
+                    .min_number_value(0.01)
+                    .required(true),
+                )
+                .add_sub_option({
+                    let mut option = CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "frequency",
+                        "How often the bill should be posted",
+                    )
+                    .required(true);
+
+                    for frequency in Schedule::FREQUENCIES {
+                        option = option.add_string_choice(*frequency, *frequency);
+                    }
+                    option
+                })
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "day",
+                        "The day of the month to post monthly bills on",
+                    )
+                    .min_int_value(1)
+                    .max_int_value(28)
+                    .required(false),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "start",
+                        "The first date to post the bill on, as YYYY-MM-DD. Defaults to today.",
+                    )
+                    .required(false),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "account",
+                        "The account number to pay into, defaults to yours if it is set up, or the head tenant's",
+                    )
+                    .required(false),
+                ),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "list",
+                "List the bills which are posted on a schedule",
+            ))
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "remove",
+                    "Stop posting a recurring bill",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "bill",
+                        "The recurring bill to remove",
+                    )
+                    .required(true)
+                    .set_autocomplete(true),
+                ),
+            ),
+        );
+
+        let bill_option = |description: &str| {
+            CreateCommandOption::new(CommandOptionType::Integer, "bill", description)
+                .required(true)
+                .set_autocomplete(true)
+        };
+
+        let mut edit = CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "edit",
+            "Change a bill you posted, anything not given is left as it is",
+        )
+        .add_sub_option(bill_option("The bill to change"))
+        .add_sub_option(
+            CreateCommandOption::new(CommandOptionType::String, "purpose", "What the bill is for")
+                .required(false),
+        )
+        .add_sub_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "account",
+                "The account number to pay into",
+            )
+            .required(false),
+        );
+        for flatmate in CONFIG.flatmates.iter() {
+            edit = edit.add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Number,
+                    flatmate.name.to_ascii_lowercase(),
+                    format!(
+                        "The new amount for {} to pay, 0 to take them off the bill",
+                        flatmate.name
+                    ),
+                )
+                .min_number_value(0.0)
+                .required(false),
+            );
+        }
+
+        cmd.add_option(edit)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "void",
+                    "Void a bill you posted, so nobody needs to pay it",
+                )
+                .add_sub_option(bill_option("The bill to void")),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "reopen",
+                    "Reopen a bill you voided",
+                )
+                .add_sub_option(bill_option("The bill to reopen")),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "revisions",
+                    "Show every change made to a bill you posted",
+                )
+                .add_sub_option(bill_option("The bill to show changes for")),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "history",
+                "Show every bill posted in this server, newest first",
+            ))
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: Option<u64> = interaction.guild_id.map(|g| g.0.into());
+
+        match self {
+            BillCommand::Edit {
+                id,
+                purpose,
+                account,
+                amounts,
+            } => Self::edit(interaction, app_state, ctx, id, purpose, account, amounts).await,
+            BillCommand::Void { id } => {
+                Self::set_voided(interaction, app_state, ctx, id, true).await
+            }
+            BillCommand::Reopen { id } => {
+                Self::set_voided(interaction, app_state, ctx, id, false).await
+            }
+            BillCommand::Revisions { id } => Self::revisions(interaction, app_state, id).await,
+            BillCommand::History => {
+                paginate(BillHistory { guild_id }, interaction, app_state).await
+            }
+            BillCommand::RecurringAdd {
+                purpose,
+                amount,
+                frequency,
+                day,
+                start,
+                account,
+            } => {
+                let schedule = match Schedule::from_parts(frequency, day.map(|d| d as i32)) {
+                    Some(schedule) => schedule,
+                    None => {
+                        return Err(CommandResponse::BasicFailure(String::from(
+                            "Monthly bills need a day of the month between 1 and 28.",
+                        )));
+                    }
+                };
+
+                let now = chrono::offset::Local::now().naive_local();
+                let start = match start {
+                    Some(start) => match NaiveDate::parse_from_str(start, "%Y-%m-%d") {
+                        Ok(start) => start,
+                        Err(_) => {
+                            return Err(CommandResponse::BasicFailure(format!(
+                                "`{}` isn't a valid date, please use YYYY-MM-DD.",
+                                start
+                            )));
+                        }
+                    },
+                    None => now.date(),
+                };
+
+                let mut next_run = schedule.first_run(start);
+                while next_run <= now {
+                    next_run = schedule.next_after(next_run);
+                }
+
+                let total_amount = Money::from_dollars(amount);
+                if total_amount <= Money::ZERO {
+                    return Err(CommandResponse::BasicFailure(String::from(
+                        "The amount of the bill has to be more than $0.00.",
+                    )));
+                }
+                let weights: Vec<(u64, f64)> = CONFIG
+                    .flatmates
+                    .iter()
+                    .map(|flatmate| (flatmate.discord_id, flatmate.weight))
+                    .collect();
+                let shares =
+                    match split::by_weight(total_amount, &weights, interaction.user.id.into()) {
+                        Ok(shares) => shares,
+                        Err(e) => return Err(CommandResponse::BasicFailure(e.to_string())),
+                    };
+                let account = payout_account(interaction.user.id.into(), account)
+                    .map_err(CommandResponse::BasicFailure)?;
+
+                Self::recurring_add(
+                    interaction,
+                    app_state,
+                    NewRecurringBill {
+                        purpose,
+                        account: &account,
+                        total_amount,
+                        shares,
+                        frequency: schedule.frequency(),
+                        day_of_month: schedule.day_of_month(),
+                        next_run,
+                    },
+                )
+                .await
+            }
+            BillCommand::RecurringList => {
+                let bills = match app_state.get_guild_recurring_bills(guild_id).await {
+                    Ok(bills) => bills,
+                    Err(e) => {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "error communicating with database: {}",
+                            e
+                        )));
+                    }
+                };
+
+                if bills.is_empty() {
+                    return Ok(CommandResponse::BasicSuccess(String::from(
+                        "No recurring bills have been set up.",
+                    )));
+                }
+
+                let hidden = bills.len().saturating_sub(MAX_FIELDS);
+                let mut embed = CreateEmbed::new()
+                    .title("Recurring bills")
+                    .color(EmbedColor::Orange as u32);
+                if hidden > 0 {
+                    embed = embed.description(format!(
+                        "Only the first {} of the {} recurring bills are shown here.",
+                        MAX_FIELDS,
+                        bills.len()
+                    ));
+                }
+
+                Ok(CommandResponse::ComplexSuccess(
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new().embed(embed.fields(
+                            bills.iter().take(MAX_FIELDS).map(|bill| {
+                                (
+                                    format!(
+                                        "#{} {} totalling {}",
+                                        bill.id,
+                                        bill.purpose,
+                                        Money::from_cents(bill.total_amount)
+                                    ),
+                                    format!(
+                                        "Posted {}, next on {}, to be paid into `{}`",
+                                        Schedule::from_parts(&bill.frequency, bill.day_of_month)
+                                            .map(|s| s.to_string())
+                                            .unwrap_or_else(|| bill.frequency.clone()),
+                                        bill.next_run.format("%d/%m/%y"),
+                                        bill.account
+                                    ),
+                                    false,
+                                )
+                            }),
+                        )),
+                    ),
+                ))
+            }
+            BillCommand::RecurringRemove { id } => {
+                match app_state.remove_recurring_bill(guild_id, id as i32).await {
+                    Ok(true) => Ok(CommandResponse::BasicSuccess(format!(
+                        "Recurring bill #{} removed, it will no longer be posted.",
+                        id
+                    ))),
+                    Ok(false) => Err(CommandResponse::BasicFailure(format!(
+                        "No recurring bill #{} was found.",
+                        id
+                    ))),
+                    Err(e) => Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    ))),
+                }
+            }
+        }
+    }
+}
+
+impl<'a> BillCommand<'a> {
+    /// suggest bills posted by the user running the command, which the subcommand can act on
+    async fn autocomplete_own_bill(
+        interaction: &CommandInteraction,
+        app_state: &AppState,
+        subcommand: Option<&str>,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        let payments = match app_state
+            .get_user_payments(
+                interaction.guild_id.map(|g| g.0.into()),
+                interaction.user.id.into(),
+            )
+            .await
+        {
+            Ok(payments) => payments,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        Ok(CreateAutocompleteResponse::new().set_choices(
+            payments
+                .into_iter()
+                .filter(|payment| match subcommand {
+                    Some("reopen") => payment.voided_at.is_some(),
+                    Some("revisions") => true,
+                    _ => payment.voided_at.is_none(),
+                })
+                .take(25)
+                .map(|payment| AutocompleteChoice {
+                    name: format!(
+                        "#{} {} ({}) on {}",
+                        payment.id,
+                        payment.purpose,
+                        Money::from_cents(payment.total_amount),
+                        payment.created_at.format("%d/%m/%y")
+                    ),
+                    value: serde_json::Value::from(payment.id),
+                })
+                .collect(),
+        ))
+    }
+}
+
+#[async_trait]
+impl<'a> AutocompleteCommand<'a> for BillCommand<'a> {
+    async fn autocomplete<'c>(
+        interaction: &'c CommandInteraction,
+        autocomplete: &'c AutocompleteOption,
+        app_state: &'c AppState,
+        _: &'c Context,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        match autocomplete.name {
+            "purpose" => handle_autocomplete_for_pay(interaction, autocomplete).await,
+            "bill" => {
+                let guild_id: Option<u64> = interaction.guild_id.map(|g| g.0.into());
+                let subcommand = interaction.data.options().first().map(|o| o.name);
+                if subcommand != Some("recurring") {
+                    return Self::autocomplete_own_bill(interaction, app_state, subcommand).await;
+                }
+
+                let bills = match app_state.get_guild_recurring_bills(guild_id).await {
+                    Ok(bills) => bills,
+                    Err(e) => {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "error communicating with database: {}",
+                            e
+                        )));
+                    }
+                };
+
+                Ok(CreateAutocompleteResponse::new().set_choices(
+                    bills
+                        .into_iter()
+                        .take(25)
+                        .map(|bill| AutocompleteChoice {
+                            name: format!(
+                                "#{} {} ({})",
+                                bill.id,
+                                bill.purpose,
+                                Money::from_cents(bill.total_amount)
+                            ),
+                            value: serde_json::Value::from(bill.id),
+                        })
+                        .collect(),
+                ))
+            }
+            _ => Err(CommandResponse::InternalFailure(
+                "Invalid autocomplete option".to_string(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,564 @@
+use std::collections::BTreeMap;
+
+use log::error;
+use serenity::{
+    all::{ChannelId, ComponentInteraction, ComponentInteractionDataKind, MessageId},
+    async_trait,
+    builder::{
+        CreateActionRow, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+        CreateMessage, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, EditMessage,
+    },
+    http::HttpError,
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::embed::EmbedColor,
+        database::shopping::{SerenityShoppingDatabase, ShoppingListItemModel, ShoppingListModel},
+    },
+    state::AppState,
+};
+
+use super::{command::InteractionCommand, util::CommandResponse};
+
+/// the start of the custom id of a board's select menus, followed by the id of its list and, for
+/// every menu but the one ticking items off, what the menu does
+const BOARD_PREFIX: &str = "board:";
+
+/// a select menu can only offer so many items at once
+const MAX_OPTIONS: usize = 25;
+
+/// the longest the value of an embed field can be
+const MAX_FIELD_LENGTH: usize = 1024;
+
+/// the most fields an embed can have
+const MAX_FIELDS: usize = 25;
+
+/// the longest an embed can be, counting its title, description and every field
+const MAX_EMBED_LENGTH: usize = 6000;
+
+/// room kept for the field saying how many stores didn't fit on the board
+const OVERFLOW_FIELD_LENGTH: usize = 50;
+
+/// the error code discord gives for a message which doesn't exist, such as a deleted board
+const UNKNOWN_MESSAGE: isize = 10008;
+
+/// how an item is listed on a board
+fn item_line(item: &ShoppingListItemModel) -> String {
+    format!(
+        "x{} {}{}{}",
+        item.quantity,
+        item.item,
+        if item.personal { " (personal)" } else { "" },
+        match &item.notes {
+            Some(notes) => format!(" - {}", notes),
+            None => String::new(),
+        }
+    )
+}
+
+/// A list as its board shows it
+struct Board {
+    list: ShoppingListModel,
+    /// the items yet to be bought, oldest first
+    items: Vec<ShoppingListItemModel>,
+    /// items bought or taken off the list which could be put back on it, newest first
+    finished: Vec<ShoppingListItemModel>,
+}
+
+/// the options of a select menu choosing between items
+fn item_options<'a>(
+    items: impl Iterator<Item = &'a ShoppingListItemModel>,
+) -> Vec<CreateSelectMenuOption> {
+    items
+        .take(MAX_OPTIONS)
+        .map(|item| {
+            let label: String = format!("x{} {}", item.quantity, item.item)
+                .chars()
+                .take(100)
+                .collect();
+            let option = CreateSelectMenuOption::new(label, item.id.to_string());
+            match &item.store {
+                Some(store) => option.description(store.as_str()),
+                None => option,
+            }
+        })
+        .collect()
+}
+
+/// a select menu for a board, doing `action` to whichever items are chosen
+fn item_menu(
+    list_id: i32,
+    action: Option<&str>,
+    placeholder: &str,
+    options: Vec<CreateSelectMenuOption>,
+) -> CreateActionRow {
+    let custom_id = match action {
+        Some(action) => format!("{}{}:{}", BOARD_PREFIX, list_id, action),
+        None => format!("{}{}", BOARD_PREFIX, list_id),
+    };
+    let count = options.len() as u8;
+
+    CreateActionRow::SelectMenu(
+        CreateSelectMenu::new(custom_id, CreateSelectMenuKind::String { options })
+            .placeholder(placeholder)
+            .min_values(1)
+            .max_values(count),
+    )
+}
+
+/// join the items for a store into a field, cutting the list short if it won't fit
+fn store_field(lines: &[String]) -> String {
+    let mut field = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let more = format!("\n...and {} more", lines.len() - i);
+        if field.len() + line.len() + more.len() + 1 > MAX_FIELD_LENGTH {
+            field.push_str(&more);
+            break;
+        }
+
+        if !field.is_empty() {
+            field.push('\n');
+        }
+        field.push_str(line);
+    }
+    field
+}
+
+/// build the board for a list out of the items which are yet to be bought, grouped by the store
+/// they're to be bought from
+fn create_board(board: &Board) -> (CreateEmbed, Vec<CreateActionRow>) {
+    let Board {
+        list,
+        items,
+        finished,
+    } = board;
+    let title = format!("The {} list", list.name);
+    let embed = CreateEmbed::new().title(title.as_str());
+
+    if list.archived_at.is_some() {
+        return (
+            embed
+                .description("This list has been archived.")
+                .color(EmbedColor::Grey as u32),
+            vec![],
+        );
+    }
+
+    // anything which has come off the list can be put back on, unless it's already there again
+    let mut offered: Vec<&ShoppingListItemModel> = items.iter().collect();
+    let readd: Vec<_> = item_options(finished.iter().filter(|item| {
+        let same = |other: &&ShoppingListItemModel| {
+            other.item.eq_ignore_ascii_case(&item.item) && other.store == item.store
+        };
+        if offered.iter().any(same) {
+            return false;
+        }
+        offered.push(*item);
+        true
+    }));
+    let readd_menu = (!readd.is_empty()).then(|| {
+        item_menu(
+            list.id,
+            Some("readd"),
+            "Put something back on the list",
+            readd,
+        )
+    });
+
+    if items.is_empty() {
+        return (
+            embed
+                .description("Everything on the list has been bought.")
+                .color(EmbedColor::Green as u32),
+            readd_menu.into_iter().collect(),
+        );
+    }
+
+    // named stores first, then everything which can be bought anywhere
+    let mut stores: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut anywhere = vec![];
+    for item in items {
+        match &item.store {
+            Some(store) => stores.entry(store).or_default().push(item_line(item)),
+            None => anywhere.push(item_line(item)),
+        }
+    }
+
+    let mut fields: Vec<(&str, String)> = stores
+        .iter()
+        .map(|(store, lines)| (*store, store_field(lines)))
+        .collect();
+    if !anywhere.is_empty() {
+        fields.push(("Anywhere", store_field(&anywhere)));
+    }
+
+    let description = "Tick items off below once they've been bought, or take them off the list.";
+    let mut length = title.len() + description.len();
+    let mut embed = embed.description(description).color(EmbedColor::Red as u32);
+    for (i, (name, value)) in fields.iter().enumerate() {
+        // unless this is the last field, keep room for one saying what didn't fit
+        let (max_fields, max_length) = if i + 1 == fields.len() {
+            (MAX_FIELDS, MAX_EMBED_LENGTH)
+        } else {
+            (MAX_FIELDS - 1, MAX_EMBED_LENGTH - OVERFLOW_FIELD_LENGTH)
+        };
+        if i >= max_fields || length + name.len() + value.len() > max_length {
+            embed = embed.field(
+                "More stores",
+                format!("...and {} more", fields.len() - i),
+                false,
+            );
+            break;
+        }
+
+        length += name.len() + value.len();
+        embed = embed.field(*name, value, false);
+    }
+
+    let mut components = vec![
+        item_menu(
+            list.id,
+            None,
+            "Tick off what you've bought",
+            item_options(items.iter()),
+        ),
+        item_menu(
+            list.id,
+            Some("remove"),
+            "Take something off the list",
+            item_options(items.iter()),
+        ),
+    ];
+    components.extend(readd_menu);
+
+    (embed, components)
+}
+
+/// load a list along with the items on it which are yet to be bought, and those which could be
+/// put back on it
+async fn load_board(app_state: &AppState, list_id: i32) -> Result<Board, CommandResponse> {
+    let list = match app_state.get_shopping_list_by_id(list_id).await {
+        Ok(Some(list)) => list,
+        Ok(None) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "shopping list {} not found",
+                list_id
+            )));
+        }
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    let items = match app_state.get_unbought_shopping_list_items(list.id).await {
+        Ok(items) => items,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    let finished = match app_state
+        .get_finished_shopping_list_items(list.id, MAX_OPTIONS as u64)
+        .await
+    {
+        Ok(items) => items,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    Ok(Board {
+        list,
+        items,
+        finished,
+    })
+}
+
+/// post a new board for a list, pin it, and remember it so it's edited from then on
+async fn post_board(
+    app_state: &AppState,
+    ctx: &Context,
+    board: &Board,
+    channel: ChannelId,
+) -> Result<(), CommandResponse> {
+    let (embed, components) = create_board(board);
+
+    let message = match channel
+        .send_message(
+            ctx,
+            CreateMessage::new().embed(embed).components(components),
+        )
+        .await
+    {
+        Ok(m) => m,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+    };
+
+    // the board is still usable unpinned, so don't fail over missing permissions
+    if let Err(e) = message.pin(ctx).await {
+        error!("error pinning shopping list board: {}", e);
+    }
+
+    if let Err(e) = app_state
+        .set_shopping_list_board(board.list.id, channel.into(), message.id.into())
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with database: {}",
+            e
+        )));
+    }
+
+    Ok(())
+}
+
+/// whether a request failed because the message it was for doesn't exist
+fn is_unknown_message(error: &serenity::Error) -> bool {
+    matches!(
+        error,
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response))
+            if response.error.code == UNKNOWN_MESSAGE
+    )
+}
+
+/// bring the board of a list up to date with its items, posting a new board in `channel` if the
+/// list doesn't have one yet, or posting it again if it has been deleted
+pub async fn refresh_board(
+    app_state: &AppState,
+    ctx: &Context,
+    list_id: i32,
+    channel: ChannelId,
+) -> Result<(), CommandResponse> {
+    let board = load_board(app_state, list_id).await?;
+
+    let channel = match (board.list.board_channel_id, board.list.board_message_id) {
+        (Some(channel_id), Some(message_id)) => {
+            let channel = ChannelId::new(channel_id as u64);
+            let (embed, components) = create_board(&board);
+
+            match channel
+                .edit_message(
+                    ctx,
+                    MessageId::new(message_id as u64),
+                    EditMessage::new().embed(embed).components(components),
+                )
+                .await
+            {
+                Ok(_) => return Ok(()),
+                Err(e) if is_unknown_message(&e) => {
+                    error!("shopping list board has gone, posting it again: {}", e);
+                    channel
+                }
+                Err(e) => {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with discord: {}",
+                        e
+                    )));
+                }
+            }
+        }
+        _ => channel,
+    };
+
+    // there's nothing to keep track of on an archived list
+    if board.list.archived_at.is_some() {
+        return Ok(());
+    }
+
+    post_board(app_state, ctx, &board, channel).await
+}
+
+/// post the board of a list again at the bottom of `channel`, taking down the old board. The new
+/// board is recorded first, so the old one being deleted isn't mistaken for a board to rebuild
+pub async fn move_board(
+    app_state: &AppState,
+    ctx: &Context,
+    list_id: i32,
+    channel: ChannelId,
+) -> Result<(), CommandResponse> {
+    let board = load_board(app_state, list_id).await?;
+    if board.list.archived_at.is_some() {
+        return Ok(());
+    }
+
+    post_board(app_state, ctx, &board, channel).await?;
+
+    if let (Some(channel_id), Some(message_id)) =
+        (board.list.board_channel_id, board.list.board_message_id)
+    {
+        if let Err(e) = ChannelId::new(channel_id as u64)
+            .delete_message(ctx, MessageId::new(message_id as u64))
+            .await
+        {
+            error!("error deleting old shopping list board: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// bring the board of the list an item is on up to date
+pub async fn refresh_item_board(
+    app_state: &AppState,
+    ctx: &Context,
+    message_id: u64,
+    channel: ChannelId,
+) -> Result<(), CommandResponse> {
+    match app_state
+        .get_shopping_list_item_by_message_id(message_id)
+        .await
+    {
+        Ok(Some(item)) => refresh_board(app_state, ctx, item.list_id, channel).await,
+        Ok(None) => Ok(()),
+        Err(e) => Err(CommandResponse::InternalFailure(format!(
+            "error communicating with database: {}",
+            e
+        ))),
+    }
+}
+
+/// post a board again from the `list_item` table if the message that was deleted was one
+pub async fn rebuild_deleted_board(
+    app_state: &AppState,
+    ctx: &Context,
+    channel: ChannelId,
+    message_id: u64,
+) -> Result<(), CommandResponse> {
+    let list = match app_state.get_shopping_list_by_board(message_id).await {
+        Ok(Some(list)) => list,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    if list.archived_at.is_some() {
+        return Ok(());
+    }
+
+    let board = load_board(app_state, list.id).await?;
+    post_board(app_state, ctx, &board, channel).await
+}
+
+/// The select menus on a shopping list board, which tick items off as bought, take them off the
+/// list, or put them back on it
+pub struct ShoppingBoard;
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for ShoppingBoard {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        interaction.data.custom_id.starts_with(BOARD_PREFIX)
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let custom_id = &interaction.data.custom_id;
+        let rest = &custom_id[BOARD_PREFIX.len()..];
+        let (list_id, action) = rest.split_once(':').unwrap_or((rest, "bought"));
+        let list_id: i32 = match list_id.parse() {
+            Ok(id) => id,
+            Err(_) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "invalid board custom id `{}`",
+                    custom_id
+                )));
+            }
+        };
+
+        let values = match &interaction.data.kind {
+            ComponentInteractionDataKind::StringSelect { values } => values,
+            kind => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "unexpected board interaction `{:?}`",
+                    kind
+                )));
+            }
+        };
+
+        let user: u64 = interaction.user.id.into();
+        for value in values {
+            let item = match value.parse() {
+                Ok(id) => match app_state.get_shopping_list_item_by_id(id).await {
+                    Ok(Some(item)) => item,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "error communicating with database: {}",
+                            e
+                        )));
+                    }
+                },
+                Err(_) => continue,
+            };
+            if item.list_id != list_id {
+                continue;
+            }
+
+            // someone may have done the same since the board was last updated
+            let message_id = item.message_id as u64;
+            let result = match (action, item.bought) {
+                ("bought", false) => {
+                    app_state
+                        .set_shopping_list_item_bought(user, message_id, true)
+                        .await
+                }
+                ("remove", false) => app_state.set_shopping_list_item_cleared(message_id).await,
+                ("readd", true) => {
+                    app_state
+                        .set_shopping_list_item_bought(user, message_id, false)
+                        .await
+                }
+                ("bought" | "remove" | "readd", _) => continue,
+                _ => {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "invalid board custom id `{}`",
+                        custom_id
+                    )));
+                }
+            };
+            if let Err(e) = result {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        }
+
+        let board = load_board(app_state, list_id).await?;
+        let (embed, components) = create_board(&board);
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .embed(embed)
+                    .components(components),
+            ),
+        ))
+    }
+}
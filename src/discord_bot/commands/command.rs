@@ -1,18 +1,35 @@
 use serenity::{
-    all::{AutocompleteOption, CommandInteraction, ComponentInteraction},
+    all::{
+        AutocompleteOption, ButtonStyle, CommandInteraction, ComponentInteraction,
+        ComponentInteractionDataKind, ModalInteraction,
+    },
     async_trait,
-    builder::{CreateAutocompleteResponse, CreateCommand},
+    builder::{
+        CreateActionRow, CreateAutocompleteResponse, CreateButton, CreateCommand, CreateEmbed,
+        CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
+        CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption,
+    },
     model::{application::CommandType, Permissions},
     prelude::Context,
 };
 
 use crate::{
     discord_bot::commands::{
+        balance::BalanceCommand,
+        bank::BankCommand,
+        bill::{BillCommand, BillHistory},
+        board::ShoppingBoard,
+        export::ExportCommand,
         hide::HideCommand,
+        kitty::KittyCommand,
+        list::ListCommand,
         pay::{PayAllCommand, PayCommand},
         ping::PingCommand,
+        remind::RemindersCommand,
         say::SayCommand,
-        shop::Shop,
+        settle::SettleCommand,
+        shop::{Shop, ShopDuplicate, ShopHistory, ShopHistoryCommand, ShoppingComplete},
+        statement::StatementCommand,
     },
     state::AppState,
 };
@@ -72,14 +89,208 @@ pub trait InteractionCommand<'a>: Command<'a> {
     ) -> Result<CommandResponse, CommandResponse>;
 }
 
-// #[async_trait]
-// pub trait PaginatedResponse<'a>: Command<'a> {
-//     /// Get the number of pages this response has
-//     fn get_page_count(&self) -> usize;
+/// A command which opens a modal, whose submission must be handled
+#[async_trait]
+pub trait ModalCommand<'a>: Command<'a> {
+    /// validate if this modal was opened by a given command
+    async fn answerable<'b>(
+        interaction: &'b ModalInteraction,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> bool;
+
+    /// handle the submission of the modal
+    async fn modal<'b>(
+        interaction: &'b ModalInteraction,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse>;
+}
+
+/// the prefix for the custom id of the controls on a paginated response.
+/// The full id is `page:<key>:<owner>:<page>:<count>:<action>:<state>`, so that the response can
+/// be rebuilt without storing anything.
+const PAGE_PREFIX: &str = "page:";
+
+/// the most pages which can be offered in the jump menu at once
+const JUMP_OPTIONS: u64 = 25;
+
+/// A single page of a paginated response
+pub struct Page {
+    pub embed: CreateEmbed,
+    /// the number of pages the response has in total
+    pub count: u64,
+}
+
+/// A response which is too long for one message, shown a page at a time with buttons to move
+/// between the pages. Only the user who asked for the response can change its page.
+#[async_trait]
+pub trait PaginatedResponse: Sized + Send + Sync {
+    /// a short key identifying the response in the custom id of its controls
+    fn key() -> &'static str;
+
+    /// the state needed to rebuild this response, which must be short enough to fit in a custom id
+    fn to_state(&self) -> String;
+
+    /// rebuild the response from the state stored in its controls
+    fn from_state(state: &str) -> Option<Self>;
+
+    /// Get a specific page of this response, counting from 0, along with the number of pages
+    async fn get_page(&self, page: u64, app_state: &AppState) -> Result<Page, CommandResponse>;
+}
+
+/// build the controls for a page of a response, leaving them off if there is only one page
+fn page_components<T: PaginatedResponse>(
+    response: &T,
+    owner: u64,
+    page: u64,
+    count: u64,
+) -> Vec<CreateActionRow> {
+    if count <= 1 {
+        return Vec::with_capacity(0);
+    }
+
+    let state = response.to_state();
+    let id = |action: &str| {
+        format!(
+            "{}{}:{}:{}:{}:{}:{}",
+            PAGE_PREFIX,
+            T::key(),
+            owner,
+            page,
+            count,
+            action,
+            state
+        )
+    };
+    let last = count - 1;
+
+    let mut rows = vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(id("first"))
+            .style(ButtonStyle::Secondary)
+            .label("⏮")
+            .disabled(page == 0),
+        CreateButton::new(id("prev"))
+            .style(ButtonStyle::Primary)
+            .label("◀")
+            .disabled(page == 0),
+        CreateButton::new(id("next"))
+            .style(ButtonStyle::Primary)
+            .label("▶")
+            .disabled(page >= last),
+        CreateButton::new(id("last"))
+            .style(ButtonStyle::Secondary)
+            .label("⏭")
+            .disabled(page >= last),
+    ])];
+
+    // a menu can only hold so many options, so offer the pages around the current one
+    if count > 2 {
+        let start = page
+            .saturating_sub(JUMP_OPTIONS / 2)
+            .min(count.saturating_sub(JUMP_OPTIONS));
+        let end = (start + JUMP_OPTIONS).min(count);
+        let options = (start..end)
+            .map(|p| {
+                CreateSelectMenuOption::new(format!("Page {}", p + 1), p.to_string())
+                    .default_selection(p == page)
+            })
+            .collect();
+
+        rows.push(CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(id("jump"), CreateSelectMenuKind::String { options })
+                .placeholder("Jump to page"),
+        ));
+    }
+
+    rows
+}
+
+/// add the page number to the footer of a page, if there is more than one
+fn page_embed(page: Page, number: u64) -> CreateEmbed {
+    match page.count {
+        0 | 1 => page.embed,
+        count => page.embed.footer(CreateEmbedFooter::new(format!(
+            "Page {} of {}",
+            number + 1,
+            count
+        ))),
+    }
+}
+
+/// Respond to a command with the first page of a paginated response
+pub async fn paginate<T: PaginatedResponse>(
+    response: T,
+    interaction: &CommandInteraction,
+    app_state: &AppState,
+) -> Result<CommandResponse, CommandResponse> {
+    let owner: u64 = interaction.user.id.into();
+    let page = response.get_page(0, app_state).await?;
+    let components = page_components(&response, owner, 0, page.count);
+
+    Ok(CommandResponse::ComplexSuccess(
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .embed(page_embed(page, 0))
+                .components(components),
+        ),
+    ))
+}
+
+/// move a paginated response to the page asked for by one of its controls
+async fn turn_page<T: PaginatedResponse>(
+    interaction: &ComponentInteraction,
+    app_state: &AppState,
+) -> Result<CommandResponse, CommandResponse> {
+    let custom_id = &interaction.data.custom_id;
+    let invalid =
+        || CommandResponse::InternalFailure(format!("invalid page custom id `{}`", custom_id));
+
+    let parts: Vec<&str> = custom_id[PAGE_PREFIX.len()..].splitn(6, ':').collect();
+    let (owner, page, count, action, state) = match parts.as_slice() {
+        [_, owner, page, count, action, state] => (*owner, *page, *count, *action, *state),
+        _ => return Err(invalid()),
+    };
+    let owner: u64 = owner.parse().map_err(|_| invalid())?;
+    let page: u64 = page.parse().map_err(|_| invalid())?;
+    let count: u64 = count.parse().map_err(|_| invalid())?;
+    let response = T::from_state(state).ok_or_else(invalid)?;
+
+    if owner != u64::from(interaction.user.id) {
+        return Err(CommandResponse::BasicFailure(String::from(
+            "Only whoever asked for this can change its page.",
+        )));
+    }
+
+    let target = match (action, &interaction.data.kind) {
+        ("first", _) => 0,
+        ("prev", _) => page.saturating_sub(1),
+        ("next", _) => page + 1,
+        ("last", _) => count.saturating_sub(1),
+        ("jump", ComponentInteractionDataKind::StringSelect { values }) => values
+            .first()
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(invalid)?,
+        _ => return Err(invalid()),
+    };
 
-//     /// Get a specific page of this response
-//     fn get_page(&self, page: usize) -> CommandResponse<'a>;
-// }
+    // the number of pages may have changed since the controls were made
+    let mut shown = response.get_page(target, app_state).await?;
+    let mut target = target;
+    if target >= shown.count && shown.count > 0 {
+        target = shown.count - 1;
+        shown = response.get_page(target, app_state).await?;
+    }
+    let components = page_components(&response, owner, target, shown.count);
+
+    Ok(CommandResponse::ComplexSuccess(
+        CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .embed(page_embed(shown, target))
+                .components(components),
+        ),
+    ))
+}
 
 /// match against a list of provided command types, and generate an application command that can be registered with discord
 macro_rules! application_command {
@@ -149,7 +360,7 @@ macro_rules! interaction {
             fn assert_interaction<'a, T: InteractionCommand<'a, Error=String>>() {}
             $(
                 assert_interaction::<$x>();
-                if <$x>::answerable($cmd, $state, $context).await {
+                if <$x as InteractionCommand>::answerable($cmd, $state, $context).await {
                     return <$x>::interaction($cmd, $state, $context).await
                 }
             )*
@@ -158,6 +369,40 @@ macro_rules! interaction {
     };
 }
 
+/// match against a list of provided modal command types, and produce a response which can be sent to the user
+macro_rules! modal {
+    ( $cmd:expr, $state:expr, $context:expr, $( $x:ty ),* $(,)? ) => {
+        {
+            /// ensures that the provided type has relevant traits
+            fn assert_modal<'a, T: ModalCommand<'a, Error=String>>() {}
+            $(
+                assert_modal::<$x>();
+                if <$x as ModalCommand>::answerable($cmd, $state, $context).await {
+                    return <$x>::modal($cmd, $state, $context).await
+                }
+            )*
+            Err(CommandResponse::InternalFailure(String::from("Unsupported Modal Command")))
+        }
+    };
+}
+
+/// match against a list of provided paginated response types, and turn the page of the one the
+/// interaction belongs to
+macro_rules! paginated {
+    ( $cmd:expr, $state:expr, $( $x:ty ),* $(,)? ) => {
+        {
+            /// ensures that the provided type has relevant traits
+            fn assert_paginated<T: PaginatedResponse>() {}
+            $(
+                assert_paginated::<$x>();
+                if ($cmd).data.custom_id.starts_with(&format!("{}{}:", PAGE_PREFIX, <$x>::key())) {
+                    return turn_page::<$x>($cmd, $state).await
+                }
+            )*
+        }
+    };
+}
+
 pub fn application_command() -> Vec<CreateCommand> {
     let mut base = vec![];
     application_command!(
@@ -168,8 +413,18 @@ pub fn application_command() -> Vec<CreateCommand> {
         // DistanceCommand,
         PayCommand,
         PayAllCommand,
+        BalanceCommand,
+        BankCommand,
+        SettleCommand,
+        BillCommand,
+        ExportCommand,
+        StatementCommand,
+        KittyCommand,
+        RemindersCommand,
         Shop,
-        // ShoppingComplete,
+        ShopHistoryCommand,
+        ListCommand,
+        ShoppingComplete,
     );
     base
 }
@@ -189,8 +444,18 @@ pub async fn command<'a>(
         // DistanceCommand,
         PayCommand,
         PayAllCommand,
+        BalanceCommand,
+        BankCommand,
+        SettleCommand,
+        BillCommand,
+        ExportCommand,
+        StatementCommand,
+        KittyCommand,
+        RemindersCommand,
         Shop,
-        // ShoppingComplete,
+        ShopHistoryCommand,
+        ListCommand,
+        ShoppingComplete,
     )
 }
 
@@ -200,7 +465,16 @@ pub async fn autocomplete<'a>(
     app_state: &'a AppState,
     context: &'a Context,
 ) -> Result<CreateAutocompleteResponse, CommandResponse> {
-    autocomplete!(command, app_state, context, PayCommand, PayAllCommand, Shop)
+    autocomplete!(
+        command,
+        app_state,
+        context,
+        PayCommand,
+        PayAllCommand,
+        BillCommand,
+        Shop,
+        ListCommand,
+    )
 }
 
 pub async fn interaction<'a>(
@@ -208,9 +482,25 @@ pub async fn interaction<'a>(
     app_state: &'a AppState,
     context: &'a Context,
 ) -> Result<CommandResponse, CommandResponse> {
+    paginated!(command, app_state, BillHistory, ShopHistory);
+
     interaction!(
-        command, app_state, context, PayCommand, // also handles PayAllCommand
+        command,
+        app_state,
+        context,
+        PayCommand, // also handles PayAllCommand
+        SettleCommand,
+        RemindersCommand,
+        ShoppingBoard,
+        ShopDuplicate,
         Shop,
-        // ShoppingList
     )
 }
+
+pub async fn modal<'a>(
+    command: &'a ModalInteraction,
+    app_state: &'a AppState,
+    context: &'a Context,
+) -> Result<CommandResponse, CommandResponse> {
+    modal!(command, app_state, context, PayCommand)
+}
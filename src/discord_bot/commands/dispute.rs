@@ -0,0 +1,437 @@
+//! Lets a flatmate dispute their share of a bill, and the flatmate who posted the bill accept the
+//! dispute with an adjusted share or reject it.
+
+use log::error;
+use serenity::{
+    all::{
+        ActionRowComponent, ButtonStyle, ComponentInteraction, InputTextStyle, ModalInteraction,
+        UserId,
+    },
+    builder::{
+        CreateActionRow, CreateButton, CreateEmbed, CreateInputText, CreateInteractionResponse,
+        CreateInteractionResponseMessage, CreateMessage, CreateModal,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::embed::EmbedColor,
+        database::payments::{
+            PaymentEdit, PaymentModel, SerenityPaymentDatabase, DISPUTE_ACCEPTED, DISPUTE_OPEN,
+            DISPUTE_REJECTED,
+        },
+    },
+    finance::money::Money,
+    state::AppState,
+};
+
+use super::{
+    pay::refresh_bill_message,
+    util::{display_name, CommandResponse},
+};
+
+/// the custom id of the "Dispute" button on a bill
+pub const DISPUTE_BUTTON: &str = "dispute";
+
+/// the prefix for the custom id of the modals and buttons used to raise and answer a dispute.
+/// The full id is `dispute:<guild id>:<action>:<payment id>:<user>`, as disputes are answered in
+/// DMs, which don't carry a guild id.
+const DISPUTE_PREFIX: &str = "dispute:";
+
+/// whether a custom id belongs to one of the modals or buttons used to raise and answer a dispute
+pub fn is_dispute(custom_id: &str) -> bool {
+    custom_id.starts_with(DISPUTE_PREFIX)
+}
+
+/// build the custom id for an action on a flatmate's dispute of a bill
+fn dispute_id(payment: &PaymentModel, action: &str, user: u64) -> String {
+    format!(
+        "{}{}:{}:{}:{}",
+        DISPUTE_PREFIX,
+        payment.guild_id.unwrap_or_default(),
+        action,
+        payment.id,
+        user
+    )
+}
+
+/// split a dispute custom id into its action, payment id and the flatmate disputing
+fn parse_dispute_id(custom_id: &str) -> Result<(&str, i32, u64), CommandResponse> {
+    let invalid =
+        || CommandResponse::InternalFailure(format!("invalid dispute custom id `{}`", custom_id));
+
+    let mut parts = custom_id
+        .strip_prefix(DISPUTE_PREFIX)
+        .ok_or_else(invalid)?
+        .split(':')
+        .skip(1);
+    let action = parts.next().ok_or_else(invalid)?;
+    let payment_id = parts
+        .next()
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(invalid)?;
+    let user = parts
+        .next()
+        .and_then(|user| user.parse().ok())
+        .ok_or_else(invalid)?;
+
+    Ok((action, payment_id, user))
+}
+
+/// the text entered in one of the inputs of a submitted modal
+fn input_value(interaction: &ModalInteraction, custom_id: &str) -> Option<String> {
+    interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) if input.custom_id == custom_id => {
+                Some(input.value.trim().to_string())
+            }
+            _ => None,
+        })
+}
+
+/// the position of a flatmate's dispute in the stored disputes of a bill, if it hasn't been
+/// answered yet
+pub fn open_dispute(payment: &PaymentModel, user: u64) -> Option<usize> {
+    payment
+        .disputed_users
+        .iter()
+        .position(|u| *u == user as i64)
+        .filter(|i| payment.dispute_outcomes.get(*i).map(String::as_str) == Some(DISPUTE_OPEN))
+}
+
+/// a flatmate's share of a bill, if they have one
+fn share_of(payment: &PaymentModel, user: u64) -> Option<Money> {
+    payment
+        .individual_users
+        .iter()
+        .position(|u| *u == user as i64)
+        .and_then(|i| payment.individual_amounts.get(i))
+        .map(|amount| Money::from_cents(*amount))
+}
+
+async fn load_payment(
+    app_state: &AppState,
+    payment_id: i32,
+) -> Result<PaymentModel, CommandResponse> {
+    match app_state.get_payment_by_id(payment_id).await {
+        Ok(Some(payment)) => Ok(payment),
+        Ok(None) => Err(CommandResponse::BasicFailure(String::from(
+            "This bill no longer exists.",
+        ))),
+        Err(e) => Err(CommandResponse::InternalFailure(format!(
+            "error communicating with database: {}",
+            e
+        ))),
+    }
+}
+
+/// update the bill's message after its disputes change
+async fn refresh(ctx: &Context, payment: &PaymentModel) -> Result<(), CommandResponse> {
+    refresh_bill_message(ctx, payment).await.map_err(|e| {
+        CommandResponse::InternalFailure(format!("error communicating with discord: {}", e))
+    })
+}
+
+/// send a flatmate a DM about a dispute, logging rather than failing if it can't be delivered, as
+/// the dispute has already been recorded by then
+async fn notify(ctx: &Context, user: u64, message: CreateMessage) {
+    if let Err(e) = UserId::new(user).direct_message(ctx, message).await {
+        error!("failed to send dispute message to {}: {}", user, e);
+    }
+}
+
+/// open the modal asking a flatmate why they dispute their share of a bill, after the "Dispute"
+/// button is pressed. The bill must already have been checked to be unpaid by them.
+pub fn start_dispute(
+    interaction: &ComponentInteraction,
+    payment: &PaymentModel,
+) -> Result<CommandResponse, CommandResponse> {
+    let user: u64 = interaction.user.id.into();
+
+    if payment.created_by == user as i64 {
+        return Err(CommandResponse::BasicFailure(String::from(
+            "You posted this bill, use `/bill edit` to change your share instead.",
+        )));
+    }
+
+    if open_dispute(payment, user).is_some() {
+        return Err(CommandResponse::BasicFailure(format!(
+            "You've already disputed your share, {} hasn't answered yet.",
+            display_name(payment.created_by as u64)
+        )));
+    }
+
+    Ok(CommandResponse::ComplexSuccess(
+        CreateInteractionResponse::Modal(
+            CreateModal::new(dispute_id(payment, "reason", user), "Dispute your share").components(
+                vec![CreateActionRow::InputText(
+                    CreateInputText::new(
+                        InputTextStyle::Paragraph,
+                        "What's wrong with your share?",
+                        "reason",
+                    )
+                    .placeholder("e.g. I was away for half of this bill")
+                    .max_length(500),
+                )],
+            ),
+        ),
+    ))
+}
+
+/// handle the "Accept" and "Reject" buttons sent to the creator of a bill with a dispute
+pub async fn answer_dispute(
+    interaction: &ComponentInteraction,
+    app_state: &AppState,
+    ctx: &Context,
+) -> Result<CommandResponse, CommandResponse> {
+    let (action, payment_id, disputed_by) = parse_dispute_id(&interaction.data.custom_id)?;
+    let payment = load_payment(app_state, payment_id).await?;
+
+    if open_dispute(&payment, disputed_by).is_none() {
+        return Err(CommandResponse::BasicFailure(String::from(
+            "This dispute has already been answered.",
+        )));
+    }
+    let share = share_of(&payment, disputed_by).unwrap_or(Money::ZERO);
+
+    match action {
+        // the adjusted share is asked for before the dispute is accepted
+        "accept" => Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Modal(
+                CreateModal::new(
+                    dispute_id(&payment, "amount", disputed_by),
+                    "Accept the dispute",
+                )
+                .components(vec![CreateActionRow::InputText(
+                    CreateInputText::new(InputTextStyle::Short, "New share", "amount")
+                        .value(share.to_decimal()),
+                )]),
+            ),
+        )),
+        "reject" => {
+            let payment = match app_state
+                .resolve_payment_share_dispute(payment.id, disputed_by, DISPUTE_REJECTED)
+                .await
+            {
+                Ok(payment) => payment,
+                Err(e) => {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+            };
+            refresh(ctx, &payment).await?;
+
+            notify(
+                ctx,
+                disputed_by,
+                CreateMessage::new().content(format!(
+                    "{} rejected your dispute of the bill for {}, your share stays at {}.",
+                    display_name(payment.created_by as u64),
+                    payment.purpose,
+                    share
+                )),
+            )
+            .await;
+
+            Ok(CommandResponse::ComplexSuccess(
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!(
+                            "Rejected, {}'s share stays at {}.",
+                            display_name(disputed_by),
+                            share
+                        ))
+                        .components(vec![]),
+                ),
+            ))
+        }
+        _ => Err(CommandResponse::InternalFailure(format!(
+            "unknown dispute action `{}`",
+            action
+        ))),
+    }
+}
+
+/// handle the modals for the reason for a dispute and the adjusted share when accepting one
+pub async fn submit_dispute(
+    interaction: &ModalInteraction,
+    app_state: &AppState,
+    ctx: &Context,
+) -> Result<CommandResponse, CommandResponse> {
+    let (action, payment_id, disputed_by) = parse_dispute_id(&interaction.data.custom_id)?;
+    let payment = load_payment(app_state, payment_id).await?;
+
+    match action {
+        "reason" => {
+            let reason = input_value(interaction, "reason").unwrap_or_default();
+            if reason.is_empty() {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "Please say what's wrong with your share.",
+                )));
+            }
+
+            // the bill may have changed while the modal was open
+            if payment.voided_at.is_some() {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "This bill has been voided, there's nothing to pay.",
+                )));
+            }
+            if payment.paid_users.contains(&(disputed_by as i64)) {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "You've already paid this bill!",
+                )));
+            }
+            let share = match share_of(&payment, disputed_by) {
+                Some(share) => share,
+                None => {
+                    return Err(CommandResponse::BasicFailure(String::from(
+                        "You don't have a share in this bill!",
+                    )));
+                }
+            };
+
+            let payment = match app_state
+                .set_payment_share_disputed(payment.id, disputed_by, &reason)
+                .await
+            {
+                Ok(payment) => payment,
+                Err(e) => {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+            };
+            refresh(ctx, &payment).await?;
+
+            let created_by = payment.created_by as u64;
+            notify(
+                ctx,
+                created_by,
+                CreateMessage::new()
+                    .embed(
+                        CreateEmbed::new()
+                            .title("Share disputed")
+                            .description(format!(
+                                "{} disputes their share of {} of the bill for {} you posted on {}.",
+                                display_name(disputed_by),
+                                share,
+                                payment.purpose,
+                                payment.created_at.format("%d/%m/%y")
+                            ))
+                            .field("Reason:", reason, false)
+                            .color(EmbedColor::Orange as u32),
+                    )
+                    .components(vec![CreateActionRow::Buttons(vec![
+                        CreateButton::new(dispute_id(&payment, "accept", disputed_by))
+                            .style(ButtonStyle::Success)
+                            .label("Accept"),
+                        CreateButton::new(dispute_id(&payment, "reject", disputed_by))
+                            .style(ButtonStyle::Danger)
+                            .label("Reject"),
+                    ])]),
+            )
+            .await;
+
+            Ok(CommandResponse::BasicSuccess(format!(
+                "Your dispute has been sent to {}.",
+                display_name(created_by)
+            )))
+        }
+        "amount" => {
+            if open_dispute(&payment, disputed_by).is_none() {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "This dispute has already been answered.",
+                )));
+            }
+
+            let amount: Money = match input_value(interaction, "amount")
+                .unwrap_or_default()
+                .parse()
+            {
+                Ok(amount) if amount >= Money::ZERO => amount,
+                Ok(_) => {
+                    return Err(CommandResponse::BasicFailure(String::from(
+                        "A share can't be negative.",
+                    )));
+                }
+                Err(e) => return Err(CommandResponse::BasicFailure(e)),
+            };
+
+            let shares = payment
+                .individual_users
+                .iter()
+                .zip(payment.individual_amounts.iter())
+                .map(|(user, cents)| match *user as u64 {
+                    user if user == disputed_by => (user, amount),
+                    user => (user, Money::from_cents(*cents)),
+                })
+                .collect();
+            let edit = PaymentEdit {
+                purpose: None,
+                account: None,
+                shares: Some(shares),
+                references: None,
+            };
+
+            let user: u64 = interaction.user.id.into();
+            let payment = match app_state.edit_payment(payment.id, user, edit).await {
+                Ok(payment) => payment,
+                Err(e) => {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+            };
+            let payment = match app_state
+                .resolve_payment_share_dispute(payment.id, disputed_by, DISPUTE_ACCEPTED)
+                .await
+            {
+                Ok(payment) => payment,
+                Err(e) => {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+            };
+            refresh(ctx, &payment).await?;
+
+            notify(
+                ctx,
+                disputed_by,
+                CreateMessage::new().content(format!(
+                    "{} accepted your dispute of the bill for {}, your share is now {}.",
+                    display_name(payment.created_by as u64),
+                    payment.purpose,
+                    amount
+                )),
+            )
+            .await;
+
+            Ok(CommandResponse::ComplexSuccess(
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!(
+                            "Accepted, {}'s share is now {}.",
+                            display_name(disputed_by),
+                            amount
+                        ))
+                        .components(vec![]),
+                ),
+            ))
+        }
+        _ => Err(CommandResponse::InternalFailure(format!(
+            "unknown dispute action `{}`",
+            action
+        ))),
+    }
+}
@@ -0,0 +1,202 @@
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateAttachment, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::database::payments::SerenityPaymentDatabase,
+    finance::export::{self, ExportFormat},
+    state::{AppState, CONFIG},
+};
+
+use super::{
+    command::Command,
+    util::{parse_date, CommandResponse},
+};
+
+/// Export the flat's records as a file
+pub enum ExportCommand<'a> {
+    Payments {
+        format: ExportFormat,
+        from: Option<&'a str>,
+        to: Option<&'a str>,
+    },
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ExportCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let subcommand = interaction
+            .data
+            .options()
+            .into_iter()
+            .next()
+            .ok_or("No subcommand provided")?;
+
+        match (subcommand.name, subcommand.value) {
+            ("payments", ResolvedValue::SubCommand(options)) => {
+                let mut format = ExportFormat::Csv;
+                let mut from = None;
+                let mut to = None;
+
+                for option in options {
+                    match (option.name, option.value) {
+                        ("format", ResolvedValue::String(val)) => {
+                            format = ExportFormat::from_name(val)
+                                .ok_or_else(|| format!("unknown export format `{}`", val))?
+                        }
+                        ("from", ResolvedValue::String(val)) => from = Some(val),
+                        ("to", ResolvedValue::String(val)) => to = Some(val),
+                        (opt, val) => {
+                            return Err(format!(
+                                "unexpected option name: `{}` and value `{:?}`",
+                                opt, val
+                            ))
+                        }
+                    }
+                }
+
+                Ok(ExportCommand::Payments { format, from, to })
+            }
+            (opt, _) => Err(format!("unexpected subcommand `{}`", opt)),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ExportCommand<'a> {
+    fn name() -> &'static str {
+        "export"
+    }
+
+    fn description() -> &'static str {
+        "Download the flat's records as a file"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "payments",
+                "Every bill, share and payment, optionally between two dates",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "format",
+                    "The type of file to export, defaults to CSV",
+                )
+                .add_string_choice("CSV", "csv")
+                .add_string_choice("JSON", "json")
+                .add_string_choice("Spreadsheet (ODS)", "ods")
+                .required(false),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "from",
+                    "The first day to export, as YYYY-MM-DD",
+                )
+                .required(false),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "to",
+                    "The last day to export, as YYYY-MM-DD",
+                )
+                .required(false),
+            ),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let ExportCommand::Payments { format, from, to } = self;
+        let (from, to) = (parse_date(from)?, parse_date(to)?);
+
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "The first day to export is after the last.",
+                )));
+            }
+        }
+
+        let guild_id = interaction.guild_id.map(|g| g.0.into());
+        let payments = match app_state.get_guild_payment_history(guild_id).await {
+            Ok(payments) => payments,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+        let settlements = match app_state.get_guild_settlements(guild_id).await {
+            Ok(settlements) => settlements,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        // the export is read outside of discord, so use names rather than mentions
+        let rows = export::rows(&payments, &settlements, from, to, |user| {
+            match CONFIG.flatmates.iter().find(|f| f.discord_id == user) {
+                Some(flatmate) => flatmate.display_name.clone(),
+                None => user.to_string(),
+            }
+        });
+        if rows.is_empty() {
+            return Err(CommandResponse::BasicFailure(String::from(
+                "There are no payments to export in that time.",
+            )));
+        }
+
+        let contents = match export::write(format, &rows) {
+            Ok(contents) => contents,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "failed to write export: {}",
+                    e
+                )));
+            }
+        };
+
+        let range = match (from, to) {
+            (None, None) => String::from("all"),
+            (from, to) => format!(
+                "{}-to-{}",
+                from.map(|d| d.to_string())
+                    .unwrap_or_else(|| String::from("start")),
+                to.map(|d| d.to_string())
+                    .unwrap_or_else(|| String::from("now"))
+            ),
+        };
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .ephemeral(true)
+                    .content(format!("Exported {} payment events.", rows.len()))
+                    .add_file(CreateAttachment::bytes(
+                        contents,
+                        format!("payments-{}.{}", range, format.extension()),
+                    )),
+            ),
+        ))
+    }
+}
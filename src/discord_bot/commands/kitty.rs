@@ -0,0 +1,372 @@
+use chrono::Local;
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateActionRow, CreateAttachment, CreateButton, CreateCommand, CreateCommandOption,
+        CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+    },
+    model::prelude::Attachment,
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::embed::EmbedColor,
+        database::kitty::{KittyEntryModel, NewKittyEntry, SerenityKittyDatabase},
+    },
+    finance::{kitty, money::Money, split},
+    state::{AppState, CONFIG},
+};
+
+use super::{
+    command::Command,
+    pay::archive_receipt,
+    util::{display_name, join_lines, CommandResponse},
+};
+
+/// how many of the most recent top ups and purchases are shown with the balance
+const RECENT_ENTRIES: usize = 10;
+
+/// Manage the flat's kitty, a shared fund for small purchases
+pub enum KittyCommand<'a> {
+    Topup {
+        amount: f64,
+        note: Option<&'a str>,
+    },
+    Spend {
+        amount: f64,
+        purpose: &'a str,
+        receipt: &'a Attachment,
+    },
+    Balance,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for KittyCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let subcommand = interaction
+            .data
+            .options()
+            .into_iter()
+            .next()
+            .ok_or("No subcommand provided")?;
+
+        match (subcommand.name, subcommand.value) {
+            ("balance", ResolvedValue::SubCommand(_)) => Ok(KittyCommand::Balance),
+            ("topup", ResolvedValue::SubCommand(options)) => {
+                let mut amount = None;
+                let mut note = None;
+
+                for option in options {
+                    match (option.name, option.value) {
+                        ("amount", ResolvedValue::Number(val)) => amount = Some(val),
+                        ("note", ResolvedValue::String(val)) => note = Some(val),
+                        (opt, val) => {
+                            return Err(format!(
+                                "unexpected option name: `{}` and value `{:?}`",
+                                opt, val
+                            ))
+                        }
+                    }
+                }
+
+                Ok(KittyCommand::Topup {
+                    amount: amount.ok_or("No amount provided")?,
+                    note,
+                })
+            }
+            ("spend", ResolvedValue::SubCommand(options)) => {
+                let mut amount = None;
+                let mut purpose = None;
+                let mut receipt = None;
+
+                for option in options {
+                    match (option.name, option.value) {
+                        ("amount", ResolvedValue::Number(val)) => amount = Some(val),
+                        ("purpose", ResolvedValue::String(val)) => purpose = Some(val),
+                        ("receipt", ResolvedValue::Attachment(val)) => receipt = Some(val),
+                        (opt, val) => {
+                            return Err(format!(
+                                "unexpected option name: `{}` and value `{:?}`",
+                                opt, val
+                            ))
+                        }
+                    }
+                }
+
+                Ok(KittyCommand::Spend {
+                    amount: amount.ok_or("No amount provided")?,
+                    purpose: purpose.ok_or("No purpose provided")?,
+                    receipt: receipt.ok_or("No receipt provided")?,
+                })
+            }
+            (opt, _) => Err(format!("unexpected subcommand `{}`", opt)),
+        }
+    }
+}
+
+/// the flatmates living in the flat today, who are asked to top up the kitty
+fn current_flatmates() -> usize {
+    let today = Local::now().date_naive();
+
+    CONFIG
+        .flatmates
+        .iter()
+        .filter(|f| split::days_in_residence(f.move_in, f.move_out, today, today) > 0)
+        .count()
+}
+
+/// warn that the kitty is running low, suggesting how much each flatmate should put in to bring it
+/// back up to its target
+fn low_balance_embed(balance: Money) -> Option<CreateEmbed> {
+    if balance >= Money::from_dollars(CONFIG.kitty.low_balance) {
+        return None;
+    }
+
+    let target = Money::from_dollars(CONFIG.kitty.target);
+    let description = match kitty::even_topup(balance, target, current_flatmates()) {
+        Some(each) => format!(
+            "The kitty is down to {}. A top up of {} from each flatmate would bring it back to {}.",
+            balance, each, target
+        ),
+        None => format!("The kitty is down to {}.", balance),
+    };
+
+    Some(
+        CreateEmbed::new()
+            .title("Kitty running low")
+            .description(description)
+            .color(EmbedColor::Orange as u32),
+    )
+}
+
+/// build the embed showing the kitty's balance and its most recent top ups and purchases
+fn create_balance_embed(entries: &[KittyEntryModel]) -> CreateEmbed {
+    let recent = if entries.is_empty() {
+        String::from("Nothing has been put into the kitty yet.")
+    } else {
+        join_lines(
+            entries
+                .iter()
+                .rev()
+                .take(RECENT_ENTRIES)
+                .map(|entry| {
+                    let amount = Money::from_cents(entry.amount);
+                    format!(
+                        "{} {} {}{}: {}",
+                        entry.created_at.format("%d/%m/%y"),
+                        display_name(entry.user_id as u64),
+                        if amount > Money::ZERO { "+" } else { "" },
+                        amount,
+                        entry.description
+                    )
+                })
+                .collect(),
+        )
+    };
+
+    CreateEmbed::new()
+        .title("Kitty")
+        .description(recent)
+        .field("Balance:", kitty::balance(entries).to_string(), false)
+        .color(EmbedColor::Green as u32)
+}
+
+#[async_trait]
+impl<'a> Command<'a> for KittyCommand<'a> {
+    fn name() -> &'static str {
+        "kitty"
+    }
+
+    fn description() -> &'static str {
+        "Top up, spend from or check the flat's shared kitty"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "topup",
+                "Put money into the kitty",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Number,
+                    "amount",
+                    "How much you put in",
+                )
+                .min_number_value(0.01)
+                .required(true),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "note",
+                    "A note about the top up",
+                )
+                .required(false),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "spend",
+                "Pay for a shared purchase out of the kitty",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::Number, "amount", "How much was spent")
+                    .min_number_value(0.01)
+                    .required(true),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "purpose",
+                    "What was bought, e.g. cleaning supplies",
+                )
+                .required(true),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Attachment,
+                    "receipt",
+                    "Attach a photograph of the receipt",
+                )
+                .required(true),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "balance",
+            "Show how much is in the kitty, and what it was last spent on",
+        ))
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let user: u64 = interaction.user.id.into();
+        let guild_id: Option<u64> = interaction.guild_id.map(|g| g.0.into());
+
+        let entries = match app_state.get_kitty_entries(guild_id).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+        let balance = kitty::balance(&entries);
+
+        let (embed, message, balance) = match self {
+            KittyCommand::Balance => (
+                create_balance_embed(&entries),
+                CreateInteractionResponseMessage::new(),
+                balance,
+            ),
+            KittyCommand::Topup { amount, note } => {
+                let amount = Money::from_dollars(amount);
+
+                let entry = NewKittyEntry {
+                    amount,
+                    description: note.unwrap_or("Top up"),
+                    receipt_url: "",
+                    image_path: "",
+                };
+                if let Err(e) = app_state
+                    .add_kitty_entry(user, interaction.channel_id.into(), guild_id, entry)
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+
+                (
+                    CreateEmbed::new()
+                        .title("Kitty topped up")
+                        .description(match note {
+                            Some(note) => format!(
+                                "{} put {} into the kitty: {}",
+                                display_name(user),
+                                amount,
+                                note
+                            ),
+                            None => {
+                                format!("{} put {} into the kitty", display_name(user), amount)
+                            }
+                        })
+                        .field("Balance:", (balance + amount).to_string(), false)
+                        .color(EmbedColor::Green as u32),
+                    CreateInteractionResponseMessage::new(),
+                    balance + amount,
+                )
+            }
+            KittyCommand::Spend {
+                amount,
+                purpose,
+                receipt,
+            } => {
+                let amount = Money::from_dollars(amount);
+                if amount > balance {
+                    return Err(CommandResponse::BasicFailure(format!(
+                        "The kitty only has {} in it, top it up with `/kitty topup` first.",
+                        balance
+                    )));
+                }
+
+                let (contents, image_path, receipt_url) =
+                    archive_receipt(app_state, receipt).await?;
+
+                let entry = NewKittyEntry {
+                    amount: -amount,
+                    description: purpose,
+                    receipt_url: &receipt_url,
+                    image_path: &image_path,
+                };
+                if let Err(e) = app_state
+                    .add_kitty_entry(user, interaction.channel_id.into(), guild_id, entry)
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+
+                (
+                    CreateEmbed::new()
+                        .title("Spent from the kitty")
+                        .description(format!(
+                            "{} spent {} from the kitty on {}",
+                            display_name(user),
+                            amount,
+                            purpose
+                        ))
+                        .field("Balance:", (balance - amount).to_string(), false)
+                        .color(EmbedColor::Green as u32),
+                    CreateInteractionResponseMessage::new()
+                        .add_file(CreateAttachment::bytes(contents, receipt.filename.clone()))
+                        .components(vec![CreateActionRow::Buttons(vec![
+                            CreateButton::new_link(receipt_url).label("Receipt"),
+                        ])]),
+                    balance - amount,
+                )
+            }
+        };
+
+        let mut embeds = vec![embed];
+        embeds.extend(low_balance_embed(balance));
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(message.embeds(embeds)),
+        ))
+    }
+}
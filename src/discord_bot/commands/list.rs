@@ -0,0 +1,371 @@
+use log::error;
+use serenity::{
+    all::{AutocompleteOption, CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{
+        AutocompleteChoice, CreateAutocompleteResponse, CreateCommand, CreateCommandOption,
+        CreateEmbed, CreateInteractionResponse, CreateInteractionResponseFollowup,
+        CreateInteractionResponseMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::embed::EmbedColor,
+        database::shopping::{SerenityShoppingDatabase, ShoppingListModel},
+    },
+    state::AppState,
+};
+
+use super::{
+    board::refresh_board,
+    command::{AutocompleteCommand, Command},
+    util::CommandResponse,
+};
+
+/// the form list names are stored in, so that "Groceries" and "groceries " are the same list
+pub fn normalise_list_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// find a shopping list of a guild by name, failing with the lists that do exist if there's none
+/// by that name
+pub async fn find_list(
+    app_state: &AppState,
+    guild_id: Option<u64>,
+    name: &str,
+) -> Result<ShoppingListModel, CommandResponse> {
+    let name = normalise_list_name(name);
+
+    let lists = match app_state.get_shopping_lists(guild_id).await {
+        Ok(lists) => lists,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    match lists.iter().find(|list| list.name == name) {
+        Some(list) => Ok(list.clone()),
+        None if lists.is_empty() => Err(CommandResponse::BasicFailure(format!(
+            "There's no list called `{}`, create it with `/list create`.",
+            name
+        ))),
+        None => Err(CommandResponse::BasicFailure(format!(
+            "There's no list called `{}`, the lists are: {}.",
+            name,
+            lists
+                .iter()
+                .map(|list| format!("`{}`", list.name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+/// suggest the names of a guild's shopping lists which contain what has been typed so far
+pub async fn autocomplete_list_names(
+    app_state: &AppState,
+    guild_id: Option<u64>,
+    search_phrase: &str,
+) -> Result<CreateAutocompleteResponse, CommandResponse> {
+    let lists = match app_state.get_shopping_lists(guild_id).await {
+        Ok(lists) => lists,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    let search_phrase = normalise_list_name(search_phrase);
+    let choices = lists
+        .into_iter()
+        .filter(|list| list.name.contains(&search_phrase))
+        .take(25)
+        .map(|list| AutocompleteChoice {
+            name: list.name.clone(),
+            value: serde_json::Value::String(list.name),
+        })
+        .collect();
+
+    Ok(CreateAutocompleteResponse::new().set_choices(choices))
+}
+
+/// Manage the named shopping lists of a guild, such as "groceries" and "hardware"
+pub enum ListCommand<'a> {
+    Create { name: &'a str },
+    Switch { name: &'a str },
+    Archive { name: Option<&'a str> },
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ListCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let subcommand = interaction
+            .data
+            .options()
+            .into_iter()
+            .next()
+            .ok_or("No subcommand provided")?;
+
+        let options = match subcommand.value {
+            ResolvedValue::SubCommand(options) => options,
+            val => return Err(format!("unexpected subcommand value `{:?}`", val)),
+        };
+
+        let mut name = None;
+        for option in options {
+            match (option.name, option.value) {
+                ("name", ResolvedValue::String(val)) => name = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        match subcommand.name {
+            "create" => Ok(ListCommand::Create {
+                name: name.ok_or("No name provided")?,
+            }),
+            "switch" => Ok(ListCommand::Switch {
+                name: name.ok_or("No name provided")?,
+            }),
+            "archive" => Ok(ListCommand::Archive { name }),
+            opt => Err(format!("unexpected subcommand `{}`", opt)),
+        }
+    }
+}
+
+/// a message announcing a change to the shopping lists, which everyone can see
+fn list_message(description: String) -> CreateInteractionResponse {
+    CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new().embed(
+            CreateEmbed::new()
+                .title("Shopping lists")
+                .description(description)
+                .color(EmbedColor::Green as u32),
+        ),
+    )
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ListCommand<'a> {
+    fn name() -> &'static str {
+        "list"
+    }
+
+    fn description() -> &'static str {
+        "Create, switch between and archive shopping lists"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "create",
+                "Create a shopping list, and add new items to it",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "name",
+                    "The name of the list, e.g. groceries",
+                )
+                .max_length(50)
+                .required(true),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "switch",
+                "Add new items to a different shopping list",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "name",
+                    "The list to switch to",
+                )
+                .set_autocomplete(true)
+                .required(true),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "archive",
+                "Archive a shopping list once it's done with",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "name",
+                    "The list to archive, defaults to the current list",
+                )
+                .set_autocomplete(true)
+                .required(false),
+            ),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: Option<u64> = interaction.guild_id.map(|g| g.0.into());
+
+        match self {
+            ListCommand::Create { name } => {
+                let name = normalise_list_name(name);
+                if name.is_empty() {
+                    return Err(CommandResponse::BasicFailure(String::from(
+                        "A list needs a name.",
+                    )));
+                }
+
+                match app_state.get_shopping_list_by_name(guild_id, &name).await {
+                    Ok(None) => {}
+                    Ok(Some(_)) => {
+                        return Err(CommandResponse::BasicFailure(format!(
+                            "There's already a list called `{}`.",
+                            name
+                        )));
+                    }
+                    Err(e) => {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "error communicating with database: {}",
+                            e
+                        )));
+                    }
+                }
+
+                let response = list_message(format!(
+                    "Created the `{}` list, new items will be added to it.",
+                    name
+                ));
+                if let Err(e) = interaction.create_response(&ctx, response).await {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "Failed to create interaction response: {}",
+                        e
+                    )));
+                }
+
+                let message = match interaction.get_response(&ctx).await {
+                    Ok(m) => m,
+                    Err(e) => {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "Failed to get interaction response: {}",
+                            e
+                        )));
+                    }
+                };
+
+                if let Err(e) = app_state
+                    .add_shopping_list(
+                        interaction.user.id.into(),
+                        message.id.into(),
+                        interaction.channel_id.into(),
+                        guild_id,
+                        &name,
+                    )
+                    .await
+                {
+                    error!("error adding shopping list: {}", e);
+                    if let Err(inner_e) = interaction
+                        .create_followup(
+                            &ctx,
+                            CreateInteractionResponseFollowup::new()
+                                .content(
+                                    "error communicating with database, this list has not been saved",
+                                )
+                                .ephemeral(true),
+                        )
+                        .await
+                    {
+                        error!("error creating followup to return error: {}", inner_e);
+                    }
+                }
+
+                Ok(CommandResponse::NoResponse)
+            }
+            ListCommand::Switch { name } => {
+                let list = find_list(app_state, guild_id, name).await?;
+
+                if let Err(e) = app_state.set_shopping_list_active(list.id).await {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+
+                Ok(CommandResponse::ComplexSuccess(list_message(format!(
+                    "Switched to the `{}` list, new items will be added to it.",
+                    list.name
+                ))))
+            }
+            ListCommand::Archive { name } => {
+                let list = match name {
+                    Some(name) => find_list(app_state, guild_id, name).await?,
+                    None => match app_state.get_shopping_list(guild_id).await {
+                        Ok(Some((list, _))) => list,
+                        Ok(None) => {
+                            return Err(CommandResponse::BasicFailure(String::from(
+                                "There are no shopping lists to archive.",
+                            )));
+                        }
+                        Err(e) => {
+                            return Err(CommandResponse::InternalFailure(format!(
+                                "error communicating with database: {}",
+                                e
+                            )));
+                        }
+                    },
+                };
+
+                if let Err(e) = app_state.set_shopping_list_archived(list.id).await {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+
+                // leave the board showing that the list is done with
+                refresh_board(app_state, ctx, list.id, interaction.channel_id).await?;
+
+                Ok(CommandResponse::ComplexSuccess(list_message(format!(
+                    "Archived the `{}` list.",
+                    list.name
+                ))))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> AutocompleteCommand<'a> for ListCommand<'a> {
+    async fn autocomplete<'c>(
+        command: &'c CommandInteraction,
+        autocomplete: &'c AutocompleteOption,
+        app_state: &'c AppState,
+        _: &'c Context,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        autocomplete_list_names(
+            app_state,
+            command.guild_id.map(|g| g.0.into()),
+            autocomplete.value,
+        )
+        .await
+    }
+}
@@ -1,10 +1,26 @@
 mod command;
 mod util;
 
+mod balance;
+mod bank;
+mod bill;
+mod board;
+mod dispute;
+mod export;
 mod hide;
+mod kitty;
+mod list;
 mod pay;
 mod ping;
+mod remind;
 mod say;
+mod settle;
 mod shop;
+mod statement;
 
-pub use command::{application_command, autocomplete, command, interaction};
+pub use board::rebuild_deleted_board;
+pub use command::{application_command, autocomplete, command, interaction, modal};
+pub use dispute::open_dispute;
+pub use pay::post_bill;
+pub use remind::{in_quiet_hours, send_reminder};
+pub use statement::send_statement;
@@ -1,15 +1,18 @@
 use std::collections::HashSet;
 
+use chrono::{NaiveDate, NaiveDateTime};
+use log::error;
 use serenity::{
     all::{
-        AutocompleteOption, ButtonStyle, CommandInteraction, CommandOptionType,
-        ComponentInteraction, ResolvedValue,
+        AutocompleteOption, ButtonStyle, ChannelId, CommandInteraction, CommandOptionType,
+        ComponentInteraction, Message, MessageId, ModalInteraction, ResolvedValue,
     },
     async_trait,
     builder::{
         AutocompleteChoice, CreateActionRow, CreateAttachment, CreateAutocompleteResponse,
         CreateButton, CreateCommand, CreateCommandOption, CreateEmbed, CreateEmbedFooter,
-        CreateInteractionResponse, CreateInteractionResponseMessage, EditMessage,
+        CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+        EditInteractionResponse, EditMessage,
     },
     json::Value,
     model::prelude::Attachment,
@@ -17,16 +20,26 @@ use serenity::{
 };
 
 use crate::{
-    discord_bot::common::embed::EmbedColor,
+    discord_bot::{
+        common::embed::EmbedColor,
+        database::{
+            payments::{
+                NewPayment, PaymentModel, SerenityPaymentDatabase, DISPUTE_ACCEPTED, DISPUTE_OPEN,
+            },
+            DatabaseResult,
+        },
+    },
+    finance::{account::AccountNumber, budget, money::Money, reference, split},
     state::{AppState, Flatmate, CONFIG},
 };
 
 use super::{
-    command::{AutocompleteCommand, Command, InteractionCommand},
-    util::CommandResponse,
+    command::{AutocompleteCommand, Command, InteractionCommand, ModalCommand},
+    dispute::{self, DISPUTE_BUTTON},
+    util::{display_name, parse_date, random_phrase, CommandResponse},
 };
 
-async fn handle_autocomplete_for_pay<'c>(
+pub async fn handle_autocomplete_for_pay<'c>(
     interaction: &'c CommandInteraction,
     autocomplete: &'c AutocompleteOption<'_>,
 ) -> Result<CreateAutocompleteResponse, CommandResponse> {
@@ -35,24 +48,53 @@ async fn handle_autocomplete_for_pay<'c>(
     // match over which option is focussed, and provide options for that
     match autocomplete.name {
         "purpose" => {
-            response = response.set_choices(vec![
-                AutocompleteChoice {
-                    name: String::from("Food"),
-                    value: Value::from("food"),
-                },
-                AutocompleteChoice {
-                    name: String::from("Power"),
-                    value: Value::from("power"),
-                },
-                AutocompleteChoice {
-                    name: String::from("Water"),
-                    value: Value::from("water"),
-                },
-                AutocompleteChoice {
-                    name: String::from("Internet/Wifi"),
-                    value: Value::from("internet"),
-                },
-            ]);
+            response = response.set_choices(
+                CONFIG
+                    .categories
+                    .iter()
+                    .take(25)
+                    .map(|category| AutocompleteChoice {
+                        name: category.label.clone(),
+                        value: Value::from(category.name.clone()),
+                    })
+                    .collect(),
+            );
+        }
+        "except" => {
+            // the option is a comma separated list, so only complete the last name in it
+            let (listed, partial) = match autocomplete.value.rsplit_once(',') {
+                Some((listed, partial)) => (Some(listed), partial.trim()),
+                None => (None, autocomplete.value.trim()),
+            };
+            let already: Vec<&str> = listed
+                .map(|listed| listed.split(',').map(str::trim).collect())
+                .unwrap_or_default();
+
+            response = response.set_choices(
+                CONFIG
+                    .flatmates
+                    .iter()
+                    .filter(|f| !already.iter().any(|a| a.eq_ignore_ascii_case(&f.name)))
+                    .filter(|f| {
+                        f.name
+                            .to_ascii_lowercase()
+                            .starts_with(&partial.to_ascii_lowercase())
+                            || f.display_name
+                                .to_ascii_lowercase()
+                                .starts_with(&partial.to_ascii_lowercase())
+                    })
+                    .map(|f| {
+                        let value = match listed {
+                            Some(listed) => format!("{}, {}", listed, f.name),
+                            None => f.name.clone(),
+                        };
+                        AutocompleteChoice {
+                            name: value.clone(),
+                            value: Value::from(value),
+                        }
+                    })
+                    .collect(),
+            );
         }
         i if CONFIG
             .flatmates
@@ -97,65 +139,708 @@ async fn handle_autocomplete_for_pay<'c>(
     Ok(response)
 }
 
-async fn create_response<'a>(
+/// The state of a single flatmate's share of a bill
+struct BillShare {
+    /// the discord id of the flatmate
+    user: u64,
+    /// the amount owed
+    amount: Money,
+    /// when the flatmate marked their share as paid, if they have
+    paid_at: Option<NaiveDateTime>,
+    /// the reference to pay the share with, bills from before references were added have none
+    reference: Option<String>,
+    /// the reason the flatmate disputed their share and how the dispute was answered, if they have
+    dispute: Option<(String, String)>,
+}
+
+impl BillShare {
+    /// load the shares of a stored bill, along with whether each has been paid
+    fn from_payment(payment: &PaymentModel) -> Vec<Self> {
+        payment
+            .individual_users
+            .iter()
+            .zip(payment.individual_amounts.iter())
+            .enumerate()
+            .map(|(i, (user, amount))| BillShare {
+                user: *user as u64,
+                amount: Money::from_cents(*amount),
+                paid_at: payment
+                    .paid_users
+                    .iter()
+                    .position(|paid| paid == user)
+                    .and_then(|i| payment.paid_at.get(i).copied()),
+                reference: payment.share_references.get(i).cloned(),
+                dispute: payment
+                    .disputed_users
+                    .iter()
+                    .position(|disputed| disputed == user)
+                    .and_then(|i| {
+                        payment
+                            .dispute_reasons
+                            .get(i)
+                            .cloned()
+                            .zip(payment.dispute_outcomes.get(i).cloned())
+                    }),
+            })
+            .collect()
+    }
+
+    /// the shares of a bill which has just been created, so nobody has paid yet
+    fn unpaid(payment: &NewPayment<'_>) -> Vec<Self> {
+        payment
+            .shares
+            .iter()
+            .enumerate()
+            .map(|(i, (user, amount))| BillShare {
+                user: *user,
+                amount: *amount,
+                paid_at: None,
+                reference: payment.references.get(i).cloned(),
+                dispute: None,
+            })
+            .collect()
+    }
+}
+
+/// Create a unique reference for each of the given flatmates' shares of a bill
+pub async fn share_references(
+    app_state: &AppState,
+    guild_id: Option<u64>,
     purpose: &str,
-    user: &str,
-    receipt: &str,
-    total: f64,
-    amounts: Vec<(&Flatmate, f64)>,
-    account: &str,
-    ctx: &Context,
-) -> CreateInteractionResponse {
-    CreateInteractionResponse::Message(
-        CreateInteractionResponseMessage::new()
-            .embed(
-                CreateEmbed::new()
-                    .title("Bill created")
-                    .description(format!(
-                        "Bill for {} totalling ${:.2} created by {} on {} to be paid into `{}`",
-                        purpose,
-                        total,
-                        user,
-                        chrono::offset::Local::now().format("%d/%m/%y at %I:%M%P"),
-                        account
+    date: NaiveDate,
+    users: &[u64],
+) -> DatabaseResult<Vec<String>> {
+    let taken = app_state.get_guild_share_references(guild_id).await?;
+    let names: Vec<String> = users
+        .iter()
+        .map(|user| {
+            CONFIG
+                .flatmates
+                .iter()
+                .find(|f| f.discord_id == *user)
+                .map(|f| f.name.clone())
+                .unwrap_or_default()
+        })
+        .collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+
+    Ok(reference::generate(purpose, date, &names, &taken))
+}
+
+/// The account a bill is paid into: the account given with the command if there is one, otherwise
+/// the account of the flatmate who paid for it, falling back to the head tenant's
+pub fn payout_account(user: u64, given: Option<&str>) -> Result<String, String> {
+    match given {
+        Some(account) => account
+            .parse::<AccountNumber>()
+            .map(|account| account.to_string())
+            .map_err(|e| format!("`{}` isn't a valid account number. {}", account.trim(), e)),
+        None => {
+            let configured = CONFIG
+                .flatmates
+                .iter()
+                .find(|f| f.discord_id == user)
+                .and_then(|f| f.account.as_deref())
+                .unwrap_or(&CONFIG.head_tennant_acc_number);
+
+            // configured accounts are checked at startup, this only writes them out the usual way
+            configured
+                .parse::<AccountNumber>()
+                .map(|account| account.to_string())
+                .map_err(|e| format!("The account in the config isn't valid. {}", e))
+        }
+    }
+}
+
+/// find the flatmate a user is referring to by name or display name
+fn find_flatmate(name: &str) -> Option<&'static Flatmate> {
+    CONFIG.flatmates.iter().find(|flatmate| {
+        flatmate.name.eq_ignore_ascii_case(name) || flatmate.display_name.eq_ignore_ascii_case(name)
+    })
+}
+
+/// parse a comma separated list of flatmates, such as `john, peter`
+fn parse_flatmate_list(input: &str) -> Result<Vec<u64>, String> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            find_flatmate(name)
+                .map(|flatmate| flatmate.discord_id)
+                .ok_or_else(|| format!("`{}` isn't a flatmate", name))
+        })
+        .collect()
+}
+
+/// parse a list of per-flatmate values, such as `john=1.2, peter=1`
+fn parse_split_values(input: &str) -> Result<Vec<(u64, f64)>, String> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (name, value) = entry
+                .split_once('=')
+                .or_else(|| entry.split_once(':'))
+                .ok_or_else(|| format!("`{}` should look like `name=value`", entry))?;
+
+            let flatmate = find_flatmate(name.trim())
+                .ok_or_else(|| format!("`{}` isn't a flatmate", name.trim()))?;
+            let value = value
+                .trim()
+                .trim_end_matches('%')
+                .parse::<f64>()
+                .map_err(|_| format!("`{}` isn't a number", value.trim()))?;
+
+            Ok((flatmate.discord_id, value))
+        })
+        .collect()
+}
+
+/// split a bill between all flatmates except those excluded, using the given split mode
+fn split_bill(
+    total: Money,
+    mode: &str,
+    values: Option<&str>,
+    period: Option<(NaiveDate, NaiveDate)>,
+    excluded: &[u64],
+    remainder_to: u64,
+) -> Result<Vec<(u64, Money)>, String> {
+    let overrides = match values {
+        Some(values) => parse_split_values(values)?,
+        None => Vec::new(),
+    };
+    let flatmates: Vec<&Flatmate> = CONFIG
+        .flatmates
+        .iter()
+        .filter(|f| !excluded.contains(&f.discord_id))
+        .collect();
+    let value_for = |flatmate: &Flatmate| {
+        overrides
+            .iter()
+            .rev()
+            .find(|(user, _)| *user == flatmate.discord_id)
+            .map(|(_, value)| *value)
+    };
+
+    let result = match mode {
+        "even" => {
+            let users: Vec<u64> = flatmates.iter().map(|f| f.discord_id).collect();
+            split::evenly(total, &users, remainder_to)
+        }
+        "weights" | "shares" => {
+            if mode == "shares" && overrides.is_empty() {
+                return Err(String::from(
+                    "Please give the shares for the split, e.g. `john=1.2, peter=1`",
+                ));
+            }
+
+            // anyone not given a share falls back to their configured weight
+            let weights: Vec<(u64, f64)> = flatmates
+                .iter()
+                .map(|f| (f.discord_id, value_for(f).unwrap_or(f.weight)))
+                .collect();
+            split::by_weight(total, &weights, remainder_to)
+        }
+        "percent" => {
+            // anyone not given a percentage doesn't pay anything
+            let percents: Vec<(u64, f64)> = flatmates
+                .iter()
+                .map(|f| (f.discord_id, value_for(f).unwrap_or(0.0)))
+                .collect();
+            split::by_percent(total, &percents, remainder_to)
+        }
+        "days" => {
+            let (start, end) = match period {
+                Some(period) => period,
+                None => {
+                    return Err(String::from(
+                        "Please give the billing period to split by days, with `from` and `to`",
                     ))
-                    .color(EmbedColor::Red as u32)
-                    .fields({
-                        let mut fields: Vec<(String, String, bool)> =
-                            Vec::with_capacity(amounts.len());
-                        for (flatmate, amount) in amounts {
-                            if amount == 0.0 {
-                                continue;
-                            }
+                }
+            };
+
+            let tenures: Vec<(u64, Option<NaiveDate>, Option<NaiveDate>)> = flatmates
+                .iter()
+                .map(|f| (f.discord_id, f.move_in, f.move_out))
+                .collect();
+            split::by_days(total, &tenures, start, end, remainder_to)
+        }
+        _ => return Err(format!("`{}` isn't a valid way to split a bill", mode)),
+    };
+
+    result.map_err(|e| e.to_string())
+}
 
-                            fields.push((
-                                format!("Amount for {} to pay:", flatmate.display_name),
-                                format!("${:.2}", amount),
-                                false,
-                            ));
+/// build the embed for a bill, marking off any shares which have been paid
+fn create_bill_embed(
+    purpose: &str,
+    created_by: u64,
+    created_at: NaiveDateTime,
+    account: &str,
+    total: Money,
+    period: Option<(NaiveDate, NaiveDate)>,
+    shares: &[BillShare],
+) -> CreateEmbed {
+    let all_paid = shares.iter().all(|share| share.paid_at.is_some());
+    let disputed = shares
+        .iter()
+        .any(|share| matches!(&share.dispute, Some((_, outcome)) if outcome == DISPUTE_OPEN));
+    let period = match period {
+        Some((start, end)) => format!(
+            ", covering {} to {}",
+            start.format("%d/%m/%y"),
+            end.format("%d/%m/%y")
+        ),
+        None => String::new(),
+    };
+
+    CreateEmbed::new()
+        .title("Bill created")
+        .description(format!(
+            "Bill for {}{} totalling {} created by {} on {} to be paid into `{}`",
+            purpose,
+            period,
+            total,
+            display_name(created_by),
+            created_at.format("%d/%m/%y at %I:%M%P"),
+            account
+        ))
+        .color(if all_paid {
+            EmbedColor::Green as u32
+        } else if disputed {
+            EmbedColor::Orange as u32
+        } else {
+            EmbedColor::Red as u32
+        })
+        .fields(shares.iter().map(|share| {
+            let (name, value) = match share.paid_at {
+                Some(paid_at) => (
+                    format!("{} paid {} on:", display_name(share.user), share.amount),
+                    paid_at.format("%d/%m/%y at %I:%M%P").to_string(),
+                ),
+                None => (
+                    format!("Amount for {} to pay:", display_name(share.user)),
+                    match &share.reference {
+                        Some(reference) => {
+                            format!("{}, use reference `{}`", share.amount, reference)
                         }
+                        None => share.amount.to_string(),
+                    },
+                ),
+            };
 
-                        fields
-                    })
-                    .footer(CreateEmbedFooter::new(format!(
-                        "\n{}",
-                        CONFIG.phrases[rand::random::<usize>() % CONFIG.phrases.len()]
-                    ))),
+            let value = match &share.dispute {
+                Some((reason, outcome)) if outcome == DISPUTE_OPEN => {
+                    format!("{}\nDisputed: {}", value, reason)
+                }
+                Some((_, outcome)) if outcome == DISPUTE_ACCEPTED => {
+                    format!("{}\nDispute accepted, share adjusted", value)
+                }
+                Some(_) => format!("{}\nDispute rejected", value),
+                None => value,
+            };
+
+            (name, value, false)
+        }))
+}
+
+/// build the buttons attached to a bill, which are removed once everyone has paid
+fn create_bill_components(receipt: &str, shares: &[BillShare]) -> Vec<CreateActionRow> {
+    if shares.iter().all(|share| share.paid_at.is_some()) {
+        return Vec::with_capacity(0);
+    }
+
+    let mut buttons = vec![
+        CreateButton::new("paid")
+            .style(ButtonStyle::Success)
+            .label("Paid!"),
+        CreateButton::new(DISPUTE_BUTTON)
+            .style(ButtonStyle::Secondary)
+            .label("Dispute"),
+    ];
+
+    // scheduled bills are posted without a receipt
+    if !receipt.is_empty() {
+        buttons.push(CreateButton::new_link(receipt).label("Receipt"));
+    }
+
+    vec![CreateActionRow::Buttons(buttons)]
+}
+
+/// build the embed for a bill which has just been created, with a random phrase in the footer
+fn create_new_bill_embed(created_by: u64, payment: &NewPayment<'_>) -> CreateEmbed {
+    let shares = BillShare::unpaid(payment);
+
+    create_bill_embed(
+        payment.purpose,
+        created_by,
+        chrono::offset::Local::now().naive_local(),
+        payment.account,
+        payment.total_amount,
+        payment.period,
+        &shares,
+    )
+    .footer(CreateEmbedFooter::new(format!("\n{}", random_phrase())))
+}
+
+/// rebuild the message for a bill from its stored state, keeping the original footer
+async fn edit_bill_message(
+    ctx: &Context,
+    message: &mut Message,
+    payment: &PaymentModel,
+) -> Result<(), serenity::Error> {
+    let shares = BillShare::from_payment(payment);
+    let footer = message
+        .embeds
+        .get(0)
+        .and_then(|embed| embed.footer.as_ref())
+        .map(|footer| footer.text.clone())
+        .unwrap_or_default();
+
+    let mut embed = create_bill_embed(
+        &payment.purpose,
+        payment.created_by as u64,
+        payment.created_at,
+        &payment.account,
+        Money::from_cents(payment.total_amount),
+        payment.period_start.zip(payment.period_end),
+        &shares,
+    )
+    .footer(CreateEmbedFooter::new(footer));
+    let mut components = create_bill_components(&payment.receipt_url, &shares);
+
+    // a voided bill is kept for the record, but nobody needs to pay it
+    if let Some(voided_at) = payment.voided_at {
+        embed = embed
+            .title(format!(
+                "Bill voided on {}",
+                voided_at.format("%d/%m/%y at %I:%M%P")
+            ))
+            .color(EmbedColor::Grey as u32);
+        components = Vec::with_capacity(0);
+    }
+
+    message
+        .edit(ctx, EditMessage::new().embed(embed).components(components))
+        .await
+}
+
+/// find the message for a bill and rebuild it from its stored state
+pub async fn refresh_bill_message(
+    ctx: &Context,
+    payment: &PaymentModel,
+) -> Result<(), serenity::Error> {
+    let mut message = ChannelId::new(payment.channel_id as u64)
+        .message(ctx, MessageId::new(payment.originating_message_id as u64))
+        .await?;
+
+    edit_bill_message(ctx, &mut message, payment).await
+}
+
+/// warn the channel a bill was posted in if the bill took its category past one of the thresholds
+/// of the category's monthly budget
+async fn check_budget(
+    ctx: &Context,
+    app_state: &AppState,
+    channel_id: ChannelId,
+    guild_id: Option<u64>,
+    payment: &PaymentModel,
+) {
+    let category = CONFIG
+        .categories
+        .iter()
+        .find(|category| budget::in_category(&payment.purpose, &[&category.name, &category.label]));
+    let (category, limit) = match category.and_then(|c| c.budget.map(|b| (c, b))) {
+        Some((category, limit)) => (category, Money::from_dollars(limit)),
+        None => return,
+    };
+
+    let payments = match app_state.get_guild_payment_history(guild_id).await {
+        Ok(payments) => payments,
+        Err(e) => {
+            error!("error communicating with database: {}", e);
+            return;
+        }
+    };
+
+    let after = budget::spent(
+        &payments,
+        &[&category.name, &category.label],
+        payment.created_at.date(),
+    );
+    let before = after - Money::from_cents(payment.total_amount);
+    let threshold = match budget::crossed(limit, before, after) {
+        Some(threshold) => threshold,
+        None => return,
+    };
+
+    let embed = CreateEmbed::new()
+        .title(match threshold {
+            100 => format!("{} is over budget", category.label),
+            _ => format!("{} is nearly over budget", category.label),
+        })
+        .description(format!(
+            "Bills for {} this month now total {}, {}% of the {} monthly budget.",
+            category.label,
+            after,
+            budget::percent_used(limit, after),
+            limit
+        ))
+        .color(match threshold {
+            100 => EmbedColor::Red as u32,
+            _ => EmbedColor::Orange as u32,
+        });
+
+    if let Err(e) = channel_id
+        .send_message(ctx, CreateMessage::new().embed(embed))
+        .await
+    {
+        error!("failed to post budget warning: {}", e);
+    }
+}
+
+/// post a new bill into a channel without a receipt, and record it in the database.
+/// Used for bills which are created on a schedule rather than by a command.
+pub async fn post_bill(
+    ctx: &Context,
+    app_state: &AppState,
+    channel_id: ChannelId,
+    guild_id: Option<u64>,
+    created_by: u64,
+    payment: NewPayment<'_>,
+) -> DatabaseResult<PaymentModel> {
+    let users: Vec<u64> = payment.shares.iter().map(|(user, _)| *user).collect();
+    let references = share_references(
+        app_state,
+        guild_id,
+        payment.purpose,
+        chrono::offset::Local::now().date_naive(),
+        &users,
+    )
+    .await?;
+    let payment = NewPayment {
+        references,
+        ..payment
+    };
+    let shares = BillShare::unpaid(&payment);
+
+    let message = channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new()
+                .embed(create_new_bill_embed(created_by, &payment))
+                .components(create_bill_components(payment.receipt_url, &shares)),
+        )
+        .await?;
+
+    let payment = match app_state
+        .add_payment(
+            created_by,
+            message.id.into(),
+            channel_id.into(),
+            guild_id,
+            payment,
+        )
+        .await
+    {
+        Ok(payment) => payment,
+        Err(e) => {
+            // don't leave a bill up whose buttons refer to nothing
+            if let Err(inner_e) = message.delete(ctx).await {
+                error!("error deleting unsaved bill: {}", inner_e);
+            }
+            return Err(e);
+        }
+    };
+
+    check_budget(ctx, app_state, channel_id, guild_id, &payment).await;
+    Ok(payment)
+}
+
+/// Keep our own copy of a receipt, as the discord link will eventually expire. Returns the contents
+/// of the receipt, its name in the archive and the url to link to it with, which is the archived
+/// copy if it can be reached and the discord attachment otherwise.
+pub async fn archive_receipt(
+    app_state: &AppState,
+    receipt: &Attachment,
+) -> Result<(Vec<u8>, String, String), CommandResponse> {
+    let contents = match receipt.download().await {
+        Ok(contents) => contents,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "Failed to download receipt: {}",
+                e
+            )));
+        }
+    };
+    let image_path = match app_state.receipts.store(&contents, &receipt.filename).await {
+        Ok(name) => name,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "Failed to archive receipt: {}",
+                e
+            )));
+        }
+    };
+
+    let receipt_url = app_state
+        .receipts
+        .url(&image_path)
+        .unwrap_or_else(|| receipt.url.clone());
+
+    Ok((contents, image_path, receipt_url))
+}
+
+/// tell whoever is posting a bill that it couldn't be posted, once the response has been deferred
+async fn bill_failed(
+    interaction: &CommandInteraction,
+    ctx: &Context,
+    response: CommandResponse,
+    message: &str,
+) -> CommandResponse {
+    response.write_to_log();
+    if let Err(e) = interaction
+        .edit_response(ctx, EditInteractionResponse::new().content(message))
+        .await
+    {
+        error!("error editing message to return error: {}", e);
+    }
+    CommandResponse::NoResponse
+}
+
+/// post a new bill in response to a command, and record it in the database
+async fn create_bill<'b>(
+    interaction: &'b CommandInteraction,
+    app_state: &'b AppState,
+    ctx: &'b Context,
+    receipt: &'b Attachment,
+    payment: NewPayment<'b>,
+) -> Result<CommandResponse, CommandResponse> {
+    let created_by: u64 = interaction.user.id.into();
+    let guild_id: Option<u64> = interaction.guild_id.map(|g| g.0.into());
+
+    // archiving the receipt and giving out references can take longer than discord waits for a
+    // response, so respond first and fill the bill in after
+    if let Err(e) = interaction
+        .create_response(
+            &ctx,
+            CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+        )
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with discord: {}",
+            e
+        )));
+    }
+
+    let (contents, image_path, receipt_url) = match archive_receipt(app_state, receipt).await {
+        Ok(archived) => archived,
+        Err(response) => {
+            return Err(bill_failed(
+                interaction,
+                ctx,
+                response,
+                "error saving the receipt, this bill has not been posted",
             )
-            .add_file(CreateAttachment::url(ctx, receipt).await.unwrap()) //XXX: handle error
-            .components({
-                let mut components = Vec::with_capacity(2);
-                components.push(CreateActionRow::Buttons({
-                    vec![
-                        CreateButton::new("paid")
-                            .style(ButtonStyle::Success)
-                            .label("Paid!"),
-                        CreateButton::new_link(receipt).label("Receipt"),
-                    ]
-                }));
-                components
-            }),
+            .await);
+        }
+    };
+    let users: Vec<u64> = payment.shares.iter().map(|(user, _)| *user).collect();
+    let references = match share_references(
+        app_state,
+        guild_id,
+        payment.purpose,
+        chrono::offset::Local::now().date_naive(),
+        &users,
     )
+    .await
+    {
+        Ok(references) => references,
+        Err(e) => {
+            let response = CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            ));
+            return Err(bill_failed(
+                interaction,
+                ctx,
+                response,
+                "error communicating with database, this bill has not been posted",
+            )
+            .await);
+        }
+    };
+    let payment = NewPayment {
+        receipt_url: &receipt_url,
+        image_path: &image_path,
+        references,
+        ..payment
+    };
+    let shares = BillShare::unpaid(&payment);
+
+    let response = EditInteractionResponse::new()
+        .embed(create_new_bill_embed(created_by, &payment))
+        .new_attachment(CreateAttachment::bytes(contents, receipt.filename.clone()))
+        .components(create_bill_components(payment.receipt_url, &shares));
+
+    // save the bill against the deferred response before its buttons are shown, so that nothing
+    // which looks like a bill is ever posted without being saved
+    let message = match interaction.get_response(&ctx).await {
+        Ok(m) => m,
+        Err(e) => {
+            let response = CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            ));
+            return Err(bill_failed(
+                interaction,
+                ctx,
+                response,
+                "error communicating with discord, this bill has not been posted",
+            )
+            .await);
+        }
+    };
+
+    let payment = match app_state
+        .add_payment(
+            created_by,
+            message.id.into(),
+            interaction.channel_id.into(),
+            guild_id,
+            payment,
+        )
+        .await
+    {
+        Ok(payment) => payment,
+        Err(e) => {
+            let response = CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            ));
+            return Err(bill_failed(
+                interaction,
+                ctx,
+                response,
+                "error communicating with database, this bill has not been posted",
+            )
+            .await);
+        }
+    };
+
+    if let Err(e) = interaction.edit_response(&ctx, response).await {
+        return Err(CommandResponse::InternalFailure(format!(
+            "Failed to edit interaction response: {}",
+            e
+        )));
+    }
+
+    check_budget(ctx, app_state, interaction.channel_id, guild_id, &payment).await;
+
+    Ok(CommandResponse::NoResponse)
 }
 
 pub struct PayCommand {}
@@ -205,6 +890,7 @@ impl<'a> Command<'a> for PayCommand {
                     flatmate.name.to_ascii_lowercase(),
                     format!("The amount for {} to pay.", flatmate.name),
                 )
+                .min_number_value(0.0)
                 .required(true)
                 .set_autocomplete(true),
             );
@@ -214,7 +900,7 @@ impl<'a> Command<'a> for PayCommand {
             CreateCommandOption::new(
                 CommandOptionType::String,
                 "account",
-                "The account number to pay into, defaults to head tenant account.",
+                "The account number to pay into, defaults to yours if it is set up, or the head tenant's",
             )
             .required(false),
         )
@@ -223,7 +909,7 @@ impl<'a> Command<'a> for PayCommand {
     async fn handle_application_command<'b>(
         self,
         interaction: &'b CommandInteraction,
-        _: &'b AppState,
+        app_state: &'b AppState,
         ctx: &'b Context,
     ) -> Result<CommandResponse, CommandResponse> {
         // extract the options
@@ -231,9 +917,8 @@ impl<'a> Command<'a> for PayCommand {
 
         let mut purpose: Option<&str> = None;
         let mut receipt: Option<&Attachment> = None;
-        let mut amount = 0.0;
-        let mut amounts: Vec<(&Flatmate, f64)> = Vec::with_capacity(CONFIG.flatmates.len());
-        let mut account = CONFIG.head_tennant_acc_number.as_ref();
+        let mut amounts: Vec<(&Flatmate, Money)> = Vec::with_capacity(CONFIG.flatmates.len());
+        let mut account: Option<&str> = None;
 
         for option in options.iter() {
             match option.name {
@@ -257,7 +942,7 @@ impl<'a> Command<'a> for PayCommand {
                 }
                 "account" => {
                     if let ResolvedValue::String(s) = option.value {
-                        account = s;
+                        account = Some(s);
                     } else {
                         return Err(CommandResponse::InternalFailure(
                             "Failed to parse account as a string".to_string(),
@@ -268,10 +953,9 @@ impl<'a> Command<'a> for PayCommand {
                     let name = option.name;
 
                     if let ResolvedValue::Number(value) = option.value {
-                        amount += value;
                         amounts.push((
                             CONFIG.flatmates.iter().find(|f| f.name == name).unwrap(),
-                            value,
+                            Money::from_dollars(value),
                         ));
                     } else {
                         return Err(CommandResponse::InternalFailure(
@@ -291,30 +975,42 @@ impl<'a> Command<'a> for PayCommand {
 
         let purpose = purpose.unwrap();
         let receipt = receipt.unwrap();
-
-        if let Err(e) = interaction
-            .create_response(
-                &ctx,
-                create_response(
-                    purpose,
-                    &interaction.user.name,
-                    &receipt.url,
-                    amount,
-                    amounts,
-                    account,
-                    ctx,
-                )
-                .await,
-            )
-            .await
-        {
-            return Err(CommandResponse::InternalFailure(format!(
-                "Failed to create interaction response: {}",
-                e
+        if amounts.iter().any(|(_, amount)| *amount < Money::ZERO) {
+            return Err(CommandResponse::BasicFailure(String::from(
+                "An amount to pay can't be negative.",
+            )));
+        }
+        // a flatmate with nothing to pay isn't part of the bill
+        amounts.retain(|(_, amount)| !amount.is_zero());
+        if amounts.is_empty() {
+            return Err(CommandResponse::BasicFailure(String::from(
+                "A bill needs at least one share, give someone an amount to pay.",
             )));
         }
+        let account = payout_account(interaction.user.id.into(), account)
+            .map_err(CommandResponse::BasicFailure)?;
 
-        Ok(CommandResponse::NoResponse)
+        create_bill(
+            interaction,
+            app_state,
+            ctx,
+            receipt,
+            NewPayment {
+                purpose,
+                account: &account,
+                receipt_url: &receipt.url,
+                image_path: "",
+                // the total is the sum of the rounded shares, so the two always agree
+                total_amount: amounts.iter().map(|(_, amount)| amount).sum(),
+                shares: amounts
+                    .into_iter()
+                    .map(|(flatmate, amount)| (flatmate.discord_id, amount))
+                    .collect(),
+                references: Vec::new(),
+                period: None,
+            },
+        )
+        .await
     }
 }
 
@@ -322,131 +1018,119 @@ impl<'a> Command<'a> for PayCommand {
 impl<'a> InteractionCommand<'a> for PayCommand {
     async fn answerable<'b>(
         interaction: &'b ComponentInteraction,
-        _: &'b AppState,
+        app_state: &'b AppState,
         _: &'b Context,
     ) -> bool {
-        //XXX: eventually it'll be good to store message id's in the database, and react to those *specifically*
-        if let Some(embed) = interaction.message.embeds.get(0) {
-            if let Some(description) = embed.description.as_ref() {
-                return description.starts_with("Bill for ");
+        if dispute::is_dispute(&interaction.data.custom_id) {
+            return true;
+        }
+
+        let msg_id: u64 = interaction.message.id.into();
+        match app_state.get_payment_by_message_id(msg_id).await {
+            Ok(Some(_)) => true,
+            Ok(None) => false,
+            Err(e) => {
+                error!("error communicating with database: {}", e);
+                false
             }
         }
-        false
     }
 
     async fn interaction<'b>(
         interaction: &'b ComponentInteraction,
-        _: &'b AppState,
+        app_state: &'b AppState,
         ctx: &'b Context,
     ) -> Result<CommandResponse, CommandResponse> {
-        if interaction.member.is_none() {
-            return Err(CommandResponse::InternalFailure(
-                "Failed to get member".to_string(),
-            ));
+        // disputes are answered from a DM, rather than the bill's message
+        if dispute::is_dispute(&interaction.data.custom_id) {
+            return dispute::answer_dispute(interaction, app_state, ctx).await;
         }
 
+        let msg_id: u64 = interaction.message.id.into();
         let user: u64 = interaction.user.id.into();
-        let user: Option<&Flatmate> = CONFIG
-            .flatmates
-            .iter()
-            .find(|flatmate| flatmate.discord_id == user);
 
-        if user.is_none() {
-            return Err(CommandResponse::InternalFailure(
-                "Failed to get user".to_string(),
-            ));
+        let payment = match app_state.get_payment_by_message_id(msg_id).await {
+            Ok(Some(payment)) => payment,
+            Ok(None) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "no payment found for message {}",
+                    msg_id
+                )));
+            }
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if payment.voided_at.is_some() {
+            return Err(CommandResponse::BasicFailure(String::from(
+                "This bill has been voided, there's nothing to pay.",
+            )));
         }
-        let user = user.unwrap();
-        let mut message = interaction.message.clone();
-        let current_time = chrono::offset::Local::now().format("%d/%m/%y at %I:%M%P");
-        let mut all_set = 0;
 
-        if message.embeds.len() != 1 {
-            return Err(CommandResponse::InternalFailure(
-                "Invalid embeds in message".to_string(),
-            ));
+        if !payment.individual_users.contains(&(user as i64)) {
+            return Err(CommandResponse::BasicFailure(String::from(
+                "You don't have a share in this bill!",
+            )));
         }
 
-        let mut edit_message = EditMessage::new().embed(
-            CreateEmbed::new()
-                .description(
-                    interaction.message.embeds[0]
-                        .description
-                        .as_ref()
-                        .unwrap_or(&String::from("")),
-                )
-                .footer(CreateEmbedFooter::new({
-                    interaction.message.embeds[0]
-                        .footer
-                        .as_ref()
-                        .expect("footer to be present")
-                        .text
-                        .clone()
-                }))
-                .fields({
-                    let mut fields: Vec<(String, String, bool)> =
-                        Vec::with_capacity(message.embeds[0].fields.len());
-                    for field in message.embeds[0].fields.iter() {
-                        if field.name.contains("paid") {
-                            all_set += 1;
-                        }
-                        if field.name.to_lowercase().contains(&user.name)
-                            && field.name.contains("pay")
-                        {
-                            fields.push((
-                                format!(
-                                    "{}{} paid {} on:",
-                                    user.name[0..1].to_uppercase(),
-                                    &user.name[1..],
-                                    field.value
-                                ),
-                                current_time.to_string(),
-                                field.inline,
-                            ));
-                            all_set += 1;
-                        } else {
-                            fields.push((field.name.clone(), field.value.clone(), field.inline));
-                        }
-                    }
-                    fields
-                })
-                .color({
-                    if all_set == message.embeds[0].fields.len() {
-                        EmbedColor::Green as u32
-                    } else {
-                        EmbedColor::Red as u32
-                    }
-                }),
-        );
+        if payment.paid_users.contains(&(user as i64)) {
+            return Err(CommandResponse::BasicFailure(String::from(
+                "You've already paid this bill!",
+            )));
+        }
 
-        if all_set == message.embeds[0].fields.len() {
-            edit_message = edit_message.components(Vec::with_capacity(0));
+        if interaction.data.custom_id == DISPUTE_BUTTON {
+            return dispute::start_dispute(interaction, &payment);
         }
 
-        if let Err(e) = message.edit(&ctx, edit_message).await {
+        let payment = match app_state.set_payment_share_paid(payment.id, user).await {
+            Ok(payment) => payment,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        let mut message = interaction.message.clone();
+        if let Err(e) = edit_bill_message(ctx, &mut message, &payment).await {
             return Err(CommandResponse::InternalFailure(format!(
                 "Failed to edit message: {}",
                 e
             )));
         }
 
-        interaction
-            .create_response(
-                &ctx,
-                CreateInteractionResponse::Message(
-                    CreateInteractionResponseMessage::new()
-                        .content(format!(
-                            "{}{} paid!",
-                            user.name[0..1].to_uppercase(),
-                            &user.name[1..]
-                        ))
-                        .ephemeral(true),
-                ),
-            )
-            .await
-            .unwrap();
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!("{} paid!", display_name(user)))
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
+
+#[async_trait]
+impl<'a> ModalCommand<'a> for PayCommand {
+    async fn answerable<'b>(
+        interaction: &'b ModalInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        dispute::is_dispute(&interaction.data.custom_id)
+    }
 
-        Ok(CommandResponse::NoResponse)
+    async fn modal<'b>(
+        interaction: &'b ModalInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        dispute::submit_dispute(interaction, app_state, ctx).await
     }
 }
 
@@ -480,7 +1164,7 @@ impl<'a> Command<'a> for PayAllCommand {
     }
 
     fn description() -> &'static str {
-        "Evenly split a bill between all flatmates"
+        "Split a bill between all flatmates, or everyone except some of them"
     }
 
     fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
@@ -503,13 +1187,68 @@ impl<'a> Command<'a> for PayAllCommand {
         )
         .add_option(
             CreateCommandOption::new(CommandOptionType::Number, "amount", "The amount to pay")
+                .min_number_value(0.01)
                 .required(true),
         )
         .add_option(
             CreateCommandOption::new(
                 CommandOptionType::String,
                 "account",
-                "The account number to pay into, defaults to head tenant account.",
+                "The account number to pay into, defaults to yours if it is set up, or the head tenant's",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "split",
+                "How to split the bill, defaults to each flatmate's configured weight",
+            )
+            .add_string_choice("even", "even")
+            .add_string_choice("weights", "weights")
+            .add_string_choice("shares", "shares")
+            .add_string_choice("percent", "percent")
+            .add_string_choice("days in residence", "days")
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "shares",
+                "Shares or percentages for the split, e.g. `john=1.2, peter=1`",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "from",
+                "The first day the bill covers, as YYYY-MM-DD",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "to",
+                "The last day the bill covers, as YYYY-MM-DD",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "except",
+                "Flatmates who aren't part of this bill, e.g. `peter, john`",
+            )
+            .required(false)
+            .set_autocomplete(true),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::User,
+                "remainder",
+                "Who pays any cents left over from rounding, defaults to you",
             )
             .required(false),
         )
@@ -518,7 +1257,7 @@ impl<'a> Command<'a> for PayAllCommand {
     async fn handle_application_command<'b>(
         self,
         interaction: &'b CommandInteraction,
-        _: &'b AppState,
+        app_state: &'b AppState,
         ctx: &'b Context,
     ) -> Result<CommandResponse, CommandResponse> {
         // extract the options
@@ -527,7 +1266,13 @@ impl<'a> Command<'a> for PayAllCommand {
         let mut purpose: Option<&str> = None;
         let mut receipt: Option<&Attachment> = None;
         let mut amount: Option<f64> = None;
-        let mut account: &str = &CONFIG.head_tennant_acc_number;
+        let mut account: Option<&str> = None;
+        let mut split: &str = "weights";
+        let mut split_values: Option<&str> = None;
+        let mut from: Option<&str> = None;
+        let mut to: Option<&str> = None;
+        let mut remainder_to: u64 = interaction.user.id.into();
+        let mut excluded: Vec<u64> = Vec::new();
 
         for option in options.iter() {
             match option.name {
@@ -560,13 +1305,67 @@ impl<'a> Command<'a> for PayAllCommand {
                 }
                 "account" => {
                     if let ResolvedValue::String(s) = option.value {
-                        account = s;
+                        account = Some(s);
                     } else {
                         return Err(CommandResponse::InternalFailure(
                             "Failed to parse account as a string".to_string(),
                         ));
                     }
                 }
+                "split" => {
+                    if let ResolvedValue::String(s) = option.value {
+                        split = s;
+                    } else {
+                        return Err(CommandResponse::InternalFailure(
+                            "Failed to parse split as a string".to_string(),
+                        ));
+                    }
+                }
+                "shares" => {
+                    if let ResolvedValue::String(s) = option.value {
+                        split_values = Some(s);
+                    } else {
+                        return Err(CommandResponse::InternalFailure(
+                            "Failed to parse shares as a string".to_string(),
+                        ));
+                    }
+                }
+                "from" => {
+                    if let ResolvedValue::String(s) = option.value {
+                        from = Some(s);
+                    } else {
+                        return Err(CommandResponse::InternalFailure(
+                            "Failed to parse from as a string".to_string(),
+                        ));
+                    }
+                }
+                "to" => {
+                    if let ResolvedValue::String(s) = option.value {
+                        to = Some(s);
+                    } else {
+                        return Err(CommandResponse::InternalFailure(
+                            "Failed to parse to as a string".to_string(),
+                        ));
+                    }
+                }
+                "except" => {
+                    if let ResolvedValue::String(s) = option.value {
+                        excluded = parse_flatmate_list(s).map_err(CommandResponse::BasicFailure)?;
+                    } else {
+                        return Err(CommandResponse::InternalFailure(
+                            "Failed to parse except as a string".to_string(),
+                        ));
+                    }
+                }
+                "remainder" => {
+                    if let ResolvedValue::User(user, _) = option.value {
+                        remainder_to = user.id.into();
+                    } else {
+                        return Err(CommandResponse::InternalFailure(
+                            "Failed to parse remainder as a user".to_string(),
+                        ));
+                    }
+                }
                 _ => {
                     return Err(CommandResponse::InternalFailure(
                         "Invalid option".to_string(),
@@ -584,37 +1383,61 @@ impl<'a> Command<'a> for PayAllCommand {
         let purpose = purpose.unwrap();
         let amount = amount.unwrap();
         let receipt = receipt.unwrap();
+        let account = payout_account(interaction.user.id.into(), account)
+            .map_err(CommandResponse::BasicFailure)?;
 
-        // parse response and create message
-        let mut amounts: Vec<(&Flatmate, f64)> = Vec::with_capacity(CONFIG.flatmates.len());
-        let individual = amount / CONFIG.flatmates.len() as f64;
-        for flatmate in CONFIG.flatmates.iter() {
-            amounts.push((flatmate, individual));
-        }
-
-        if let Err(e) = interaction
-            .create_response(
-                &ctx,
-                create_response(
-                    purpose,
-                    &interaction.user.name,
-                    &receipt.url,
-                    amount,
-                    amounts,
-                    account,
-                    ctx,
-                )
-                .await,
-            )
-            .await
-        {
-            return Err(CommandResponse::InternalFailure(format!(
-                "Failed to create interaction response: {}",
-                e
+        // the billing period is optional, but needs both ends if it is given
+        let period = match (parse_date(from)?, parse_date(to)?) {
+            (Some(from), Some(to)) if from > to => {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "The first day the bill covers is after the last.",
+                )));
+            }
+            (Some(from), Some(to)) => Some((from, to)),
+            (None, None) => None,
+            _ => {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "Please give both the first and last day the bill covers.",
+                )));
+            }
+        };
+
+        // work out each flatmate's share of the bill
+        let total_amount = Money::from_dollars(amount);
+        if total_amount <= Money::ZERO {
+            return Err(CommandResponse::BasicFailure(String::from(
+                "The amount to pay has to be more than $0.00.",
             )));
         }
+        let shares = match split_bill(
+            total_amount,
+            split,
+            split_values,
+            period,
+            &excluded,
+            remainder_to,
+        ) {
+            Ok(shares) => shares,
+            Err(e) => return Err(CommandResponse::BasicFailure(e)),
+        };
 
-        Ok(CommandResponse::NoResponse)
+        create_bill(
+            interaction,
+            app_state,
+            ctx,
+            receipt,
+            NewPayment {
+                purpose,
+                account: &account,
+                receipt_url: &receipt.url,
+                image_path: "",
+                total_amount,
+                shares,
+                references: Vec::new(),
+                period,
+            },
+        )
+        .await
     }
 }
 
@@ -0,0 +1,426 @@
+use serenity::{
+    all::{
+        ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, GuildId,
+        ResolvedValue, UserId,
+    },
+    async_trait,
+    builder::{
+        CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
+        CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::embed::EmbedColor,
+        database::{
+            payments::{PaymentModel, SerenityPaymentDatabase},
+            reminders::{ReminderSettingsModel, SerenityReminderDatabase},
+        },
+    },
+    finance::money::Money,
+    state::AppState,
+};
+
+use super::{
+    command::{Command, InteractionCommand},
+    pay::refresh_bill_message,
+    util::{display_name, CommandResponse},
+};
+
+/// the prefix for the custom id of the "Mark paid" button sent in a reminder.
+/// The full id is `remind:<guild id>:<payment id>`, as interactions in DMs don't carry a guild id.
+const REMIND_PREFIX: &str = "remind:";
+
+/// the settings used for a guild until they are changed with `/reminders set`
+fn default_settings(guild_id: u64) -> ReminderSettingsModel {
+    ReminderSettingsModel {
+        guild_id: guild_id as i64,
+        enabled: false,
+        unpaid_after_hours: 72,
+        interval_hours: 48,
+        quiet_start: 22,
+        quiet_end: 8,
+    }
+}
+
+/// whether the given hour of the day falls in the quiet hours, which may wrap past midnight
+pub fn in_quiet_hours(hour: u32, settings: &ReminderSettingsModel) -> bool {
+    let (start, end) = (settings.quiet_start as u32, settings.quiet_end as u32);
+
+    match start.cmp(&end) {
+        std::cmp::Ordering::Equal => false,
+        std::cmp::Ordering::Less => hour >= start && hour < end,
+        std::cmp::Ordering::Greater => hour >= start || hour < end,
+    }
+}
+
+/// send a flatmate a DM reminding them to pay their share of a bill
+pub async fn send_reminder(
+    ctx: &Context,
+    guild_id: GuildId,
+    payment: &PaymentModel,
+    user: u64,
+) -> Result<(), serenity::Error> {
+    let share = payment
+        .individual_users
+        .iter()
+        .position(|u| *u as u64 == user);
+    let amount = share
+        .and_then(|i| payment.individual_amounts.get(i))
+        .map(|amount| Money::from_cents(*amount))
+        .unwrap_or_default();
+    let reference = match share.and_then(|i| payment.share_references.get(i)) {
+        Some(reference) => format!(" with reference `{}`", reference),
+        None => String::new(),
+    };
+
+    let embed = CreateEmbed::new()
+        .title("Reminder: unpaid bill")
+        .description(format!(
+            "You still owe {} {} for {}, from the bill created on {}. Please pay into `{}`{}, then press \"Mark paid\".",
+            display_name(payment.created_by as u64),
+            amount,
+            payment.purpose,
+            payment.created_at.format("%d/%m/%y"),
+            payment.account,
+            reference
+        ))
+        .color(EmbedColor::Orange as u32);
+
+    let buttons = vec![
+        CreateButton::new(format!("{}{}:{}", REMIND_PREFIX, guild_id, payment.id))
+            .style(ButtonStyle::Success)
+            .label("Mark paid"),
+        CreateButton::new_link(format!(
+            "https://discord.com/channels/{}/{}/{}",
+            guild_id, payment.channel_id, payment.originating_message_id
+        ))
+        .label("View bill"),
+    ];
+
+    UserId::new(user)
+        .direct_message(
+            ctx,
+            CreateMessage::new()
+                .embed(embed)
+                .components(vec![CreateActionRow::Buttons(buttons)]),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Configure the DMs sent to flatmates who haven't paid their share of a bill
+pub enum RemindersCommand {
+    Show,
+    Set {
+        after_hours: Option<i64>,
+        interval_hours: Option<i64>,
+        quiet_start: Option<i64>,
+        quiet_end: Option<i64>,
+    },
+    Off,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for RemindersCommand {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let subcommand = interaction
+            .data
+            .options()
+            .into_iter()
+            .next()
+            .ok_or("No subcommand provided")?;
+
+        match (subcommand.name, subcommand.value) {
+            ("show", ResolvedValue::SubCommand(_)) => Ok(RemindersCommand::Show),
+            ("off", ResolvedValue::SubCommand(_)) => Ok(RemindersCommand::Off),
+            ("set", ResolvedValue::SubCommand(options)) => {
+                let mut after_hours = None;
+                let mut interval_hours = None;
+                let mut quiet_start = None;
+                let mut quiet_end = None;
+
+                for option in options {
+                    match (option.name, option.value) {
+                        ("after", ResolvedValue::Integer(val)) => after_hours = Some(val),
+                        ("every", ResolvedValue::Integer(val)) => interval_hours = Some(val),
+                        ("quiet-start", ResolvedValue::Integer(val)) => quiet_start = Some(val),
+                        ("quiet-end", ResolvedValue::Integer(val)) => quiet_end = Some(val),
+                        (opt, val) => {
+                            return Err(format!(
+                                "unexpected option name: `{}` and value `{:?}`",
+                                opt, val
+                            ))
+                        }
+                    }
+                }
+
+                Ok(RemindersCommand::Set {
+                    after_hours,
+                    interval_hours,
+                    quiet_start,
+                    quiet_end,
+                })
+            }
+            (opt, _) => Err(format!("unexpected subcommand `{}`", opt)),
+        }
+    }
+}
+
+/// build an embed describing the reminder settings for a guild
+fn create_settings_embed(settings: &ReminderSettingsModel) -> CreateEmbed {
+    let embed = CreateEmbed::new().title("Bill reminders");
+
+    if !settings.enabled {
+        return embed
+            .description("Reminders are off. Turn them on with `/reminders set`.")
+            .color(EmbedColor::Red as u32);
+    }
+
+    embed
+        .description(format!(
+            "Flatmates are sent a DM once their share of a bill has been unpaid for {} hours, and again every {} hours until it is paid. No reminders are sent between {:02}:00 and {:02}:00.",
+            settings.unpaid_after_hours,
+            settings.interval_hours,
+            settings.quiet_start,
+            settings.quiet_end
+        ))
+        .color(EmbedColor::Green as u32)
+}
+
+#[async_trait]
+impl<'a> Command<'a> for RemindersCommand {
+    fn name() -> &'static str {
+        "reminders"
+    }
+
+    fn description() -> &'static str {
+        "Configure DM reminders for unpaid bills"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "show",
+            "Show the current reminder settings",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "set",
+                "Turn reminders on, and change when they are sent",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "after",
+                    "How many hours a share must be unpaid before the first reminder",
+                )
+                .min_int_value(1)
+                .required(false),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "every",
+                    "How many hours to wait between reminders",
+                )
+                .min_int_value(1)
+                .required(false),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "quiet-start",
+                    "The hour (0-23) from which no reminders are sent",
+                )
+                .min_int_value(0)
+                .max_int_value(23)
+                .required(false),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "quiet-end",
+                    "The hour (0-23) at which reminders may be sent again",
+                )
+                .min_int_value(0)
+                .max_int_value(23)
+                .required(false),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "off",
+            "Stop sending reminders",
+        ))
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(guild_id) => guild_id.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "Reminders can only be set up in a server.",
+                )));
+            }
+        };
+
+        let mut settings = match app_state.get_reminder_settings(guild_id).await {
+            Ok(settings) => settings.unwrap_or_else(|| default_settings(guild_id)),
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        match self {
+            RemindersCommand::Show => {
+                return Ok(CommandResponse::ComplexSuccess(
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .embed(create_settings_embed(&settings)),
+                    ),
+                ));
+            }
+            RemindersCommand::Set {
+                after_hours,
+                interval_hours,
+                quiet_start,
+                quiet_end,
+            } => {
+                settings.enabled = true;
+                if let Some(after_hours) = after_hours {
+                    settings.unpaid_after_hours = after_hours as i32;
+                }
+                if let Some(interval_hours) = interval_hours {
+                    settings.interval_hours = interval_hours as i32;
+                }
+                if let Some(quiet_start) = quiet_start {
+                    settings.quiet_start = quiet_start as i32;
+                }
+                if let Some(quiet_end) = quiet_end {
+                    settings.quiet_end = quiet_end as i32;
+                }
+            }
+            RemindersCommand::Off => settings.enabled = false,
+        }
+
+        let settings = match app_state.set_reminder_settings(settings).await {
+            Ok(settings) => settings,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().embed(create_settings_embed(&settings)),
+            ),
+        ))
+    }
+}
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for RemindersCommand {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        interaction.data.custom_id.starts_with(REMIND_PREFIX)
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let user: u64 = interaction.user.id.into();
+        let id: i32 = match interaction.data.custom_id[REMIND_PREFIX.len()..]
+            .rsplit(':')
+            .next()
+            .map(str::parse)
+        {
+            Some(Ok(id)) => id,
+            _ => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "invalid payment id in custom id `{}`",
+                    interaction.data.custom_id
+                )));
+            }
+        };
+
+        let payment = match app_state.get_payment_by_id(id).await {
+            Ok(Some(payment)) => payment,
+            Ok(None) => {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "This bill no longer exists.",
+                )));
+            }
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if payment.voided_at.is_some() {
+            return Err(CommandResponse::BasicFailure(String::from(
+                "This bill has been voided, there's nothing to pay.",
+            )));
+        }
+
+        if !payment.individual_users.contains(&(user as i64)) {
+            return Err(CommandResponse::BasicFailure(String::from(
+                "You don't have a share in this bill!",
+            )));
+        }
+
+        if !payment.paid_users.contains(&(user as i64)) {
+            let payment = match app_state.set_payment_share_paid(payment.id, user).await {
+                Ok(payment) => payment,
+                Err(e) => {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+            };
+
+            if let Err(e) = refresh_bill_message(ctx, &payment).await {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with discord: {}",
+                    e
+                )));
+            }
+        }
+
+        // replace the buttons on the reminder, so it can't be pressed again
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "Marked as paid, thanks! I've updated the bill for {}.",
+                        payment.purpose
+                    ))
+                    .components(vec![]),
+            ),
+        ))
+    }
+}
@@ -0,0 +1,380 @@
+use log::error;
+use serenity::{
+    all::{ButtonStyle, CommandInteraction, ComponentInteraction},
+    async_trait,
+    builder::{
+        CreateActionRow, CreateButton, CreateCommand, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage, EditInteractionResponse, EditMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::embed::EmbedColor,
+        database::payments::{SerenityPaymentDatabase, SettlementModel},
+    },
+    finance::{ledger::Ledger, money::Money, settle::simplify},
+    state::AppState,
+};
+
+use super::{
+    command::{Command, InteractionCommand},
+    pay::refresh_bill_message,
+    util::{display_name, CommandResponse},
+};
+
+/// the prefix for the custom id of the "Done" button on each transfer
+const SETTLE_PREFIX: &str = "settle:";
+
+/// discord allows at most five rows of five buttons on a message, and as many fields on an embed
+const MAX_TRANSFERS_SHOWN: usize = 25;
+
+/// build the embed for a settle-up plan, marking off any transfers which have been made
+fn create_plan_embed(settlements: &[SettlementModel]) -> CreateEmbed {
+    let complete = settlements.iter().all(|s| s.settled_at.is_some());
+    let hidden = settlements.len().saturating_sub(MAX_TRANSFERS_SHOWN);
+
+    CreateEmbed::new()
+        .title("Settle-up plan")
+        .description(if complete {
+            String::from("Every transfer has been made, all bills in this plan are now paid.")
+        } else {
+            format!(
+                "{} transfer{} will settle everything owing. Press \"Done\" once you've made yours.{}",
+                settlements.len(),
+                if settlements.len() == 1 { "" } else { "s" },
+                if hidden > 0 {
+                    format!(
+                        "\nOnly the first {} are shown here, run `/settle` again once they're done \
+                         for the other {}.",
+                        MAX_TRANSFERS_SHOWN, hidden
+                    )
+                } else {
+                    String::new()
+                }
+            )
+        })
+        .color(if complete {
+            EmbedColor::Green as u32
+        } else {
+            EmbedColor::Red as u32
+        })
+        .fields(settlements.iter().take(MAX_TRANSFERS_SHOWN).map(|s| {
+            (
+                format!(
+                    "{} pays {}:",
+                    display_name(s.debtor as u64),
+                    display_name(s.creditor as u64)
+                ),
+                match s.settled_at {
+                    Some(settled_at) => format!(
+                        "~~{}~~ done on {}",
+                        Money::from_cents(s.amount),
+                        settled_at.format("%d/%m/%y at %I:%M%P")
+                    ),
+                    None => Money::from_cents(s.amount).to_string(),
+                },
+                false,
+            )
+        }))
+}
+
+/// build a "Done" button for every transfer shown which is still to be made
+fn create_plan_components(settlements: &[SettlementModel]) -> Vec<CreateActionRow> {
+    let buttons: Vec<CreateButton> = settlements
+        .iter()
+        .take(MAX_TRANSFERS_SHOWN)
+        .filter(|s| s.settled_at.is_none())
+        .map(|s| {
+            CreateButton::new(format!("{}{}", SETTLE_PREFIX, s.id))
+                .style(ButtonStyle::Success)
+                .label(format!(
+                    "Done: {} → {}",
+                    display_name(s.debtor as u64),
+                    display_name(s.creditor as u64)
+                ))
+        })
+        .collect();
+
+    // discord allows at most five buttons in a row
+    buttons
+        .chunks(5)
+        .map(|row| CreateActionRow::Buttons(row.to_vec()))
+        .collect()
+}
+
+pub struct SettleCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for SettleCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for SettleCommand {
+    fn name() -> &'static str {
+        "settle"
+    }
+
+    fn description() -> &'static str {
+        "Work out the fewest transfers needed to settle all outstanding bills"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: Option<u64> = interaction.guild_id.map(|g| g.0.into());
+
+        let payments = match app_state.get_guild_payments(guild_id).await {
+            Ok(payments) => payments,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        let settlements = match app_state.get_unapplied_settlements(guild_id).await {
+            Ok(settlements) => settlements,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        let transfers = simplify(&Ledger::new(&payments, &settlements).balances());
+        if transfers.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(String::from(
+                "Everyone is square, there is nothing to settle!",
+            )));
+        }
+
+        // only bills with something still owing are closed off when this plan completes
+        let payment_ids: Vec<i32> = payments
+            .iter()
+            .filter(|p| {
+                p.individual_users
+                    .iter()
+                    .any(|u| *u != p.created_by && !p.paid_users.contains(u))
+            })
+            .map(|p| p.id)
+            .collect();
+
+        // a new plan replaces any transfers from earlier plans which haven't been made yet
+        if let Err(e) = app_state.remove_unsettled_settlements(guild_id).await {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+
+        if let Err(e) = interaction
+            .create_response(
+                &ctx,
+                CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+            )
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        let plan_message = match interaction.get_response(&ctx).await {
+            Ok(m) => m,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with discord: {}",
+                    e
+                )));
+            }
+        };
+
+        let plan = match app_state
+            .add_settlements(
+                interaction.user.id.into(),
+                plan_message.id.into(),
+                interaction.channel_id.into(),
+                guild_id,
+                transfers
+                    .into_iter()
+                    .map(|t| (t.from, t.to, t.amount))
+                    .collect(),
+                payment_ids,
+            )
+            .await
+        {
+            Ok(plan) => plan,
+            Err(e) => {
+                error!("error adding settlements: {}", e);
+                if let Err(inner_e) = interaction
+                    .edit_response(
+                        &ctx,
+                        EditInteractionResponse::new().content("error communicating with database"),
+                    )
+                    .await
+                {
+                    error!("error editing message to return error: {}", inner_e);
+                }
+                return Err(CommandResponse::NoResponse);
+            }
+        };
+
+        if let Err(e) = interaction
+            .edit_response(
+                &ctx,
+                EditInteractionResponse::new()
+                    .embed(create_plan_embed(&plan))
+                    .components(create_plan_components(&plan)),
+            )
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for SettleCommand {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        interaction.data.custom_id.starts_with(SETTLE_PREFIX)
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let user: u64 = interaction.user.id.into();
+        let id: i32 = match interaction.data.custom_id[SETTLE_PREFIX.len()..].parse() {
+            Ok(id) => id,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "invalid settlement id in custom id `{}`: {}",
+                    interaction.data.custom_id, e
+                )));
+            }
+        };
+
+        let settlement = match app_state.get_settlement_by_id(id).await {
+            Ok(Some(settlement)) => settlement,
+            Ok(None) => {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "This settle-up plan has been replaced by a newer one.",
+                )));
+            }
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if user != settlement.debtor as u64 && user != settlement.creditor as u64 {
+            return Err(CommandResponse::BasicFailure(format!(
+                "Only {} or {} can mark this transfer as done.",
+                display_name(settlement.debtor as u64),
+                display_name(settlement.creditor as u64)
+            )));
+        }
+
+        if settlement.settled_at.is_some() {
+            return Err(CommandResponse::BasicFailure(String::from(
+                "This transfer has already been made!",
+            )));
+        }
+
+        if let Err(e) = app_state.set_settlement_settled(settlement.id).await {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+
+        let plan = match app_state
+            .get_plan_settlements(settlement.plan_message_id as u64)
+            .await
+        {
+            Ok(plan) => plan,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        // once every transfer has been made, the bills covered by the plan are paid in full
+        if plan.iter().all(|s| s.settled_at.is_some()) {
+            let payments = match app_state
+                .apply_settlements(
+                    settlement.guild_id.map(|g| g as u64),
+                    &settlement.payment_ids,
+                )
+                .await
+            {
+                Ok(payments) => payments,
+                Err(e) => {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+            };
+
+            for payment in payments.iter() {
+                if let Err(e) = refresh_bill_message(ctx, payment).await {
+                    error!("error updating bill message {}: {}", payment.id, e);
+                }
+            }
+        }
+
+        let mut message = interaction.message.clone();
+        if let Err(e) = message
+            .edit(
+                &ctx,
+                EditMessage::new()
+                    .embed(create_plan_embed(&plan))
+                    .components(create_plan_components(&plan)),
+            )
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::BasicSuccess(format!(
+            "Recorded {} paying {} {}",
+            display_name(settlement.debtor as u64),
+            display_name(settlement.creditor as u64),
+            Money::from_cents(settlement.amount)
+        )))
+    }
+}
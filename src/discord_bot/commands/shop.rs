@@ -1,5 +1,6 @@
-use std::{cmp::Ordering, collections::HashSet};
+use std::collections::BTreeMap;
 
+use chrono::{Duration, Local};
 use log::error;
 use serenity::{
     all::{
@@ -10,25 +11,36 @@ use serenity::{
     builder::{
         AutocompleteChoice, CreateActionRow, CreateAutocompleteResponse, CreateButton,
         CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
-        CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateMessage,
-        EditMessage,
+        CreateInteractionResponseFollowup, CreateInteractionResponseMessage, EditMessage,
     },
     prelude::Context,
 };
 
 use crate::{
     discord_bot::{
-        common::embed::EmbedColor,
-        database::shopping::{NewShoppingListItem, SerenityShoppingDatabase},
+        common::{
+            embed::EmbedColor,
+            fuzzy::{same_name, Candidates},
+        },
+        database::shopping::{
+            NewShoppingListItem, SerenityShoppingDatabase, ShoppingListItemModel, ShoppingListModel,
+        },
     },
     state::AppState,
 };
 
 use super::{
-    command::{AutocompleteCommand, Command, InteractionCommand},
+    board::{move_board, refresh_item_board},
+    command::{
+        paginate, AutocompleteCommand, Command, InteractionCommand, Page, PaginatedResponse,
+    },
+    list::{autocomplete_list_names, find_list},
     util::CommandResponse,
 };
 
+/// how many items from the shopping history are searched for autocomplete suggestions
+const AUTOCOMPLETE_HISTORY: u64 = 200;
+
 const EXTRA_STORE_NAMES: &[&str] = &[
     "Pack'n'Save",
     "Countdown",
@@ -198,63 +210,50 @@ impl Interactable for ComponentInteraction {
     }
 }
 
-trait Constructable: Default {
-    fn add_embed(self, embed: CreateEmbed) -> Self;
-    fn add_components(self, components: Vec<CreateActionRow>) -> Self;
-}
-
-impl Constructable for CreateInteractionResponseFollowup {
-    fn add_embed(self, embed: CreateEmbed) -> Self {
-        self.embed(embed)
-    }
-
-    fn add_components(self, components: Vec<CreateActionRow>) -> Self {
-        self.components(components)
-    }
-}
-
-impl Constructable for CreateMessage {
-    fn add_embed(self, embed: CreateEmbed) -> Self {
-        self.embed(embed)
-    }
-
-    fn add_components(self, components: Vec<CreateActionRow>) -> Self {
-        self.components(components)
-    }
-}
-
-async fn create_loading_message<'b, A: Interactable>(
+/// let whoever added an item know it's on the list, just for them as the board shows everyone
+/// else, returning the id of the message to record the item against
+async fn acknowledge_item<'b, A: Interactable>(
+    shop: &'b Shop<'b>,
     interaction: &'b A,
     ctx: &'b Context,
 ) -> Result<u64, CommandResponse> {
     if let Err(e) = interaction
         .interactable_create_response(
             ctx,
-            CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .embed(
+                        CreateEmbed::new()
+                            .description(item_description(shop))
+                            .color(EmbedColor::Green as u32),
+                    )
+                    .ephemeral(true),
+            ),
         )
         .await
     {
         return Err(CommandResponse::InternalFailure(format!(
-            "error communicating with database: {}",
+            "error communicating with discord: {}",
             e
         )));
     }
 
-    let loading_message = match interaction.interactable_get_response(ctx).await {
+    let message = match interaction.interactable_get_response(ctx).await {
         Ok(m) => m,
         Err(e) => {
             return Err(CommandResponse::InternalFailure(format!(
-                "error communicating with database: {}",
+                "error communicating with discord: {}",
                 e
             )));
         }
     };
 
-    Ok(loading_message.id.into())
+    Ok(message.id.into())
 }
 
 async fn push_list_item_to_database<'b, A: Interactable>(
     shop: Shop<'b>,
+    list_id: Option<i32>,
     state: &'b AppState,
     interaction: &'b A,
     ctx: &'b Context,
@@ -271,6 +270,7 @@ async fn push_list_item_to_database<'b, A: Interactable>(
             channel_id,
             guild_id,
             NewShoppingListItem {
+                list_id,
                 item: shop.item,
                 personal: shop.personal,
                 quantity: shop.quantity,
@@ -297,43 +297,41 @@ async fn push_list_item_to_database<'b, A: Interactable>(
     Ok(())
 }
 
-async fn create_new_shopping<'b, B: Constructable>(
-    shop: &'b Shop<'b>,
-) -> Result<B, CommandResponse> {
-    Ok(B::default()
-        .add_embed(
+/// the description of an item's message, also used to bring it up to date when it changes
+fn item_description(shop: &Shop) -> String {
+    format!(
+        "Added x{} {}{} to the {} list{}{}",
+        shop.quantity,
+        shop.item,
+        if shop.personal { " (personal)" } else { "" },
+        shop.list.unwrap_or("shopping"),
+        if shop.store.is_some() {
+            format!(" from {}", shop.store.unwrap())
+        } else {
+            "".to_string()
+        },
+        if shop.notes.is_some() {
+            format!("\n**note:** {}", shop.notes.unwrap())
+        } else {
+            "".to_string()
+        },
+    )
+}
+
+/// mark the message of an item as bought, leaving only the option to add it again
+fn bought_item_edit(description: &str) -> EditMessage {
+    EditMessage::new()
+        .embed(
             CreateEmbed::new()
-                // .title("Added to shopping list") //XXX: experiment
-                .description(format!(
-                    "Added x{} {}{} to the shopping list{}{}",
-                    shop.quantity,
-                    shop.item,
-                    if shop.personal { " (personal)" } else { "" },
-                    if shop.store.is_some() {
-                        format!(" from {}", shop.store.unwrap())
-                    } else {
-                        "".to_string()
-                    },
-                    if shop.notes.is_some() {
-                        format!("\n**note:** {}", shop.notes.unwrap())
-                    } else {
-                        "".to_string()
-                    },
-                ))
-                .color(EmbedColor::Red as u32),
+                .description(format!("(BOUGHT) ~~{}~~", description))
+                .color(EmbedColor::Green as u32),
+        )
+        .components(vec![CreateActionRow::Buttons(vec![CreateButton::new(
+            "readd",
         )
-        .add_components(vec![CreateActionRow::Buttons(vec![
-            CreateButton::new("bought")
-                .style(serenity::all::ButtonStyle::Success)
-                .label("Bought"),
-            CreateButton::new("remove")
-                .style(serenity::all::ButtonStyle::Danger)
-                .label("Remove"),
-            CreateButton::new("readd")
-                .style(serenity::all::ButtonStyle::Secondary)
-                .label("Re-add")
-                .disabled(true),
-        ])]))
+        .style(serenity::all::ButtonStyle::Secondary)
+        .label("Re-add")
+        .disabled(false)])])
 }
 
 #[derive(Debug)]
@@ -343,6 +341,8 @@ pub struct Shop<'a> {
     quantity: i64,
     store: Option<&'a str>,
     notes: Option<&'a str>,
+    /// the name of the list to add the item to, the current list if not given
+    list: Option<&'a str>,
 }
 
 impl<'a> TryFrom<&'a CommandInteraction> for Shop<'a> {
@@ -355,6 +355,7 @@ impl<'a> TryFrom<&'a CommandInteraction> for Shop<'a> {
         let mut quantity: Option<i64> = None;
         let mut store: Option<&str> = None;
         let mut notes: Option<&str> = None;
+        let mut list: Option<&str> = None;
 
         for option in options.into_iter() {
             match (option.name, option.value) {
@@ -363,6 +364,7 @@ impl<'a> TryFrom<&'a CommandInteraction> for Shop<'a> {
                 ("quantity", ResolvedValue::Integer(val)) => quantity = Some(val),
                 ("store", ResolvedValue::String(val)) => store = Some(val),
                 ("notes", ResolvedValue::String(val)) => notes = Some(val),
+                ("list", ResolvedValue::String(val)) => list = Some(val),
                 (opt, val) => {
                     panic!("unexpected option name: `{}` and value `{:?}`", opt, val)
                 }
@@ -382,6 +384,7 @@ impl<'a> TryFrom<&'a CommandInteraction> for Shop<'a> {
             quantity,
             store,
             notes,
+            list,
         })
     }
 }
@@ -450,6 +453,16 @@ impl<'a> Command<'a> for Shop<'a> {
             .max_length(100)
             .to_owned(),
         )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "list",
+                "The list to add the item to, defaults to the current list",
+            )
+            .required(false)
+            .set_autocomplete(true)
+            .to_owned(),
+        )
     }
 
     async fn handle_application_command<'b>(
@@ -458,15 +471,30 @@ impl<'a> Command<'a> for Shop<'a> {
         state: &'b AppState,
         ctx: &'b Context,
     ) -> Result<CommandResponse, CommandResponse> {
-        let loading_message = create_loading_message(interaction, ctx).await?;
-        let resp = create_new_shopping(&self).await?;
+        // check the list exists before anything is posted
+        let list = match self.list {
+            Some(name) => {
+                Some(find_list(state, interaction.guild_id.map(|g| g.0.into()), name).await?)
+            }
+            None => None,
+        };
+        let shop = Shop {
+            list: list.as_ref().map(|list| list.name.as_str()),
+            ..self
+        };
 
-        if let Err(e) = interaction.create_followup(&ctx, resp).await {
-            error!("error creating followup: {}", e);
-            return Err(CommandResponse::NoResponse);
+        // rather than adding a second entry for something already on the list, ask what to do
+        if let Some((target, duplicate)) =
+            find_duplicate(&shop, list.as_ref(), interaction, state).await?
+        {
+            return hold_duplicate(&shop, &target, duplicate, interaction, state).await;
         }
 
-        push_list_item_to_database(self, state, interaction, ctx, loading_message).await?;
+        let message_id = acknowledge_item(&shop, interaction, ctx).await?;
+
+        let list_id = list.as_ref().map(|list| list.id);
+        push_list_item_to_database(shop, list_id, state, interaction, ctx, message_id).await?;
+        refresh_item_board(state, ctx, message_id, interaction.channel_id).await?;
 
         Ok(CommandResponse::NoResponse)
     }
@@ -480,11 +508,15 @@ impl<'a> AutocompleteCommand<'a> for Shop<'a> {
         app_state: &'c AppState,
         _: &'c Context,
     ) -> Result<CreateAutocompleteResponse, CommandResponse> {
-        let mut response = CreateAutocompleteResponse::new();
+        let guild_id: Option<u64> = command.guild_id.map(|g| g.0.into());
+        if autocomplete.name == "list" {
+            return autocomplete_list_names(app_state, guild_id, autocomplete.value).await;
+        }
+
         let user_id: u64 = command.user.id.into();
 
         let mut items = match app_state
-            .get_recent_shopping_list_items_by_user(user_id, 50)
+            .get_recent_shopping_list_items(guild_id, AUTOCOMPLETE_HISTORY)
             .await
         {
             Ok(items) => items,
@@ -496,7 +528,11 @@ impl<'a> AutocompleteCommand<'a> for Shop<'a> {
             }
         };
 
-        let extra_items = match app_state.get_recent_shopping_list_items(50).await {
+        // a flatmate's own history reaches further back than everyone's
+        let own_items = match app_state
+            .get_recent_shopping_list_items_by_user(guild_id, user_id, AUTOCOMPLETE_HISTORY)
+            .await
+        {
             Ok(items) => items,
             Err(e) => {
                 return Err(CommandResponse::InternalFailure(format!(
@@ -506,88 +542,40 @@ impl<'a> AutocompleteCommand<'a> for Shop<'a> {
             }
         };
 
-        for item in extra_items {
+        for item in own_items {
             if !items.contains(&item) {
                 items.push(item);
             }
         }
+        items.sort_by(|a, b| b.created_at.cmp(&a.created_at));
 
-        let search_phrase = autocomplete.value;
-
+        let mut candidates = Candidates::new();
         match autocomplete.name {
             "item" => {
-                let mut item_names: HashSet<String> =
-                    items.into_iter().map(|item| item.item).collect();
-                item_names.extend(EXTRA_ITEMS.iter().map(|item| item.to_string()));
-
-                //sort item names, preferring items that start with, then contain, the current search phrase
-                let mut item_names: Vec<String> = item_names.into_iter().collect();
-                item_names.sort_by(|a, b| {
-                    let a_start = a.starts_with(search_phrase);
-                    let b_start = b.starts_with(search_phrase);
-                    let a_contains = a.contains(search_phrase);
-                    let b_contains = b.contains(search_phrase);
-
-                    if a_start && !b_start {
-                        Ordering::Less
-                    } else if !a_start && b_start {
-                        Ordering::Greater
-                    } else if a_contains && !b_contains {
-                        Ordering::Less
-                    } else if !a_contains && b_contains {
-                        Ordering::Greater
-                    } else {
-                        a.cmp(b)
-                    }
-                });
-                item_names.truncate(25);
-
-                let choices: Vec<AutocompleteChoice> = item_names
-                    .into_iter()
-                    .map(|item| AutocompleteChoice {
-                        name: item.clone(),
-                        value: serde_json::Value::String(item),
-                    })
-                    .collect();
-
-                response = response.set_choices(choices);
+                for item in &items {
+                    candidates.add(
+                        &item.item,
+                        item.user_id as u64 == user_id,
+                        item.bought_by.is_some(),
+                    );
+                }
+                for item in EXTRA_ITEMS {
+                    candidates.add(item, false, false);
+                }
             }
             "store" => {
-                let mut store_names: HashSet<String> =
-                    items.into_iter().filter_map(|item| item.store).collect();
-                store_names.extend(EXTRA_STORE_NAMES.iter().map(|store| store.to_string()));
-
-                //sort store names, preferring stores that start with, then contain, the current search phrase
-                let mut store_names: Vec<String> = store_names.into_iter().collect();
-                store_names.sort_by(|a, b| {
-                    let a_start = a.starts_with(search_phrase);
-                    let b_start = b.starts_with(search_phrase);
-                    let a_contains = a.contains(search_phrase);
-                    let b_contains = b.contains(search_phrase);
-
-                    if a_start && !b_start {
-                        Ordering::Less
-                    } else if !a_start && b_start {
-                        Ordering::Greater
-                    } else if a_contains && !b_contains {
-                        Ordering::Less
-                    } else if !a_contains && b_contains {
-                        Ordering::Greater
-                    } else {
-                        a.cmp(b)
+                for item in &items {
+                    if let Some(store) = &item.store {
+                        candidates.add(
+                            store,
+                            item.user_id as u64 == user_id,
+                            item.bought_by.is_some(),
+                        );
                     }
-                });
-                store_names.truncate(25);
-
-                let choices: Vec<AutocompleteChoice> = store_names
-                    .into_iter()
-                    .map(|store| AutocompleteChoice {
-                        name: store.clone(),
-                        value: serde_json::Value::String(store),
-                    })
-                    .collect();
-
-                response = response.set_choices(choices);
+                }
+                for store in EXTRA_STORE_NAMES {
+                    candidates.add(store, false, false);
+                }
             }
             _ => {
                 return Err(CommandResponse::InternalFailure(
@@ -596,7 +584,16 @@ impl<'a> AutocompleteCommand<'a> for Shop<'a> {
             }
         }
 
-        Ok(response)
+        let choices: Vec<AutocompleteChoice> = candidates
+            .rank(autocomplete.value, 25)
+            .into_iter()
+            .map(|name| AutocompleteChoice {
+                name: name.clone(),
+                value: serde_json::Value::String(name),
+            })
+            .collect();
+
+        Ok(CreateAutocompleteResponse::new().set_choices(choices))
     }
 }
 
@@ -652,25 +649,12 @@ impl<'a> InteractionCommand<'a> for Shop<'a> {
                 if let Err(e) = edit_message
                     .edit(
                         &ctx,
-                        EditMessage::new()
-                            .embed(
-                                CreateEmbed::new()
-                                    //XXX: title?
-                                    .description(format!(
-                                        "(BOUGHT) ~~{}~~",
-                                        ex_embed
-                                            .description
-                                            .as_ref()
-                                            .expect("description not found")
-                                    ))
-                                    .color(EmbedColor::Green as u32),
-                            )
-                            .components(vec![CreateActionRow::Buttons(vec![CreateButton::new(
-                                "readd",
-                            )
-                            .style(serenity::all::ButtonStyle::Secondary)
-                            .label("Re-add")
-                            .disabled(false)])]),
+                        bought_item_edit(
+                            ex_embed
+                                .description
+                                .as_ref()
+                                .expect("description not found"),
+                        ),
                     )
                     .await
                 {
@@ -684,13 +668,12 @@ impl<'a> InteractionCommand<'a> for Shop<'a> {
                     .create_response(&ctx, CreateInteractionResponse::Acknowledge)
                     .await
                     .unwrap();
+
+                refresh_item_board(app_state, ctx, msg_id, interaction.channel_id).await?;
             }
             "remove" => {
-                // mark as bought in database
-                if let Err(e) = app_state
-                    .set_shopping_list_item_bought(user_id, msg_id, true)
-                    .await
-                {
+                // take off the list in database
+                if let Err(e) = app_state.set_shopping_list_item_cleared(msg_id).await {
                     return Err(CommandResponse::InternalFailure(format!(
                         "error communicating with database: {}",
                         e
@@ -742,6 +725,8 @@ impl<'a> InteractionCommand<'a> for Shop<'a> {
                     .create_response(&ctx, CreateInteractionResponse::Acknowledge)
                     .await
                     .unwrap();
+
+                refresh_item_board(app_state, ctx, msg_id, interaction.channel_id).await?;
             }
             "readd" => {
                 let item = match app_state.get_shopping_list_item_by_message_id(msg_id).await {
@@ -759,181 +744,580 @@ impl<'a> InteractionCommand<'a> for Shop<'a> {
                     }
                 };
 
-                create_loading_message(interaction, ctx).await?;
+                // re-add the item to the list it was on, unless that list has since been archived
+                let lists = match app_state
+                    .get_shopping_lists(interaction.guild_id.map(|g| g.0.into()))
+                    .await
+                {
+                    Ok(lists) => lists,
+                    Err(e) => {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "error communicating with database: {}",
+                            e
+                        )));
+                    }
+                };
+                let list = lists.iter().find(|list| list.id == item.list_id);
+
                 let shop = Shop {
                     item: item.item.as_ref(),
                     personal: item.personal,
                     quantity: item.quantity,
                     store: item.store.as_deref(),
                     notes: item.notes.as_deref(),
+                    list: list.map(|list| list.name.as_str()),
                 };
-                let resp = create_new_shopping(&shop).await?;
+                let message_id = acknowledge_item(&shop, interaction, ctx).await?;
+
+                push_list_item_to_database(
+                    shop,
+                    list.map(|list| list.id),
+                    app_state,
+                    interaction,
+                    ctx,
+                    message_id,
+                )
+                .await?;
+
+                refresh_item_board(app_state, ctx, message_id, interaction.channel_id).await?;
+            }
+            _ => {
+                return Err(CommandResponse::InternalFailure(
+                    "Invalid interaction".to_string(),
+                ));
+            }
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+/// the start of the custom id of the buttons asking what to do about a duplicate item
+const DUPLICATE_PREFIX: &str = "duplicate:";
+
+/// how many minutes a flatmate has to decide what to do about a duplicate item, after which the
+/// item is forgotten
+const PENDING_TIMEOUT_MINUTES: i64 = 15;
+
+/// find an item yet to be bought on the list being added to which is most likely the same as the
+/// one being added, e.g. "milk 2L" when adding "2L milk". Someone else's personal item isn't the
+/// same, as both need buying
+async fn find_duplicate(
+    shop: &Shop<'_>,
+    list: Option<&ShoppingListModel>,
+    interaction: &CommandInteraction,
+    state: &AppState,
+) -> Result<Option<(ShoppingListModel, ShoppingListItemModel)>, CommandResponse> {
+    let (list, items) = match list {
+        Some(list) => match state.get_unbought_shopping_list_items(list.id).await {
+            Ok(items) => (list.clone(), items),
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        },
+        None => match state
+            .get_shopping_list(interaction.guild_id.map(|g| g.0.into()))
+            .await
+        {
+            Ok(Some(list)) => list,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        },
+    };
+
+    let user_id = u64::from(interaction.user.id) as i64;
+    let duplicate = items.into_iter().find(|item| {
+        !item.bought
+            && item.personal == shop.personal
+            && (!shop.personal || item.user_id == user_id)
+            && same_name(&item.item, shop.item)
+    });
+
+    Ok(duplicate.map(|duplicate| (list, duplicate)))
+}
+
+/// hold an item back from the list, asking whoever added it whether to add to the quantity of
+/// the item already on the list, add it anyway, or leave it. The item is kept in the database
+/// until they answer, so a restart doesn't lose it
+async fn hold_duplicate(
+    shop: &Shop<'_>,
+    list: &ShoppingListModel,
+    duplicate: ShoppingListItemModel,
+    interaction: &CommandInteraction,
+    state: &AppState,
+) -> Result<CommandResponse, CommandResponse> {
+    let expired = Local::now().naive_local() - Duration::minutes(PENDING_TIMEOUT_MINUTES);
+    if let Err(e) = state.remove_pending_items_before(expired).await {
+        error!("error removing expired pending items: {}", e);
+    }
 
-                let msg_id = match interaction.create_followup(&ctx, resp).await {
-                    Ok(m) => m,
+    let pending = match state
+        .add_pending_item(
+            interaction.user.id.into(),
+            &duplicate,
+            NewShoppingListItem {
+                list_id: Some(list.id),
+                item: shop.item,
+                personal: shop.personal,
+                quantity: shop.quantity,
+                store: shop.store,
+                notes: shop.notes,
+            },
+        )
+        .await
+    {
+        Ok(pending) => pending,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    let id = |action: &str| format!("{}{}:{}", DUPLICATE_PREFIX, pending.id, action);
+
+    Ok(CommandResponse::ComplexSuccess(
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Already on the list")
+                        .description(format!(
+                            "x{} {} is already on the {} list, added by <@{}>.",
+                            duplicate.quantity, duplicate.item, list.name, duplicate.user_id
+                        ))
+                        .color(EmbedColor::Orange as u32),
+                )
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new(id("increase"))
+                        .style(serenity::all::ButtonStyle::Primary)
+                        .label("Increase quantity"),
+                    CreateButton::new(id("add"))
+                        .style(serenity::all::ButtonStyle::Secondary)
+                        .label("Add anyway"),
+                    CreateButton::new(id("cancel"))
+                        .style(serenity::all::ButtonStyle::Danger)
+                        .label("Cancel"),
+                ])])
+                .ephemeral(true),
+        ),
+    ))
+}
+
+/// replace the question about a duplicate item with what was done about it
+fn duplicate_answered(description: String) -> CreateInteractionResponse {
+    CreateInteractionResponse::UpdateMessage(
+        CreateInteractionResponseMessage::new()
+            .embed(
+                CreateEmbed::new()
+                    .title("Already on the list")
+                    .description(description)
+                    .color(EmbedColor::Grey as u32),
+            )
+            .components(vec![]),
+    )
+}
+
+/// add an item which was held back as a duplicate as its own entry after all, recorded against
+/// the question that was asked about it
+async fn add_held_item<'b>(
+    shop: Shop<'b>,
+    list_id: i32,
+    interaction: &'b ComponentInteraction,
+    state: &'b AppState,
+    ctx: &'b Context,
+) -> Result<CommandResponse, CommandResponse> {
+    let resp = duplicate_answered(format!("Added x{} {} anyway.", shop.quantity, shop.item));
+    if let Err(e) = interaction.create_response(&ctx, resp).await {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with discord: {}",
+            e
+        )));
+    }
+
+    let message_id = interaction.message.id.into();
+    push_list_item_to_database(shop, Some(list_id), state, interaction, ctx, message_id).await?;
+    refresh_item_board(state, ctx, message_id, interaction.channel_id).await?;
+
+    Ok(CommandResponse::NoResponse)
+}
+
+/// The buttons asking what to do about an item which is already on the list
+pub struct ShopDuplicate;
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for ShopDuplicate {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        interaction.data.custom_id.starts_with(DUPLICATE_PREFIX)
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let custom_id = &interaction.data.custom_id;
+        let (key, action) = match custom_id[DUPLICATE_PREFIX.len()..].split_once(':') {
+            Some((key, action)) => match key.parse::<i32>() {
+                Ok(key) => (key, action),
+                Err(_) => {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "invalid duplicate custom id `{}`",
+                        custom_id
+                    )));
+                }
+            },
+            None => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "invalid duplicate custom id `{}`",
+                    custom_id
+                )));
+            }
+        };
+
+        let expired = |reason: &str| {
+            Ok(CommandResponse::ComplexSuccess(duplicate_answered(
+                format!("{}, use `/shop` again.", reason),
+            )))
+        };
+
+        let pending = match app_state.take_pending_item(key).await {
+            Ok(Some(pending)) => pending,
+            Ok(None) => return expired("This has already been answered or has expired"),
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+        if Local::now().naive_local() - pending.created_at
+            > Duration::minutes(PENDING_TIMEOUT_MINUTES)
+        {
+            return expired("This has expired");
+        }
+
+        let list = match app_state.get_shopping_list_by_id(pending.list_id).await {
+            Ok(Some(list)) if list.archived_at.is_none() => list,
+            Ok(_) => return expired("The list has since been archived"),
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        let shop = Shop {
+            item: &pending.item,
+            personal: pending.personal,
+            quantity: pending.quantity,
+            store: pending.store.as_deref(),
+            notes: pending.notes.as_deref(),
+            list: Some(list.name.as_str()),
+        };
+
+        match action {
+            "cancel" => Ok(CommandResponse::ComplexSuccess(duplicate_answered(
+                format!("x{} {} wasn't added.", shop.quantity, shop.item),
+            ))),
+            "add" => add_held_item(shop, pending.list_id, interaction, app_state, ctx).await,
+            "increase" => {
+                let duplicate = match app_state
+                    .get_shopping_list_item_by_id(pending.duplicate_id as u64)
+                    .await
+                {
+                    Ok(duplicate) => duplicate,
                     Err(e) => {
                         return Err(CommandResponse::InternalFailure(format!(
-                            "error communicating with discord: {}",
+                            "error communicating with database: {}",
                             e
                         )));
                     }
                 };
 
-                push_list_item_to_database(shop, app_state, interaction, ctx, msg_id.id.into())
-                    .await?;
+                // if it's been bought or removed since, there's nothing left to add to
+                let duplicate = match duplicate {
+                    Some(duplicate) if !duplicate.bought => duplicate,
+                    _ => {
+                        return add_held_item(shop, pending.list_id, interaction, app_state, ctx)
+                            .await;
+                    }
+                };
+
+                let duplicate = match app_state
+                    .set_shopping_list_item_quantity(
+                        duplicate.id,
+                        duplicate.quantity + shop.quantity,
+                    )
+                    .await
+                {
+                    Ok(duplicate) => duplicate,
+                    Err(e) => {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "error communicating with database: {}",
+                            e
+                        )));
+                    }
+                };
+
+                refresh_item_board(
+                    app_state,
+                    ctx,
+                    duplicate.message_id as u64,
+                    interaction.channel_id,
+                )
+                .await?;
+
+                Ok(CommandResponse::ComplexSuccess(duplicate_answered(
+                    format!(
+                        "There are now x{} {} on the {} list.",
+                        duplicate.quantity, duplicate.item, list.name
+                    ),
+                )))
             }
-            _ => {
-                return Err(CommandResponse::InternalFailure(
-                    "Invalid interaction".to_string(),
-                ));
+            _ => Err(CommandResponse::InternalFailure(format!(
+                "invalid duplicate custom id `{}`",
+                custom_id
+            ))),
+        }
+    }
+}
+
+/// the number of items shown on each page of the history
+const HISTORY_PAGE_SIZE: u64 = 15;
+
+/// Every item ever added to the shopping lists of a guild, a page at a time
+pub struct ShopHistory {
+    guild_id: Option<u64>,
+}
+
+#[async_trait]
+impl PaginatedResponse for ShopHistory {
+    fn key() -> &'static str {
+        "shop"
+    }
+
+    fn to_state(&self) -> String {
+        self.guild_id.map(|g| g.to_string()).unwrap_or_default()
+    }
+
+    fn from_state(state: &str) -> Option<Self> {
+        match state {
+            "" => Some(ShopHistory { guild_id: None }),
+            guild_id => guild_id
+                .parse()
+                .ok()
+                .map(|g| ShopHistory { guild_id: Some(g) }),
+        }
+    }
+
+    async fn get_page(&self, page: u64, app_state: &AppState) -> Result<Page, CommandResponse> {
+        let (items, count) = match app_state
+            .get_shopping_history_page(self.guild_id, page, HISTORY_PAGE_SIZE)
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
             }
+        };
+
+        let description = if items.is_empty() {
+            String::from("Nothing has been added to the shopping list yet.")
+        } else {
+            items
+                .iter()
+                .map(|item| {
+                    format!(
+                        "{} x{} {}{}{} by <@{}>{}",
+                        item.created_at.format("%d/%m/%y"),
+                        item.quantity,
+                        item.item,
+                        if item.personal { " (personal)" } else { "" },
+                        match &item.store {
+                            Some(store) => format!(" from {}", store),
+                            None => String::new(),
+                        },
+                        item.user_id,
+                        if item.bought { " ✅" } else { "" }
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        Ok(Page {
+            embed: CreateEmbed::new()
+                .title("Shopping history")
+                .description(description)
+                .color(EmbedColor::Green as u32),
+            count,
+        })
+    }
+}
+
+pub struct ShopHistoryCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopHistoryCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopHistoryCommand {
+    fn name() -> &'static str {
+        "shop-history"
+    }
+
+    fn description() -> &'static str {
+        "show everything that has been added to the shopping list, newest first"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = interaction.guild_id.map(|g| g.0.into());
+        paginate(ShopHistory { guild_id }, interaction, app_state).await
+    }
+}
+
+/// Close out a shopping trip, summarising what was bought and bringing the board of whatever wasn't
+/// back to the bottom of the channel so it doesn't get forgotten
+pub struct ShoppingComplete;
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShoppingComplete {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(ShoppingComplete)
+    }
+}
+
+/// summarise what each flatmate bought on a shopping trip
+fn create_trip_embed(bought: &[&ShoppingListItemModel], list: &str) -> CreateEmbed {
+    let mut by_buyer: BTreeMap<i64, Vec<String>> = BTreeMap::new();
+    for item in bought {
+        if let Some(buyer) = item.bought_by {
+            by_buyer.entry(buyer).or_default().push(format!(
+                "x{} {}{}",
+                item.quantity,
+                item.item,
+                if item.personal { " (personal)" } else { "" }
+            ));
+        }
+    }
+
+    let description = if by_buyer.is_empty() {
+        String::from("Nothing was bought on this trip.")
+    } else {
+        by_buyer
+            .iter()
+            .map(|(buyer, items)| format!("<@{}>: {}", buyer, items.join(", ")))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    CreateEmbed::new()
+        .title(format!("Bought from the {} list", list))
+        .description(description)
+        .color(EmbedColor::Green as u32)
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShoppingComplete {
+    fn name() -> &'static str {
+        "shopping-complete"
+    }
+
+    fn description() -> &'static str {
+        "Run this command once you have completed shopping"
+    }
+
+    fn get_application_command_options(command: CreateCommand) -> CreateCommand {
+        command
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        cmd_interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: Option<u64> = cmd_interaction.guild_id.map(|g| g.0.into());
+
+        let (list, items) = match app_state.get_shopping_list(guild_id).await {
+            Ok(Some(list)) => list,
+            Ok(None) => {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "There's no shopping list to complete.",
+                )));
+            }
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        // the trip is everything bought since the list was last completed, for a list which has
+        // never been completed that's everything ever bought from it
+        let bought: Vec<_> = items
+            .iter()
+            .filter(|item| item.bought_by.is_some() && item.bought_at > list.completed_at)
+            .collect();
+
+        if let Err(e) = cmd_interaction.create_response(&ctx,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("-----------------------------------\n**Shopping Complete!**\n-----------------------------------")
+                    .embed(create_trip_embed(&bought, &list.name))
+            )
+        ).await {
+            error!("error communicating with discord to create initial response: {}", e);
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
         }
 
+        if let Err(e) = app_state.set_shopping_list_completed(list.id).await {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+
+        // whatever wasn't bought stays on the board, brought down below the summary
+        move_board(app_state, ctx, list.id, cmd_interaction.channel_id).await?;
+
         Ok(CommandResponse::NoResponse)
     }
 }
-
-// pub struct ShoppingComplete;
-
-// impl<'a> TryFrom<&'a CommandInteraction> for ShoppingComplete {
-//     type Error = String;
-
-//     fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
-//         Ok(ShoppingComplete)
-//     }
-// }
-
-// #[async_trait]
-// impl<'a> Command<'a> for ShoppingComplete {
-//     fn name() -> &'static str {
-//         "shopping-complete"
-//     }
-
-//     fn description() -> &'static str {
-//         "Run this command once you have completed shopping"
-//     }
-
-//     fn get_application_command_options(command: CreateCommand) -> CreateCommand {
-//         command
-//     }
-
-//     async fn handle_application_command<'b>(
-//         self,
-//         cmd_interaction: &'b CommandInteraction,
-//         app_state: &'b AppState,
-//         ctx: &'b Context,
-//     ) -> Result<CommandResponse, CommandResponse> {
-//         // TODO: actually make use of the shopping list -> shopping list item table
-//         // to separate what items are actually available to be bought when this command runs
-//         if let Err(e) = cmd_interaction.create_response(&ctx,
-//             CreateInteractionResponse::Message(
-//                 CreateInteractionResponseMessage::new()
-//                     .content("-----------------------------------\n**Shopping Complete!**\n-----------------------------------")
-//             )
-//         ).await {
-//             error!("error communicating with discord to create initial response: {}", e);
-//             return Err(CommandResponse::InternalFailure(format!(
-//                 "error communicating with discord: {}",
-//                 e
-//             )));
-//         }
-
-//         // collect every non-bought item from the shopping list
-//         let items = match app_state.get_unbought_shopping_list_items().await {
-//             Ok(items) => items,
-//             Err(e) => {
-//                 return Err(CommandResponse::InternalFailure(format!(
-//                     "error communicating with database: {}",
-//                     e
-//                 )));
-//             }
-//         };
-
-//         let channel = cmd_interaction.channel_id();
-
-//         // for each item, send a message to the shopping channel
-//         for item in items {
-//             let shop = Shop {
-//                 item: item.item.as_ref(),
-//                 personal: item.personal,
-//                 quantity: item.quantity,
-//                 store: item.store.as_deref(),
-//                 notes: item.notes.as_deref(),
-//             };
-
-//             let resp = create_new_shopping(&shop).await?;
-
-//             let new_msg = match channel.send_message(&ctx, resp).await {
-//                 Ok(m) => m,
-//                 Err(e) => {
-//                     error!(
-//                         "error communicating with discord to send shopping list item: {}",
-//                         e
-//                     );
-//                     return Err(CommandResponse::InternalFailure(format!(
-//                         "error communicating with discord: {}",
-//                         e
-//                     )));
-//                 }
-//             };
-
-//             push_list_item_to_database(shop, app_state, cmd_interaction, ctx, new_msg.id.into())
-//                 .await?;
-
-//             // mark old item as bought in the database
-//             if let Err(e) = app_state
-//                 .set_shopping_list_item_bought(item.user_id as u64, item.message_id as u64, true)
-//                 .await
-//             {
-//                 return Err(CommandResponse::InternalFailure(format!(
-//                     "error communicating with database: {}",
-//                     e
-//                 )));
-//             }
-
-//             let ex_embed = match channel.message(&ctx, item.message_id as u64).await {
-//                 Ok(m) => m.embeds.first().unwrap().clone(),
-//                 Err(e) => {
-//                     error!("error communicating with discord to get old message: {}", e);
-//                     return Err(CommandResponse::InternalFailure(format!(
-//                         "error communicating with discord: {}",
-//                         e
-//                     )));
-//                 }
-//             };
-
-//             // edit the old message to show that it has been refreshed
-//             if let Err(e) = channel
-//                 .edit_message(
-//                     ctx,
-//                     item.message_id as u64,
-//                     EditMessage::new()
-//                         .embed(
-//                             CreateEmbed::new()
-//                                 .description(format!(
-//                                     "(REFRESHED) ~~{}~~",
-//                                     ex_embed
-//                                         .description
-//                                         .as_ref()
-//                                         .expect("description not found")
-//                                 ))
-//                                 .color(EmbedColor::Blue as u32),
-//                         )
-//                         .components(vec![]),
-//                 )
-//                 .await
-//             {
-//                 error!(
-//                     "error communicating with discord to edit old message: {}",
-//                     e
-//                 );
-//                 return Err(CommandResponse::InternalFailure(format!(
-//                     "error communicating with discord to edit old message: {}",
-//                     e
-//                 )));
-//             }
-//         }
-
-//         Ok(CommandResponse::NoResponse)
-//     }
-// }
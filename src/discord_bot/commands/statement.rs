@@ -0,0 +1,205 @@
+use chrono::{Local, NaiveDate};
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue, UserId},
+    async_trait,
+    builder::{
+        CreateCommand, CreateCommandOption, CreateEmbed, CreateEmbedFooter,
+        CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{common::embed::EmbedColor, database::payments::SerenityPaymentDatabase},
+    finance::{
+        money::Money,
+        monthly::{self, MonthlyStatement},
+    },
+    state::AppState,
+};
+
+use super::{
+    command::Command,
+    util::{display_name, join_lines, random_phrase, CommandResponse},
+};
+
+/// parse a month given as an option, in the form YYYY-MM
+fn parse_month(month: &str) -> Result<NaiveDate, CommandResponse> {
+    NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d").map_err(|_| {
+        CommandResponse::BasicFailure(format!(
+            "`{}` isn't a valid month, please use YYYY-MM.",
+            month
+        ))
+    })
+}
+
+/// build the embed for a flatmate's monthly statement, with a random phrase in the footer
+pub fn create_statement_embed(statement: &MonthlyStatement) -> CreateEmbed {
+    let bills = if statement.bills.is_empty() {
+        String::from("No bills this month.")
+    } else {
+        join_lines(
+            statement
+                .bills
+                .iter()
+                .map(|bill| {
+                    format!(
+                        "{} #{} {}: {} {}",
+                        bill.created_at.format("%d/%m/%y"),
+                        bill.bill,
+                        bill.purpose,
+                        bill.share,
+                        match bill.paid_at {
+                            _ if bill.paid_for => String::from("(paid the bill)"),
+                            Some(paid_at) => format!("(paid {})", paid_at.format("%d/%m/%y")),
+                            None => String::from("(unpaid)"),
+                        }
+                    )
+                })
+                .collect(),
+        )
+    };
+
+    let mut embed = CreateEmbed::new()
+        .title(format!(
+            "Statement for {}, {}",
+            display_name(statement.user),
+            statement.month.format("%B %Y")
+        ))
+        .description(bills)
+        .field("Paid this month:", statement.paid.to_string(), true)
+        .field("Owes at month end:", statement.owes.to_string(), true)
+        .field("Is owed at month end:", statement.owed.to_string(), true)
+        .color(match statement.owes {
+            o if o > Money::ZERO => EmbedColor::Red as u32,
+            _ if statement.owed > Money::ZERO => EmbedColor::Orange as u32,
+            _ => EmbedColor::Green as u32,
+        })
+        .footer(CreateEmbedFooter::new(random_phrase()));
+
+    if !statement.categories.is_empty() {
+        embed = embed.field(
+            "By category:",
+            statement
+                .categories
+                .iter()
+                .map(|(purpose, amount)| format!("{}: {}", purpose, amount))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            false,
+        );
+    }
+
+    embed
+}
+
+/// send a flatmate their monthly statement as a DM
+pub async fn send_statement(
+    ctx: &Context,
+    statement: &MonthlyStatement,
+) -> Result<(), serenity::Error> {
+    UserId::new(statement.user)
+        .direct_message(
+            ctx,
+            CreateMessage::new().embed(create_statement_embed(statement)),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Show a flatmate's statement for a month
+pub struct StatementCommand<'a> {
+    month: Option<&'a str>,
+    flatmate: Option<u64>,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for StatementCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let mut month = None;
+        let mut flatmate = None;
+
+        for option in interaction.data.options() {
+            match (option.name, option.value) {
+                ("month", ResolvedValue::String(val)) => month = Some(val),
+                ("flatmate", ResolvedValue::User(user, _)) => flatmate = Some(user.id.into()),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self { month, flatmate })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for StatementCommand<'a> {
+    fn name() -> &'static str {
+        "statement"
+    }
+
+    fn description() -> &'static str {
+        "Show the bills, payments and balance for a month"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "month",
+                "The month to show, as YYYY-MM, defaults to this month",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::User,
+                "flatmate",
+                "The flatmate to show the statement for, defaults to you",
+            )
+            .required(false),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let month = match self.month {
+            Some(month) => parse_month(month)?,
+            None => monthly::first_of_month(Local::now().date_naive()),
+        };
+        let user = self.flatmate.unwrap_or_else(|| interaction.user.id.into());
+
+        let payments = match app_state
+            .get_guild_payment_history(interaction.guild_id.map(|g| g.0.into()))
+            .await
+        {
+            Ok(payments) => payments,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        let statement = monthly::build(&payments, user, month);
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .ephemeral(true)
+                    .embed(create_statement_embed(&statement)),
+            ),
+        ))
+    }
+}
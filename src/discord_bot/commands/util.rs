@@ -1,8 +1,55 @@
 //! Various utilities to assist with writing application commands for the DIANA bot
 
+use chrono::NaiveDate;
 use log::{debug, error, info, warn};
 use serenity::builder::{CreateInteractionResponse, CreateInteractionResponseMessage};
 
+use crate::state::CONFIG;
+
+/// the name to display for a user, preferring their configured flatmate name
+pub fn display_name(user: u64) -> String {
+    match CONFIG.flatmates.iter().find(|f| f.discord_id == user) {
+        Some(flatmate) => flatmate.display_name.clone(),
+        None => format!("<@{}>", user),
+    }
+}
+
+/// one of the configured phrases, picked at random, for the footer of an embed
+pub fn random_phrase() -> &'static str {
+    &CONFIG.phrases[rand::random::<usize>() % CONFIG.phrases.len()]
+}
+
+/// the most characters discord allows in an embed description
+const DESCRIPTION_LIMIT: usize = 4096;
+
+/// join lines into an embed description, leaving off any that don't fit
+pub fn join_lines(lines: Vec<String>) -> String {
+    let mut description = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let more = format!("…and {} more", lines.len() - i);
+        if description.len() + line.len() + more.len() + 2 > DESCRIPTION_LIMIT {
+            description.push_str(&more);
+            break;
+        }
+        description.push_str(line);
+        description.push('\n');
+    }
+    description
+}
+
+/// parse an optional date given as an option, in the form YYYY-MM-DD
+pub fn parse_date(date: Option<&str>) -> Result<Option<NaiveDate>, CommandResponse> {
+    date.map(|date| {
+        NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| {
+            CommandResponse::BasicFailure(format!(
+                "`{}` isn't a valid date, please use YYYY-MM-DD.",
+                date
+            ))
+        })
+    })
+    .transpose()
+}
+
 #[derive(Debug, Clone, Copy)]
 #[allow(dead_code, clippy::missing_docs_in_private_items)]
 pub enum FailureMessageKind {
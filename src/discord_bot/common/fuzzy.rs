@@ -0,0 +1,323 @@
+//! Fuzzy matching of what has been typed into an autocomplete option against the names it could
+//! complete to. Matching ignores case, is done word by word so that "paper toilet" still finds
+//! "toilet paper", and forgives a typo or two in longer words.
+
+use std::{cmp::Reverse, collections::HashMap};
+
+/// points for a typed word which is exactly a word of the name
+const EXACT: u32 = 100;
+/// points for a typed word which is the start of a word of the name
+const PREFIX: u32 = 80;
+/// points for a typed word which is a word of the name, give or take a typo
+const TYPO: u32 = 60;
+/// points for a typed word which is the start of a word of the name, give or take a typo
+const TYPO_PREFIX: u32 = 50;
+/// points for a typed word found somewhere inside a word of the name
+const INSIDE: u32 = 40;
+/// points taken off a typo match for each typo
+const PER_TYPO: u32 = 15;
+/// points taken off for each word of the name which wasn't typed, so "milk" ranks above
+/// "milk chocolate"
+const PER_EXTRA_WORD: u32 = 2;
+
+/// points for each time the flatmate searching has used a name themselves
+const PER_OWN_USE: u32 = 4;
+/// points for each time anyone has bought a name
+const PER_PURCHASE: u32 = 2;
+/// uses and purchases beyond this many stop adding points, so that a match is never buried under
+/// a popular name which barely matches
+const MAX_COUNTED: u32 = 10;
+
+/// split a name into its lowercase words
+fn words(name: &str) -> Vec<String> {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// the number of single character insertions, deletions, substitutions and swaps of neighbouring
+/// characters it takes to turn `a` into `b`
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    // rows[i][j] is the distance between the first i characters of a and the first j of b
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+
+    rows[a.len()][b.len()]
+}
+
+/// how many typos are forgiven in a typed word, short words have to be typed correctly as a
+/// single typo makes them a different word altogether
+fn typos_allowed(typed: &[char]) -> usize {
+    match typed.len() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+/// how well a typed word matches a word of a name, if at all
+fn word_score(typed: &str, word: &str) -> Option<u32> {
+    if word == typed {
+        return Some(EXACT);
+    }
+    if word.starts_with(typed) {
+        return Some(PREFIX);
+    }
+
+    let typed_chars: Vec<char> = typed.chars().collect();
+    let word_chars: Vec<char> = word.chars().collect();
+    let allowed = typos_allowed(&typed_chars);
+
+    let mut best = None;
+    if allowed > 0 {
+        let typos = edit_distance(&typed_chars, &word_chars);
+        if typos <= allowed {
+            best = Some(TYPO - PER_TYPO * typos as u32);
+        }
+
+        // the word may not have been finished yet, so compare against the start of it too
+        let start = &word_chars[..word_chars.len().min(typed_chars.len())];
+        let typos = edit_distance(&typed_chars, start);
+        if typos <= allowed {
+            best = best.max(Some(TYPO_PREFIX - PER_TYPO * typos as u32));
+        }
+    }
+
+    if typed_chars.len() > 1 && word.contains(typed) {
+        best = best.max(Some(INSIDE));
+    }
+
+    best
+}
+
+/// how well what has been typed matches a name, `None` if any typed word matches none of the
+/// words of the name
+fn score(typed: &str, name: &str) -> Option<u32> {
+    let typed = words(typed);
+    let name = words(name);
+
+    let mut total = 0;
+    for typed_word in &typed {
+        total += name
+            .iter()
+            .filter_map(|word| word_score(typed_word, word))
+            .max()?;
+    }
+
+    let extra_words = name.len().saturating_sub(typed.len()) as u32;
+    Some(total.saturating_sub(PER_EXTRA_WORD * extra_words))
+}
+
+/// whether two names are most likely the same thing, written with their words in a different
+/// order or with a typo or two, e.g. "milk 2L" and "2L Milk"
+pub fn same_name(a: &str, b: &str) -> bool {
+    let a = words(a);
+    let mut b = words(b);
+    if a.is_empty() || a.len() != b.len() {
+        return false;
+    }
+
+    for word in &a {
+        let chars: Vec<char> = word.chars().collect();
+        // a typo in a short word is as likely to be a different word, e.g. "tea" and "pea"
+        let allowed = if chars.len() < 5 {
+            0
+        } else {
+            typos_allowed(&chars)
+        };
+
+        // pair every word off with a word of the other name, each only once
+        let found = b.iter().position(|other| other == word).or_else(|| {
+            b.iter().position(|other| {
+                edit_distance(&chars, &other.chars().collect::<Vec<_>>()) <= allowed
+            })
+        });
+        match found {
+            Some(i) => {
+                b.swap_remove(i);
+            }
+            None => return false,
+        }
+    }
+
+    true
+}
+
+/// A name which could be suggested, along with how much it's been used
+#[derive(Debug)]
+struct Candidate {
+    /// the name as it was last written
+    name: String,
+    /// how many times the flatmate searching has used it
+    own_uses: u32,
+    /// how many times anyone has bought it
+    purchases: u32,
+}
+
+impl Candidate {
+    /// the points a name earns for being used a lot, whatever was typed
+    fn popularity(&self) -> u32 {
+        self.own_uses.min(MAX_COUNTED) * PER_OWN_USE
+            + self.purchases.min(MAX_COUNTED) * PER_PURCHASE
+    }
+}
+
+/// The names that could be suggested for an autocomplete option, gathered from history, with the
+/// same name written differently (e.g. "Milk" and "milk") treated as one
+#[derive(Debug, Default)]
+pub struct Candidates {
+    /// each name, keyed by its lowercase words
+    by_key: HashMap<String, Candidate>,
+}
+
+impl Candidates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add a use of a name. Names are expected newest first, so the first way a name is written
+    /// is the one suggested
+    pub fn add(&mut self, name: &str, own: bool, bought: bool) {
+        let key = words(name).join(" ");
+        if key.is_empty() {
+            return;
+        }
+
+        let candidate = self.by_key.entry(key).or_insert_with(|| Candidate {
+            name: name.trim().to_string(),
+            own_uses: 0,
+            purchases: 0,
+        });
+        candidate.own_uses += u32::from(own);
+        candidate.purchases += u32::from(bought);
+    }
+
+    /// the best `limit` names for what has been typed, best first. Nothing typed suggests the
+    /// most used names
+    pub fn rank(self, typed: &str, limit: usize) -> Vec<String> {
+        let mut ranked: Vec<(u32, Candidate)> = self
+            .by_key
+            .into_values()
+            .filter_map(|candidate| {
+                let score = score(typed, &candidate.name)?;
+                Some((score + candidate.popularity(), candidate))
+            })
+            .collect();
+
+        ranked.sort_by(|(a_score, a), (b_score, b)| {
+            Reverse(a_score)
+                .cmp(&Reverse(b_score))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(_, candidate)| candidate.name)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: &str, b: &str) -> usize {
+        edit_distance(
+            &a.chars().collect::<Vec<_>>(),
+            &b.chars().collect::<Vec<_>>(),
+        )
+    }
+
+    fn rank(names: &[&str], typed: &str) -> Vec<String> {
+        let mut candidates = Candidates::new();
+        for name in names {
+            candidates.add(name, false, false);
+        }
+        candidates.rank(typed, 25)
+    }
+
+    #[test]
+    fn edit_distance_counts_each_kind_of_typo() {
+        assert_eq!(distance("milk", "milk"), 0);
+        assert_eq!(distance("mlk", "milk"), 1);
+        assert_eq!(distance("milks", "milk"), 1);
+        assert_eq!(distance("silk", "milk"), 1);
+        assert_eq!(distance("toliet", "toilet"), 1);
+        assert_eq!(distance("", "milk"), 4);
+        assert_eq!(distance("bread", "milk"), 5);
+    }
+
+    #[test]
+    fn same_name_ignores_case_and_word_order() {
+        assert!(same_name("milk 2L", "2L Milk"));
+        assert!(same_name("Toilet paper", "toliet paper"));
+        assert!(!same_name("tea", "pea"));
+        assert!(!same_name("milk", "milk chocolate"));
+        assert!(!same_name("milk milk", "milk bread"));
+        assert!(!same_name("", ""));
+    }
+
+    #[test]
+    fn score_forgives_typos_in_longer_words() {
+        assert!(score("toliet paper", "Toilet Paper").is_some());
+        assert!(score("paper toilet", "Toilet Paper").is_some());
+        assert!(score("dishwashng", "Dishwashing liquid").is_some());
+        assert!(score("cntdown", "Countdown").is_some());
+        assert!(score("xyz", "Toilet Paper").is_none());
+        assert!(score("pe", "tea").is_none());
+    }
+
+    #[test]
+    fn score_prefers_closer_matches() {
+        assert!(score("milk", "Milk") > score("milk", "Milk chocolate"));
+        assert!(score("mil", "Milk") > score("mlk", "Milk"));
+        assert!(score("choc", "Chocolate") > score("late", "Chocolate"));
+    }
+
+    #[test]
+    fn rank_finds_typos() {
+        let names = ["Toilet paper", "Paper towels", "Milk", "Dishwashing liquid"];
+        assert_eq!(rank(&names, "toliet paper")[0], "Toilet paper");
+        assert_eq!(rank(&names, "dishwashng"), vec!["Dishwashing liquid"]);
+        assert_eq!(
+            rank(&["Countdown", "Pak'nSave"], "cntdown"),
+            vec!["Countdown"]
+        );
+        assert!(rank(&names, "xyz").is_empty());
+    }
+
+    #[test]
+    fn rank_merges_names_and_weights_history() {
+        let mut candidates = Candidates::new();
+        candidates.add("Milk", false, false);
+        candidates.add("milk", true, true);
+        candidates.add("Milk chocolate", false, false);
+        candidates.add("Mince", true, true);
+        assert_eq!(candidates.rank("MILK", 25), vec!["Milk", "Milk chocolate"]);
+
+        let mut candidates = Candidates::new();
+        candidates.add("Bread", false, false);
+        candidates.add("Bananas", true, true);
+        assert_eq!(candidates.rank("", 1), vec!["Bananas"]);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod distance;
 pub mod embed;
+pub mod fuzzy;
@@ -3,14 +3,18 @@ pub type DatabaseResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync +
 pub mod shopping {
     use crate::state::AppState;
     use chrono::Local;
+    use chrono::NaiveDateTime;
+    use sea_orm::sea_query::Expr;
     use sea_orm::ActiveValue;
     use sea_orm::ColumnTrait;
     use sea_orm::EntityTrait;
     use sea_orm::IntoActiveModel;
     use sea_orm::ModelTrait;
+    use sea_orm::PaginatorTrait;
     use sea_orm::QueryFilter;
     use sea_orm::QueryOrder;
     use sea_orm::QuerySelect;
+    use sea_orm::Select;
     use serenity::async_trait;
 
     use super::DatabaseResult;
@@ -23,9 +27,19 @@ pub mod shopping {
     pub use entity::list_item::Entity as ShoppingListItemEntity;
     pub use entity::list_item::Model as ShoppingListItemModel;
 
+    pub use entity::pending_item::ActiveModel as PendingItemActiveModel;
+    pub use entity::pending_item::Entity as PendingItemEntity;
+    pub use entity::pending_item::Model as PendingItemModel;
+
     use sea_orm::ActiveModelTrait;
 
+    /// the name of the list created when something is added to the shopping list of a guild which
+    /// has no lists yet
+    pub const DEFAULT_LIST_NAME: &str = "shopping";
+
     pub struct NewShoppingListItem<'a> {
+        /// the list to add the item to, the guild's current list if `None`
+        pub list_id: Option<i32>,
         pub item: &'a str,
         pub store: Option<&'a str>,
         pub notes: Option<&'a str>,
@@ -33,12 +47,81 @@ pub mod shopping {
         pub personal: bool,
     }
 
+    /// every list of a guild which hasn't been archived
+    fn open_lists(guild_id: Option<u64>) -> Select<ShoppingListEntity> {
+        let column = <ShoppingListEntity as EntityTrait>::Column::CreationMessageGuildId;
+        ShoppingListEntity::find()
+            .filter(match guild_id {
+                Some(guild_id) => column.eq(guild_id as i64),
+                None => column.is_null(),
+            })
+            .filter(<ShoppingListEntity as EntityTrait>::Column::ArchivedAt.is_null())
+    }
+
     #[async_trait]
     pub trait SerenityShoppingDatabase {
+        /// the current shopping list of a guild along with its items. This is the list last switched
+        /// to, or the newest list which hasn't been archived if none has been switched to
         async fn get_shopping_list(
             &self,
+            guild_id: Option<u64>,
         ) -> DatabaseResult<Option<(ShoppingListModel, Vec<ShoppingListItemModel>)>>;
 
+        /// every shopping list of a guild which hasn't been archived, oldest first
+        async fn get_shopping_lists(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<ShoppingListModel>>;
+
+        /// the shopping list of a guild with the given name, if it hasn't been archived
+        async fn get_shopping_list_by_name(
+            &self,
+            guild_id: Option<u64>,
+            name: &str,
+        ) -> DatabaseResult<Option<ShoppingListModel>>;
+
+        /// a shopping list by its id, whether or not it has been archived
+        async fn get_shopping_list_by_id(
+            &self,
+            list_id: i32,
+        ) -> DatabaseResult<Option<ShoppingListModel>>;
+
+        /// the shopping list which a board message shows
+        async fn get_shopping_list_by_board(
+            &self,
+            message_id: u64,
+        ) -> DatabaseResult<Option<ShoppingListModel>>;
+
+        /// record the message showing a shopping list's board
+        async fn set_shopping_list_board(
+            &self,
+            list_id: i32,
+            channel_id: u64,
+            message_id: u64,
+        ) -> DatabaseResult<ShoppingListModel>;
+
+        /// create a shopping list, and make it the current list of its guild
+        async fn add_shopping_list(
+            &self,
+
+            user: u64,
+            message_id: u64,
+            channel_id: u64,
+            guild_id: Option<u64>,
+
+            name: &str,
+        ) -> DatabaseResult<ShoppingListModel>;
+
+        /// make a shopping list the current list of its guild
+        async fn set_shopping_list_active(&self, list_id: i32)
+            -> DatabaseResult<ShoppingListModel>;
+
+        /// archive a shopping list, so that nothing more is added to it
+        async fn set_shopping_list_archived(
+            &self,
+            list_id: i32,
+        ) -> DatabaseResult<ShoppingListModel>;
+
         async fn add_shopping_list_item(
             &self,
 
@@ -60,6 +143,7 @@ pub mod shopping {
             message_id: u64,
         ) -> DatabaseResult<Option<ShoppingListItemModel>>;
 
+        /// mark an item as bought by a flatmate, or no longer bought
         async fn set_shopping_list_item_bought(
             &self,
             user: u64,
@@ -67,38 +151,102 @@ pub mod shopping {
             setting: bool,
         ) -> DatabaseResult<()>;
 
+        /// change how many of an item are needed
+        async fn set_shopping_list_item_quantity(
+            &self,
+            id: i32,
+            quantity: i64,
+        ) -> DatabaseResult<ShoppingListItemModel>;
+
+        /// take an item off the shopping list without it having been bought
+        async fn set_shopping_list_item_cleared(&self, message_id: u64) -> DatabaseResult<()>;
+
+        /// the items a flatmate most recently added to the shopping lists of a guild, newest first
         async fn get_recent_shopping_list_items_by_user(
             &self,
+            guild_id: Option<u64>,
             user: u64,
             count: u64,
         ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
 
+        /// the items most recently added to the shopping lists of a guild, newest first
         async fn get_recent_shopping_list_items(
             &self,
+            guild_id: Option<u64>,
             count: u64,
         ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
 
+        /// every item of a shopping list which is yet to be bought, oldest first
         async fn get_unbought_shopping_list_items(
             &self,
+            list_id: i32,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        /// the items of a shopping list which have been bought or taken off it, newest first
+        async fn get_finished_shopping_list_items(
+            &self,
+            list_id: i32,
+            count: u64,
         ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        /// mark the end of a shopping trip, items bought after this count towards the next trip
+        async fn set_shopping_list_completed(
+            &self,
+            list_id: i32,
+        ) -> DatabaseResult<ShoppingListModel>;
+
+        /// a page of every item ever added to the shopping lists of a guild, newest first, along
+        /// with the number of pages
+        async fn get_shopping_history_page(
+            &self,
+            guild_id: Option<u64>,
+            page: u64,
+            per_page: u64,
+        ) -> DatabaseResult<(Vec<ShoppingListItemModel>, u64)>;
+
+        /// hold an item back from the list of the item it duplicates, until whoever added it
+        /// decides what to do about it
+        async fn add_pending_item(
+            &self,
+            user: u64,
+            duplicate: &ShoppingListItemModel,
+            item: NewShoppingListItem<'_>,
+        ) -> DatabaseResult<PendingItemModel>;
+
+        /// take an item which was held back to deal with it, `None` if it has already been dealt
+        /// with
+        async fn take_pending_item(&self, id: i32) -> DatabaseResult<Option<PendingItemModel>>;
+
+        /// forget every item held back before the given time, which can no longer be answered
+        async fn remove_pending_items_before(&self, before: NaiveDateTime) -> DatabaseResult<()>;
     }
 
     #[async_trait]
     impl SerenityShoppingDatabase for AppState {
         async fn get_shopping_list(
             &self,
+            guild_id: Option<u64>,
         ) -> DatabaseResult<Option<(ShoppingListModel, Vec<ShoppingListItemModel>)>> {
-            // try to load the most recent shopping list from the database
-            // XXX: should work for multiple guilds at some point
-            let shopping_list: Option<ShoppingListModel> = ShoppingListEntity::find()
-                .order_by_desc(<ShoppingListEntity as EntityTrait>::Column::CreatedAt)
+            // prefer the list which was last switched to, falling back to the most recent list
+            // for guilds which have never switched lists
+            let active: Option<ShoppingListModel> = open_lists(guild_id)
+                .filter(<ShoppingListEntity as EntityTrait>::Column::Active.eq(true))
                 .one(&*self.database)
                 .await?;
+            let shopping_list = match active {
+                Some(shopping_list) => shopping_list,
+                None => {
+                    let newest = open_lists(guild_id)
+                        .order_by_desc(<ShoppingListEntity as EntityTrait>::Column::CreatedAt)
+                        .one(&*self.database)
+                        .await?;
 
-            if shopping_list.is_none() {
-                return Ok(None);
-            }
-            let shopping_list = shopping_list.unwrap();
+                    match newest {
+                        Some(shopping_list) => shopping_list,
+                        None => return Ok(None),
+                    }
+                }
+            };
 
             // load the items for the shopping list
             let items = shopping_list
@@ -109,6 +257,150 @@ pub mod shopping {
             Ok(Some((shopping_list, items)))
         }
 
+        async fn get_shopping_lists(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<ShoppingListModel>> {
+            let lists = open_lists(guild_id)
+                .order_by_asc(<ShoppingListEntity as EntityTrait>::Column::CreatedAt)
+                .all(&*self.database)
+                .await?;
+
+            Ok(lists)
+        }
+
+        async fn get_shopping_list_by_name(
+            &self,
+            guild_id: Option<u64>,
+            name: &str,
+        ) -> DatabaseResult<Option<ShoppingListModel>> {
+            let list = open_lists(guild_id)
+                .filter(<ShoppingListEntity as EntityTrait>::Column::Name.eq(name))
+                .one(&*self.database)
+                .await?;
+
+            Ok(list)
+        }
+
+        async fn get_shopping_list_by_id(
+            &self,
+            list_id: i32,
+        ) -> DatabaseResult<Option<ShoppingListModel>> {
+            let list = ShoppingListEntity::find_by_id(list_id)
+                .one(&*self.database)
+                .await?;
+
+            Ok(list)
+        }
+
+        async fn get_shopping_list_by_board(
+            &self,
+            message_id: u64,
+        ) -> DatabaseResult<Option<ShoppingListModel>> {
+            let list = ShoppingListEntity::find()
+                .filter(
+                    <ShoppingListEntity as EntityTrait>::Column::BoardMessageId
+                        .eq(message_id as i64),
+                )
+                .one(&*self.database)
+                .await?;
+
+            Ok(list)
+        }
+
+        async fn set_shopping_list_board(
+            &self,
+            list_id: i32,
+            channel_id: u64,
+            message_id: u64,
+        ) -> DatabaseResult<ShoppingListModel> {
+            let shopping_list = ShoppingListEntity::find_by_id(list_id)
+                .one(&*self.database)
+                .await?
+                .ok_or("shopping list not found")?;
+
+            let mut shopping_list = shopping_list.into_active_model();
+            shopping_list.board_channel_id = ActiveValue::Set(Some(channel_id as i64));
+            shopping_list.board_message_id = ActiveValue::Set(Some(message_id as i64));
+
+            Ok(shopping_list.update(&*self.database).await?)
+        }
+
+        async fn add_shopping_list(
+            &self,
+
+            user: u64,
+            message_id: u64,
+            channel_id: u64,
+            guild_id: Option<u64>,
+
+            name: &str,
+        ) -> DatabaseResult<ShoppingListModel> {
+            let shopping_list = ShoppingListActiveModel {
+                id: ActiveValue::NotSet,
+                name: ActiveValue::Set(name.to_string()),
+                created_by: ActiveValue::Set(user as i64),
+                created_at: ActiveValue::Set(Local::now().naive_local()),
+                creation_message_id: ActiveValue::Set(message_id as i64),
+                creation_message_channel_id: ActiveValue::Set(channel_id as i64),
+                creation_message_guild_id: ActiveValue::Set(guild_id.map(|g| g as i64)),
+                active: ActiveValue::Set(false),
+                archived_at: ActiveValue::Set(None),
+                completed_at: ActiveValue::Set(None),
+                board_message_id: ActiveValue::Set(None),
+                board_channel_id: ActiveValue::Set(None),
+            }
+            .insert(&*self.database)
+            .await?;
+
+            self.set_shopping_list_active(shopping_list.id).await
+        }
+
+        async fn set_shopping_list_active(
+            &self,
+            list_id: i32,
+        ) -> DatabaseResult<ShoppingListModel> {
+            let shopping_list = ShoppingListEntity::find_by_id(list_id)
+                .one(&*self.database)
+                .await?
+                .ok_or("shopping list not found")?;
+
+            // only one list in a guild is current at a time
+            let column = <ShoppingListEntity as EntityTrait>::Column::CreationMessageGuildId;
+            ShoppingListEntity::update_many()
+                .col_expr(
+                    <ShoppingListEntity as EntityTrait>::Column::Active,
+                    Expr::value(false),
+                )
+                .filter(match shopping_list.creation_message_guild_id {
+                    Some(guild_id) => column.eq(guild_id),
+                    None => column.is_null(),
+                })
+                .exec(&*self.database)
+                .await?;
+
+            let mut shopping_list = shopping_list.into_active_model();
+            shopping_list.active = ActiveValue::Set(true);
+
+            Ok(shopping_list.update(&*self.database).await?)
+        }
+
+        async fn set_shopping_list_archived(
+            &self,
+            list_id: i32,
+        ) -> DatabaseResult<ShoppingListModel> {
+            let shopping_list = ShoppingListEntity::find_by_id(list_id)
+                .one(&*self.database)
+                .await?
+                .ok_or("shopping list not found")?;
+
+            let mut shopping_list = shopping_list.into_active_model();
+            shopping_list.active = ActiveValue::Set(false);
+            shopping_list.archived_at = ActiveValue::Set(Some(Local::now().naive_local()));
+
+            Ok(shopping_list.update(&*self.database).await?)
+        }
+
         async fn add_shopping_list_item(
             &self,
 
@@ -119,38 +411,29 @@ pub mod shopping {
 
             item: NewShoppingListItem<'_>,
         ) -> DatabaseResult<()> {
-            // try to load the most recent shopping list from the database, and add the item to it
-            // if no shopping list exists, create a new one
-
-            let shopping_list: Option<(ShoppingListModel, Vec<ShoppingListItemModel>)> =
-                self.get_shopping_list().await?;
-
-            let shopping_list = match shopping_list {
-                Some(s) => s.0,
-                None => {
-                    let shopping_list = ShoppingListActiveModel {
-                        id: ActiveValue::NotSet,
-                        name: ActiveValue::Set(item.item.to_string()),
-                        created_by: ActiveValue::Set(user as i64),
-                        created_at: ActiveValue::Set(Local::now().naive_local()),
-                        creation_message_id: ActiveValue::Set(message_id as i64),
-                        creation_message_channel_id: ActiveValue::Set(channel_id as i64),
-                        creation_message_guild_id: ActiveValue::Set(guild_id.map(|g| g as i64)),
-                    };
-
-                    shopping_list.insert(&*self.database).await?;
-
-                    ShoppingListEntity::find()
-                        .order_by_desc(<ShoppingListEntity as EntityTrait>::Column::CreatedAt)
-                        .one(&*self.database)
+            // add the item to the list it was given for, or else the guild's current shopping list.
+            // If the guild has no shopping list, create one
+            let list_id = match item.list_id {
+                Some(list_id) => list_id,
+                None => match self.get_shopping_list(guild_id).await? {
+                    Some((shopping_list, _)) => shopping_list.id,
+                    None => {
+                        self.add_shopping_list(
+                            user,
+                            message_id,
+                            channel_id,
+                            guild_id,
+                            DEFAULT_LIST_NAME,
+                        )
                         .await?
-                        .unwrap()
-                }
+                        .id
+                    }
+                },
             };
 
             let item = ShoppingListItemActiveModel {
                 id: ActiveValue::NotSet,
-                list_id: ActiveValue::Set(shopping_list.id),
+                list_id: ActiveValue::Set(list_id),
                 message_id: ActiveValue::Set(message_id as i64),
                 user_id: ActiveValue::Set(user as i64),
 
@@ -162,6 +445,9 @@ pub mod shopping {
                 personal: ActiveValue::Set(item.personal),
                 store: ActiveValue::Set(item.store.map(|s| s.to_string())),
                 notes: ActiveValue::Set(item.notes.map(|n| n.to_string())),
+                channel_id: ActiveValue::Set(Some(channel_id as i64)),
+                bought_by: ActiveValue::Set(None),
+                bought_at: ActiveValue::Set(None),
             };
             item.insert(&*self.database).await?;
 
@@ -206,25 +492,73 @@ pub mod shopping {
                     <ShoppingListItemEntity as EntityTrait>::Column::MessageId
                         .eq(message_id as i64),
                 )
-                .filter(<ShoppingListItemEntity as EntityTrait>::Column::UserId.eq(user as i64))
                 .one(&*self.database)
                 .await?;
 
             if let Some(shopping_list_item) = shopping_list_item {
                 let mut shopping_list_item = shopping_list_item.into_active_model();
                 shopping_list_item.bought = ActiveValue::Set(setting);
+                if setting {
+                    shopping_list_item.bought_by = ActiveValue::Set(Some(user as i64));
+                    shopping_list_item.bought_at =
+                        ActiveValue::Set(Some(Local::now().naive_local()));
+                } else {
+                    shopping_list_item.bought_by = ActiveValue::Set(None);
+                    shopping_list_item.bought_at = ActiveValue::Set(None);
+                }
                 shopping_list_item.update(&*self.database).await?;
             }
 
             Ok(())
         }
 
+        async fn set_shopping_list_item_quantity(
+            &self,
+            id: i32,
+            quantity: i64,
+        ) -> DatabaseResult<ShoppingListItemModel> {
+            let shopping_list_item = ShoppingListItemEntity::find_by_id(id)
+                .one(&*self.database)
+                .await?
+                .ok_or("shopping list item not found")?;
+
+            let mut shopping_list_item = shopping_list_item.into_active_model();
+            shopping_list_item.quantity = ActiveValue::Set(quantity);
+
+            Ok(shopping_list_item.update(&*self.database).await?)
+        }
+
+        async fn set_shopping_list_item_cleared(&self, message_id: u64) -> DatabaseResult<()> {
+            // cleared items are left marked as bought so they drop off the list, but without a
+            // buyer so they aren't counted towards a shopping trip
+            ShoppingListItemEntity::update_many()
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::Bought,
+                    Expr::value(true),
+                )
+                .filter(
+                    <ShoppingListItemEntity as EntityTrait>::Column::MessageId
+                        .eq(message_id as i64),
+                )
+                .exec(&*self.database)
+                .await?;
+
+            Ok(())
+        }
+
         async fn get_recent_shopping_list_items_by_user(
             &self,
+            guild_id: Option<u64>,
             user: u64,
             count: u64,
         ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let column = <ShoppingListEntity as EntityTrait>::Column::CreationMessageGuildId;
             let shopping_list: Vec<ShoppingListItemModel> = ShoppingListItemEntity::find()
+                .inner_join(ShoppingListEntity)
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
                 .filter(<ShoppingListItemEntity as EntityTrait>::Column::UserId.eq(user as i64))
                 .order_by_desc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
                 .limit(count)
@@ -236,9 +570,16 @@ pub mod shopping {
 
         async fn get_recent_shopping_list_items(
             &self,
+            guild_id: Option<u64>,
             count: u64,
         ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let column = <ShoppingListEntity as EntityTrait>::Column::CreationMessageGuildId;
             let shopping_list: Vec<ShoppingListItemModel> = ShoppingListItemEntity::find()
+                .inner_join(ShoppingListEntity)
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
                 .order_by_desc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
                 .limit(count)
                 .all(&*self.database)
@@ -249,14 +590,1299 @@ pub mod shopping {
 
         async fn get_unbought_shopping_list_items(
             &self,
+            list_id: i32,
         ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
             let shopping_list: Vec<ShoppingListItemModel> = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::ListId.eq(list_id))
                 .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .all(&*self.database)
+                .await?;
+
+            Ok(shopping_list)
+        }
+
+        async fn get_finished_shopping_list_items(
+            &self,
+            list_id: i32,
+            count: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let shopping_list: Vec<ShoppingListItemModel> = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::ListId.eq(list_id))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(true))
                 .order_by_desc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .limit(count)
                 .all(&*self.database)
                 .await?;
 
             Ok(shopping_list)
         }
+
+        async fn set_shopping_list_completed(
+            &self,
+            list_id: i32,
+        ) -> DatabaseResult<ShoppingListModel> {
+            let shopping_list = ShoppingListEntity::find_by_id(list_id)
+                .one(&*self.database)
+                .await?
+                .ok_or("shopping list not found")?;
+
+            let mut shopping_list = shopping_list.into_active_model();
+            shopping_list.completed_at = ActiveValue::Set(Some(Local::now().naive_local()));
+
+            Ok(shopping_list.update(&*self.database).await?)
+        }
+
+        async fn get_shopping_history_page(
+            &self,
+            guild_id: Option<u64>,
+            page: u64,
+            per_page: u64,
+        ) -> DatabaseResult<(Vec<ShoppingListItemModel>, u64)> {
+            let column = <ShoppingListEntity as EntityTrait>::Column::CreationMessageGuildId;
+            let paginator = ShoppingListItemEntity::find()
+                .inner_join(ShoppingListEntity)
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .order_by_desc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .paginate(&*self.database, per_page);
+
+            let pages = paginator.num_pages().await?;
+            let items = paginator.fetch_page(page).await?;
+
+            Ok((items, pages))
+        }
+
+        async fn add_pending_item(
+            &self,
+            user: u64,
+            duplicate: &ShoppingListItemModel,
+            item: NewShoppingListItem<'_>,
+        ) -> DatabaseResult<PendingItemModel> {
+            let pending = PendingItemActiveModel {
+                id: ActiveValue::NotSet,
+                list_id: ActiveValue::Set(duplicate.list_id),
+                duplicate_id: ActiveValue::Set(duplicate.id),
+                user_id: ActiveValue::Set(user as i64),
+                created_at: ActiveValue::Set(Local::now().naive_local()),
+                item: ActiveValue::Set(item.item.to_string()),
+                personal: ActiveValue::Set(item.personal),
+                quantity: ActiveValue::Set(item.quantity),
+                store: ActiveValue::Set(item.store.map(|s| s.to_string())),
+                notes: ActiveValue::Set(item.notes.map(|n| n.to_string())),
+            };
+
+            Ok(pending.insert(&*self.database).await?)
+        }
+
+        async fn take_pending_item(&self, id: i32) -> DatabaseResult<Option<PendingItemModel>> {
+            let pending = match PendingItemEntity::find_by_id(id)
+                .one(&*self.database)
+                .await?
+            {
+                Some(pending) => pending,
+                None => return Ok(None),
+            };
+
+            // only whoever removes it gets to deal with it, in case a button is pressed twice
+            let result = PendingItemEntity::delete_by_id(id)
+                .exec(&*self.database)
+                .await?;
+            if result.rows_affected == 0 {
+                return Ok(None);
+            }
+
+            Ok(Some(pending))
+        }
+
+        async fn remove_pending_items_before(&self, before: NaiveDateTime) -> DatabaseResult<()> {
+            PendingItemEntity::delete_many()
+                .filter(<PendingItemEntity as EntityTrait>::Column::CreatedAt.lt(before))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(())
+        }
+    }
+}
+
+pub mod payments {
+    use std::collections::HashSet;
+
+    use crate::finance::money::Money;
+    use crate::state::AppState;
+    use chrono::Local;
+    use chrono::NaiveDate;
+    use sea_orm::sea_query::Expr;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::DatabaseTransaction;
+    use sea_orm::EntityTrait;
+    use sea_orm::IntoActiveModel;
+    use sea_orm::PaginatorTrait;
+    use sea_orm::QueryFilter;
+    use sea_orm::QueryOrder;
+    use sea_orm::QuerySelect;
+    use sea_orm::TransactionTrait;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::payment::ActiveModel as PaymentActiveModel;
+    pub use entity::payment::Entity as PaymentEntity;
+    pub use entity::payment::Model as PaymentModel;
+
+    pub use entity::payment_revision::ActiveModel as PaymentRevisionActiveModel;
+    pub use entity::payment_revision::Entity as PaymentRevisionEntity;
+    pub use entity::payment_revision::Model as PaymentRevisionModel;
+
+    pub use entity::settlement::ActiveModel as SettlementActiveModel;
+    pub use entity::settlement::Entity as SettlementEntity;
+    pub use entity::settlement::Model as SettlementModel;
+
+    pub struct NewPayment<'a> {
+        pub purpose: &'a str,
+        pub account: &'a str,
+        pub receipt_url: &'a str,
+        /// the name of the receipt in the receipt archive, empty if it hasn't been archived
+        pub image_path: &'a str,
+        /// the total of the bill
+        pub total_amount: Money,
+        /// the flatmates who share this bill, and the amount each of them owes
+        pub shares: Vec<(u64, Money)>,
+        /// the reference each flatmate should pay their share with, in the same order as `shares`.
+        /// Filled in when the bill is posted
+        pub references: Vec<String>,
+        /// the first and last day the bill covers, for bills such as power which are for a period
+        pub period: Option<(NaiveDate, NaiveDate)>,
+    }
+
+    /// the outcome stored for a dispute which the bill's creator hasn't answered yet
+    pub const DISPUTE_OPEN: &str = "";
+    /// the outcome stored once the creator has accepted a dispute and adjusted the share
+    pub const DISPUTE_ACCEPTED: &str = "accepted";
+    /// the outcome stored once the creator has rejected a dispute, leaving the share as it was
+    pub const DISPUTE_REJECTED: &str = "rejected";
+
+    /// changes made to a bill after it was posted, fields which are `None` are left unchanged
+    pub struct PaymentEdit<'a> {
+        pub purpose: Option<&'a str>,
+        pub account: Option<&'a str>,
+        /// the new share for every flatmate in the bill, the total is updated to match
+        pub shares: Option<Vec<(u64, Money)>>,
+        /// the reference for each of the new shares, in the same order, given along with `shares`
+        pub references: Option<Vec<String>>,
+    }
+
+    #[async_trait]
+    pub trait SerenityPaymentDatabase {
+        async fn add_payment(
+            &self,
+
+            user: u64,
+            message_id: u64,
+            channel_id: u64,
+            guild_id: Option<u64>,
+
+            payment: NewPayment<'_>,
+        ) -> DatabaseResult<PaymentModel>;
+
+        async fn get_payment_by_message_id(
+            &self,
+            message_id: u64,
+        ) -> DatabaseResult<Option<PaymentModel>>;
+
+        async fn get_payment_by_id(&self, payment_id: i32) -> DatabaseResult<Option<PaymentModel>>;
+
+        async fn set_payment_share_paid(
+            &self,
+            payment_id: i32,
+            user: u64,
+        ) -> DatabaseResult<PaymentModel>;
+
+        /// record that a flatmate has just been reminded to pay their share of a bill
+        async fn set_payment_share_reminded(
+            &self,
+            payment_id: i32,
+            user: u64,
+        ) -> DatabaseResult<PaymentModel>;
+
+        /// record that a flatmate disputes their share of a bill, replacing any earlier dispute of
+        /// theirs which has been answered
+        async fn set_payment_share_disputed(
+            &self,
+            payment_id: i32,
+            user: u64,
+            reason: &str,
+        ) -> DatabaseResult<PaymentModel>;
+
+        /// record the creator's answer to a flatmate's dispute, one of the `DISPUTE_*` outcomes
+        async fn resolve_payment_share_dispute(
+            &self,
+            payment_id: i32,
+            user: u64,
+            outcome: &str,
+        ) -> DatabaseResult<PaymentModel>;
+
+        /// every bill in a guild which hasn't been voided, oldest first
+        async fn get_guild_payments(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<PaymentModel>>;
+
+        /// every bill in a guild, including voided bills, oldest first
+        async fn get_guild_payment_history(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<PaymentModel>>;
+
+        /// a page of every bill in a guild, including voided bills, newest first, along with the
+        /// number of pages
+        async fn get_guild_payment_history_page(
+            &self,
+            guild_id: Option<u64>,
+            page: u64,
+            per_page: u64,
+        ) -> DatabaseResult<(Vec<PaymentModel>, u64)>;
+
+        /// every bill created by a user in a guild, including voided bills, newest first
+        async fn get_user_payments(
+            &self,
+            guild_id: Option<u64>,
+            user: u64,
+        ) -> DatabaseResult<Vec<PaymentModel>>;
+
+        /// every payment reference that has been given out in a guild
+        async fn get_guild_share_references(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<HashSet<String>>;
+
+        async fn edit_payment(
+            &self,
+            payment_id: i32,
+            user: u64,
+            edit: PaymentEdit<'_>,
+        ) -> DatabaseResult<PaymentModel>;
+
+        async fn set_payment_voided(
+            &self,
+            payment_id: i32,
+            user: u64,
+            voided: bool,
+        ) -> DatabaseResult<PaymentModel>;
+
+        async fn get_payment_revisions(
+            &self,
+            payment_id: i32,
+        ) -> DatabaseResult<Vec<PaymentRevisionModel>>;
+
+        async fn set_payment_paid_in_full(&self, payment_id: i32) -> DatabaseResult<PaymentModel>;
+
+        async fn add_settlements(
+            &self,
+
+            user: u64,
+            plan_message_id: u64,
+            channel_id: u64,
+            guild_id: Option<u64>,
+
+            transfers: Vec<(u64, u64, Money)>,
+            payment_ids: Vec<i32>,
+        ) -> DatabaseResult<Vec<SettlementModel>>;
+
+        async fn get_settlement_by_id(&self, id: i32) -> DatabaseResult<Option<SettlementModel>>;
+
+        async fn get_plan_settlements(
+            &self,
+            plan_message_id: u64,
+        ) -> DatabaseResult<Vec<SettlementModel>>;
+
+        async fn get_unapplied_settlements(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<SettlementModel>>;
+
+        /// every transfer ever planned to settle up in a guild, applied or not
+        async fn get_guild_settlements(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<SettlementModel>>;
+
+        async fn remove_unsettled_settlements(&self, guild_id: Option<u64>) -> DatabaseResult<()>;
+
+        async fn set_settlement_settled(&self, id: i32) -> DatabaseResult<SettlementModel>;
+
+        async fn apply_settlements(
+            &self,
+            guild_id: Option<u64>,
+            payment_ids: &[i32],
+        ) -> DatabaseResult<Vec<PaymentModel>>;
+    }
+
+    #[async_trait]
+    impl SerenityPaymentDatabase for AppState {
+        async fn add_payment(
+            &self,
+
+            user: u64,
+            message_id: u64,
+            channel_id: u64,
+            guild_id: Option<u64>,
+
+            payment: NewPayment<'_>,
+        ) -> DatabaseResult<PaymentModel> {
+            let payment = PaymentActiveModel {
+                id: ActiveValue::NotSet,
+                total_amount: ActiveValue::Set(payment.total_amount.cents()),
+                created_by: ActiveValue::Set(user as i64),
+                created_at: ActiveValue::Set(Local::now().naive_local()),
+                originating_message_id: ActiveValue::Set(message_id as i64),
+                individual_users: ActiveValue::Set(
+                    payment.shares.iter().map(|(u, _)| *u as i64).collect(),
+                ),
+                individual_amounts: ActiveValue::Set(
+                    payment.shares.iter().map(|(_, a)| a.cents()).collect(),
+                ),
+                image_path: ActiveValue::Set(payment.image_path.to_string()),
+                purpose: ActiveValue::Set(payment.purpose.to_string()),
+                account: ActiveValue::Set(payment.account.to_string()),
+                receipt_url: ActiveValue::Set(payment.receipt_url.to_string()),
+                channel_id: ActiveValue::Set(channel_id as i64),
+                guild_id: ActiveValue::Set(guild_id.map(|g| g as i64)),
+                paid_users: ActiveValue::Set(vec![]),
+                paid_at: ActiveValue::Set(vec![]),
+                reminded_users: ActiveValue::Set(vec![]),
+                reminded_at: ActiveValue::Set(vec![]),
+                voided_at: ActiveValue::Set(None),
+                share_references: ActiveValue::Set(payment.references),
+                period_start: ActiveValue::Set(payment.period.map(|(start, _)| start)),
+                period_end: ActiveValue::Set(payment.period.map(|(_, end)| end)),
+                disputed_users: ActiveValue::Set(vec![]),
+                dispute_reasons: ActiveValue::Set(vec![]),
+                dispute_outcomes: ActiveValue::Set(vec![]),
+            };
+
+            Ok(payment.insert(&*self.database).await?)
+        }
+
+        async fn get_payment_by_message_id(
+            &self,
+            message_id: u64,
+        ) -> DatabaseResult<Option<PaymentModel>> {
+            let payment = PaymentEntity::find()
+                .filter(
+                    <PaymentEntity as EntityTrait>::Column::OriginatingMessageId
+                        .eq(message_id as i64),
+                )
+                .one(&*self.database)
+                .await?;
+
+            Ok(payment)
+        }
+
+        async fn get_payment_by_id(&self, payment_id: i32) -> DatabaseResult<Option<PaymentModel>> {
+            Ok(PaymentEntity::find_by_id(payment_id)
+                .one(&*self.database)
+                .await?)
+        }
+
+        async fn set_payment_share_paid(
+            &self,
+            payment_id: i32,
+            user: u64,
+        ) -> DatabaseResult<PaymentModel> {
+            let txn = self.database.begin().await?;
+            let payment = lock_payment(&txn, payment_id).await?;
+
+            if payment.paid_users.contains(&(user as i64)) {
+                txn.commit().await?;
+                return Ok(payment);
+            }
+
+            let mut paid_users = payment.paid_users.clone();
+            let mut paid_at = payment.paid_at.clone();
+            paid_users.push(user as i64);
+            paid_at.push(Local::now().naive_local());
+
+            let mut payment = payment.into_active_model();
+            payment.paid_users = ActiveValue::Set(paid_users);
+            payment.paid_at = ActiveValue::Set(paid_at);
+
+            let payment = payment.update(&txn).await?;
+            txn.commit().await?;
+
+            Ok(payment)
+        }
+
+        async fn set_payment_share_reminded(
+            &self,
+            payment_id: i32,
+            user: u64,
+        ) -> DatabaseResult<PaymentModel> {
+            let txn = self.database.begin().await?;
+            let payment = lock_payment(&txn, payment_id).await?;
+
+            // only the most recent reminder for each flatmate is kept
+            let mut reminded_users = payment.reminded_users.clone();
+            let mut reminded_at = payment.reminded_at.clone();
+            match reminded_users.iter().position(|u| *u == user as i64) {
+                Some(i) => reminded_at[i] = Local::now().naive_local(),
+                None => {
+                    reminded_users.push(user as i64);
+                    reminded_at.push(Local::now().naive_local());
+                }
+            }
+
+            let mut payment = payment.into_active_model();
+            payment.reminded_users = ActiveValue::Set(reminded_users);
+            payment.reminded_at = ActiveValue::Set(reminded_at);
+
+            let payment = payment.update(&txn).await?;
+            txn.commit().await?;
+
+            Ok(payment)
+        }
+
+        async fn set_payment_share_disputed(
+            &self,
+            payment_id: i32,
+            user: u64,
+            reason: &str,
+        ) -> DatabaseResult<PaymentModel> {
+            let txn = self.database.begin().await?;
+            let payment = lock_payment(&txn, payment_id).await?;
+
+            // each flatmate has at most one dispute on a bill, the most recent one
+            let mut disputed_users = payment.disputed_users.clone();
+            let mut dispute_reasons = payment.dispute_reasons.clone();
+            let mut dispute_outcomes = payment.dispute_outcomes.clone();
+            match disputed_users.iter().position(|u| *u == user as i64) {
+                Some(i) => {
+                    dispute_reasons[i] = reason.to_string();
+                    dispute_outcomes[i] = DISPUTE_OPEN.to_string();
+                }
+                None => {
+                    disputed_users.push(user as i64);
+                    dispute_reasons.push(reason.to_string());
+                    dispute_outcomes.push(DISPUTE_OPEN.to_string());
+                }
+            }
+
+            let mut payment = payment.into_active_model();
+            payment.disputed_users = ActiveValue::Set(disputed_users);
+            payment.dispute_reasons = ActiveValue::Set(dispute_reasons);
+            payment.dispute_outcomes = ActiveValue::Set(dispute_outcomes);
+
+            let payment = payment.update(&txn).await?;
+            txn.commit().await?;
+
+            Ok(payment)
+        }
+
+        async fn resolve_payment_share_dispute(
+            &self,
+            payment_id: i32,
+            user: u64,
+            outcome: &str,
+        ) -> DatabaseResult<PaymentModel> {
+            let txn = self.database.begin().await?;
+            let payment = lock_payment(&txn, payment_id).await?;
+
+            let i = payment
+                .disputed_users
+                .iter()
+                .position(|u| *u == user as i64)
+                .ok_or("dispute not found")?;
+            let mut dispute_outcomes = payment.dispute_outcomes.clone();
+            dispute_outcomes[i] = outcome.to_string();
+
+            let mut payment = payment.into_active_model();
+            payment.dispute_outcomes = ActiveValue::Set(dispute_outcomes);
+
+            let payment = payment.update(&txn).await?;
+            txn.commit().await?;
+
+            Ok(payment)
+        }
+
+        async fn get_guild_payments(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<PaymentModel>> {
+            let column = <PaymentEntity as EntityTrait>::Column::GuildId;
+            let payments = PaymentEntity::find()
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .filter(<PaymentEntity as EntityTrait>::Column::VoidedAt.is_null())
+                .order_by_asc(<PaymentEntity as EntityTrait>::Column::CreatedAt)
+                .all(&*self.database)
+                .await?;
+
+            Ok(payments)
+        }
+
+        async fn get_guild_payment_history(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<PaymentModel>> {
+            let column = <PaymentEntity as EntityTrait>::Column::GuildId;
+            let payments = PaymentEntity::find()
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .order_by_asc(<PaymentEntity as EntityTrait>::Column::CreatedAt)
+                .all(&*self.database)
+                .await?;
+
+            Ok(payments)
+        }
+
+        async fn get_guild_payment_history_page(
+            &self,
+            guild_id: Option<u64>,
+            page: u64,
+            per_page: u64,
+        ) -> DatabaseResult<(Vec<PaymentModel>, u64)> {
+            let column = <PaymentEntity as EntityTrait>::Column::GuildId;
+            let paginator = PaymentEntity::find()
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .order_by_desc(<PaymentEntity as EntityTrait>::Column::CreatedAt)
+                .paginate(&*self.database, per_page);
+
+            let pages = paginator.num_pages().await?;
+            let payments = paginator.fetch_page(page).await?;
+
+            Ok((payments, pages))
+        }
+
+        async fn get_user_payments(
+            &self,
+            guild_id: Option<u64>,
+            user: u64,
+        ) -> DatabaseResult<Vec<PaymentModel>> {
+            let column = <PaymentEntity as EntityTrait>::Column::GuildId;
+            let payments = PaymentEntity::find()
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .filter(<PaymentEntity as EntityTrait>::Column::CreatedBy.eq(user as i64))
+                .order_by_desc(<PaymentEntity as EntityTrait>::Column::CreatedAt)
+                .all(&*self.database)
+                .await?;
+
+            Ok(payments)
+        }
+
+        async fn get_guild_share_references(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<HashSet<String>> {
+            let column = <PaymentEntity as EntityTrait>::Column::GuildId;
+            let payments = PaymentEntity::find()
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .all(&*self.database)
+                .await?;
+
+            Ok(payments
+                .into_iter()
+                .flat_map(|payment| payment.share_references)
+                .collect())
+        }
+
+        async fn edit_payment(
+            &self,
+            payment_id: i32,
+            user: u64,
+            edit: PaymentEdit<'_>,
+        ) -> DatabaseResult<PaymentModel> {
+            let txn = self.database.begin().await?;
+            let payment = lock_payment(&txn, payment_id).await?;
+
+            record_revision(&txn, &payment, user, "edit").await?;
+
+            let mut payment = payment.into_active_model();
+            if let Some(purpose) = edit.purpose {
+                payment.purpose = ActiveValue::Set(purpose.to_string());
+            }
+            if let Some(account) = edit.account {
+                payment.account = ActiveValue::Set(account.to_string());
+            }
+            if let Some(shares) = edit.shares {
+                payment.total_amount =
+                    ActiveValue::Set(shares.iter().map(|(_, a)| a).sum::<Money>().cents());
+                payment.individual_users =
+                    ActiveValue::Set(shares.iter().map(|(u, _)| *u as i64).collect());
+                payment.individual_amounts =
+                    ActiveValue::Set(shares.iter().map(|(_, a)| a.cents()).collect());
+            }
+            if let Some(references) = edit.references {
+                payment.share_references = ActiveValue::Set(references);
+            }
+
+            let payment = payment.update(&txn).await?;
+            txn.commit().await?;
+
+            Ok(payment)
+        }
+
+        async fn set_payment_voided(
+            &self,
+            payment_id: i32,
+            user: u64,
+            voided: bool,
+        ) -> DatabaseResult<PaymentModel> {
+            let txn = self.database.begin().await?;
+            let payment = lock_payment(&txn, payment_id).await?;
+
+            record_revision(&txn, &payment, user, if voided { "void" } else { "reopen" }).await?;
+
+            let mut payment = payment.into_active_model();
+            payment.voided_at = ActiveValue::Set(voided.then(|| Local::now().naive_local()));
+
+            let payment = payment.update(&txn).await?;
+            txn.commit().await?;
+
+            Ok(payment)
+        }
+
+        async fn get_payment_revisions(
+            &self,
+            payment_id: i32,
+        ) -> DatabaseResult<Vec<PaymentRevisionModel>> {
+            let revisions = PaymentRevisionEntity::find()
+                .filter(<PaymentRevisionEntity as EntityTrait>::Column::PaymentId.eq(payment_id))
+                .order_by_asc(<PaymentRevisionEntity as EntityTrait>::Column::RevisedAt)
+                .all(&*self.database)
+                .await?;
+
+            Ok(revisions)
+        }
+
+        async fn set_payment_paid_in_full(&self, payment_id: i32) -> DatabaseResult<PaymentModel> {
+            let txn = self.database.begin().await?;
+            let payment = lock_payment(&txn, payment_id).await?;
+
+            let mut paid_users = payment.paid_users.clone();
+            let mut paid_at = payment.paid_at.clone();
+            for user in payment.individual_users.iter() {
+                if !paid_users.contains(user) {
+                    paid_users.push(*user);
+                    paid_at.push(Local::now().naive_local());
+                }
+            }
+
+            let mut payment = payment.into_active_model();
+            payment.paid_users = ActiveValue::Set(paid_users);
+            payment.paid_at = ActiveValue::Set(paid_at);
+
+            let payment = payment.update(&txn).await?;
+            txn.commit().await?;
+
+            Ok(payment)
+        }
+
+        async fn add_settlements(
+            &self,
+
+            user: u64,
+            plan_message_id: u64,
+            channel_id: u64,
+            guild_id: Option<u64>,
+
+            transfers: Vec<(u64, u64, Money)>,
+            payment_ids: Vec<i32>,
+        ) -> DatabaseResult<Vec<SettlementModel>> {
+            let mut settlements = Vec::with_capacity(transfers.len());
+
+            for (debtor, creditor, amount) in transfers {
+                let settlement = SettlementActiveModel {
+                    id: ActiveValue::NotSet,
+                    guild_id: ActiveValue::Set(guild_id.map(|g| g as i64)),
+                    channel_id: ActiveValue::Set(channel_id as i64),
+                    plan_message_id: ActiveValue::Set(plan_message_id as i64),
+                    created_by: ActiveValue::Set(user as i64),
+                    created_at: ActiveValue::Set(Local::now().naive_local()),
+                    debtor: ActiveValue::Set(debtor as i64),
+                    creditor: ActiveValue::Set(creditor as i64),
+                    amount: ActiveValue::Set(amount.cents()),
+                    payment_ids: ActiveValue::Set(payment_ids.clone()),
+                    settled_at: ActiveValue::Set(None),
+                    applied: ActiveValue::Set(false),
+                };
+
+                settlements.push(settlement.insert(&*self.database).await?);
+            }
+
+            Ok(settlements)
+        }
+
+        async fn get_settlement_by_id(&self, id: i32) -> DatabaseResult<Option<SettlementModel>> {
+            Ok(SettlementEntity::find_by_id(id)
+                .one(&*self.database)
+                .await?)
+        }
+
+        async fn get_plan_settlements(
+            &self,
+            plan_message_id: u64,
+        ) -> DatabaseResult<Vec<SettlementModel>> {
+            let settlements = SettlementEntity::find()
+                .filter(
+                    <SettlementEntity as EntityTrait>::Column::PlanMessageId
+                        .eq(plan_message_id as i64),
+                )
+                .order_by_asc(<SettlementEntity as EntityTrait>::Column::Id)
+                .all(&*self.database)
+                .await?;
+
+            Ok(settlements)
+        }
+
+        async fn get_unapplied_settlements(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<SettlementModel>> {
+            let column = <SettlementEntity as EntityTrait>::Column::GuildId;
+            let settlements = SettlementEntity::find()
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .filter(<SettlementEntity as EntityTrait>::Column::Applied.eq(false))
+                .all(&*self.database)
+                .await?;
+
+            Ok(settlements)
+        }
+
+        async fn get_guild_settlements(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<SettlementModel>> {
+            let column = <SettlementEntity as EntityTrait>::Column::GuildId;
+            let settlements = SettlementEntity::find()
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .all(&*self.database)
+                .await?;
+
+            Ok(settlements)
+        }
+
+        async fn remove_unsettled_settlements(&self, guild_id: Option<u64>) -> DatabaseResult<()> {
+            let column = <SettlementEntity as EntityTrait>::Column::GuildId;
+            SettlementEntity::delete_many()
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .filter(<SettlementEntity as EntityTrait>::Column::SettledAt.is_null())
+                .exec(&*self.database)
+                .await?;
+
+            Ok(())
+        }
+
+        async fn set_settlement_settled(&self, id: i32) -> DatabaseResult<SettlementModel> {
+            let settlement = SettlementEntity::find_by_id(id)
+                .one(&*self.database)
+                .await?
+                .ok_or("settlement not found")?;
+
+            let mut settlement = settlement.into_active_model();
+            settlement.settled_at = ActiveValue::Set(Some(Local::now().naive_local()));
+
+            Ok(settlement.update(&*self.database).await?)
+        }
+
+        async fn apply_settlements(
+            &self,
+            guild_id: Option<u64>,
+            payment_ids: &[i32],
+        ) -> DatabaseResult<Vec<PaymentModel>> {
+            let mut payments = Vec::with_capacity(payment_ids.len());
+            for payment_id in payment_ids {
+                payments.push(self.set_payment_paid_in_full(*payment_id).await?);
+            }
+
+            // every transfer made so far has now been accounted for in the bills themselves
+            let column = <SettlementEntity as EntityTrait>::Column::GuildId;
+            SettlementEntity::update_many()
+                .col_expr(
+                    <SettlementEntity as EntityTrait>::Column::Applied,
+                    Expr::value(true),
+                )
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .filter(<SettlementEntity as EntityTrait>::Column::SettledAt.is_not_null())
+                .exec(&*self.database)
+                .await?;
+
+            Ok(payments)
+        }
+    }
+
+    /// lock a bill until the transaction ends, so that a change worked out from the bill as it is
+    /// now can't undo another change made to it at the same time, e.g. two flatmates pressing
+    /// Paid together
+    async fn lock_payment(
+        txn: &DatabaseTransaction,
+        payment_id: i32,
+    ) -> DatabaseResult<PaymentModel> {
+        Ok(PaymentEntity::find_by_id(payment_id)
+            .lock_exclusive()
+            .one(txn)
+            .await?
+            .ok_or("payment not found")?)
+    }
+
+    /// keep a copy of a bill as it was before being changed
+    async fn record_revision(
+        txn: &DatabaseTransaction,
+        payment: &PaymentModel,
+        user: u64,
+        action: &str,
+    ) -> DatabaseResult<PaymentRevisionModel> {
+        let revision = PaymentRevisionActiveModel {
+            id: ActiveValue::NotSet,
+            payment_id: ActiveValue::Set(payment.id),
+            revised_by: ActiveValue::Set(user as i64),
+            revised_at: ActiveValue::Set(Local::now().naive_local()),
+            action: ActiveValue::Set(action.to_string()),
+            purpose: ActiveValue::Set(payment.purpose.clone()),
+            account: ActiveValue::Set(payment.account.clone()),
+            total_amount: ActiveValue::Set(payment.total_amount),
+            individual_users: ActiveValue::Set(payment.individual_users.clone()),
+            individual_amounts: ActiveValue::Set(payment.individual_amounts.clone()),
+        };
+
+        Ok(revision.insert(txn).await?)
+    }
+}
+
+pub mod kitty {
+    use crate::finance::money::Money;
+    use crate::state::AppState;
+    use chrono::Local;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::QueryFilter;
+    use sea_orm::QueryOrder;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::kitty_entry::ActiveModel as KittyEntryActiveModel;
+    pub use entity::kitty_entry::Entity as KittyEntryEntity;
+    pub use entity::kitty_entry::Model as KittyEntryModel;
+
+    pub struct NewKittyEntry<'a> {
+        /// the amount put into the kitty, negative for money spent from it
+        pub amount: Money,
+        pub description: &'a str,
+        pub receipt_url: &'a str,
+        /// the name of the receipt in the receipt archive, empty for top ups
+        pub image_path: &'a str,
+    }
+
+    #[async_trait]
+    pub trait SerenityKittyDatabase {
+        async fn add_kitty_entry(
+            &self,
+
+            user: u64,
+            channel_id: u64,
+            guild_id: Option<u64>,
+
+            entry: NewKittyEntry<'_>,
+        ) -> DatabaseResult<KittyEntryModel>;
+
+        /// every top up and purchase from the kitty of a guild, oldest first
+        async fn get_kitty_entries(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<KittyEntryModel>>;
+    }
+
+    #[async_trait]
+    impl SerenityKittyDatabase for AppState {
+        async fn add_kitty_entry(
+            &self,
+
+            user: u64,
+            channel_id: u64,
+            guild_id: Option<u64>,
+
+            entry: NewKittyEntry<'_>,
+        ) -> DatabaseResult<KittyEntryModel> {
+            let entry = KittyEntryActiveModel {
+                id: ActiveValue::NotSet,
+                guild_id: ActiveValue::Set(guild_id.map(|g| g as i64)),
+                channel_id: ActiveValue::Set(channel_id as i64),
+                user_id: ActiveValue::Set(user as i64),
+                amount: ActiveValue::Set(entry.amount.cents()),
+                description: ActiveValue::Set(entry.description.to_string()),
+                receipt_url: ActiveValue::Set(entry.receipt_url.to_string()),
+                image_path: ActiveValue::Set(entry.image_path.to_string()),
+                created_at: ActiveValue::Set(Local::now().naive_local()),
+            };
+
+            Ok(entry.insert(&*self.database).await?)
+        }
+
+        async fn get_kitty_entries(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<KittyEntryModel>> {
+            let column = <KittyEntryEntity as EntityTrait>::Column::GuildId;
+            let entries = KittyEntryEntity::find()
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .order_by_asc(<KittyEntryEntity as EntityTrait>::Column::CreatedAt)
+                .all(&*self.database)
+                .await?;
+
+            Ok(entries)
+        }
+    }
+}
+
+pub mod recurring {
+    use crate::finance::money::Money;
+    use crate::state::AppState;
+    use chrono::Local;
+    use chrono::NaiveDateTime;
+    use sea_orm::sea_query::Expr;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::QueryFilter;
+    use sea_orm::QueryOrder;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::recurring_bill::ActiveModel as RecurringBillActiveModel;
+    pub use entity::recurring_bill::Entity as RecurringBillEntity;
+    pub use entity::recurring_bill::Model as RecurringBillModel;
+
+    pub struct NewRecurringBill<'a> {
+        pub purpose: &'a str,
+        pub account: &'a str,
+        /// the total of the bill
+        pub total_amount: Money,
+        /// the flatmates who share this bill, and the amount each of them owes
+        pub shares: Vec<(u64, Money)>,
+        pub frequency: &'a str,
+        pub day_of_month: Option<i32>,
+        pub next_run: NaiveDateTime,
+    }
+
+    #[async_trait]
+    pub trait SerenityRecurringBillDatabase {
+        async fn add_recurring_bill(
+            &self,
+
+            user: u64,
+            channel_id: u64,
+            guild_id: Option<u64>,
+
+            bill: NewRecurringBill<'_>,
+        ) -> DatabaseResult<RecurringBillModel>;
+
+        async fn get_guild_recurring_bills(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<RecurringBillModel>>;
+
+        async fn get_due_recurring_bills(
+            &self,
+            guild_id: Option<u64>,
+            now: NaiveDateTime,
+        ) -> DatabaseResult<Vec<RecurringBillModel>>;
+
+        /// move a recurring bill on to its next run, as long as it's still due to run at
+        /// `previous`. Returns false if something else has already moved it on
+        async fn set_recurring_bill_next_run(
+            &self,
+            id: i32,
+            previous: NaiveDateTime,
+            next_run: NaiveDateTime,
+        ) -> DatabaseResult<bool>;
+
+        /// remove a recurring bill, returning true if one was found to remove
+        async fn remove_recurring_bill(
+            &self,
+            guild_id: Option<u64>,
+            id: i32,
+        ) -> DatabaseResult<bool>;
+    }
+
+    #[async_trait]
+    impl SerenityRecurringBillDatabase for AppState {
+        async fn add_recurring_bill(
+            &self,
+
+            user: u64,
+            channel_id: u64,
+            guild_id: Option<u64>,
+
+            bill: NewRecurringBill<'_>,
+        ) -> DatabaseResult<RecurringBillModel> {
+            let bill = RecurringBillActiveModel {
+                id: ActiveValue::NotSet,
+                guild_id: ActiveValue::Set(guild_id.map(|g| g as i64)),
+                channel_id: ActiveValue::Set(channel_id as i64),
+                created_by: ActiveValue::Set(user as i64),
+                created_at: ActiveValue::Set(Local::now().naive_local()),
+                purpose: ActiveValue::Set(bill.purpose.to_string()),
+                account: ActiveValue::Set(bill.account.to_string()),
+                total_amount: ActiveValue::Set(bill.total_amount.cents()),
+                individual_users: ActiveValue::Set(
+                    bill.shares.iter().map(|(u, _)| *u as i64).collect(),
+                ),
+                individual_amounts: ActiveValue::Set(
+                    bill.shares.iter().map(|(_, a)| a.cents()).collect(),
+                ),
+                frequency: ActiveValue::Set(bill.frequency.to_string()),
+                day_of_month: ActiveValue::Set(bill.day_of_month),
+                next_run: ActiveValue::Set(bill.next_run),
+            };
+
+            Ok(bill.insert(&*self.database).await?)
+        }
+
+        async fn get_guild_recurring_bills(
+            &self,
+            guild_id: Option<u64>,
+        ) -> DatabaseResult<Vec<RecurringBillModel>> {
+            let column = <RecurringBillEntity as EntityTrait>::Column::GuildId;
+            let bills = RecurringBillEntity::find()
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .order_by_asc(<RecurringBillEntity as EntityTrait>::Column::NextRun)
+                .all(&*self.database)
+                .await?;
+
+            Ok(bills)
+        }
+
+        async fn get_due_recurring_bills(
+            &self,
+            guild_id: Option<u64>,
+            now: NaiveDateTime,
+        ) -> DatabaseResult<Vec<RecurringBillModel>> {
+            let column = <RecurringBillEntity as EntityTrait>::Column::GuildId;
+            let bills = RecurringBillEntity::find()
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .filter(<RecurringBillEntity as EntityTrait>::Column::NextRun.lte(now))
+                .all(&*self.database)
+                .await?;
+
+            Ok(bills)
+        }
+
+        async fn set_recurring_bill_next_run(
+            &self,
+            id: i32,
+            previous: NaiveDateTime,
+            next_run: NaiveDateTime,
+        ) -> DatabaseResult<bool> {
+            let result = RecurringBillEntity::update_many()
+                .col_expr(
+                    <RecurringBillEntity as EntityTrait>::Column::NextRun,
+                    Expr::value(next_run),
+                )
+                .filter(<RecurringBillEntity as EntityTrait>::Column::Id.eq(id))
+                .filter(<RecurringBillEntity as EntityTrait>::Column::NextRun.eq(previous))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(result.rows_affected > 0)
+        }
+
+        async fn remove_recurring_bill(
+            &self,
+            guild_id: Option<u64>,
+            id: i32,
+        ) -> DatabaseResult<bool> {
+            let column = <RecurringBillEntity as EntityTrait>::Column::GuildId;
+            let result = RecurringBillEntity::delete_many()
+                .filter(<RecurringBillEntity as EntityTrait>::Column::Id.eq(id))
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .exec(&*self.database)
+                .await?;
+
+            Ok(result.rows_affected > 0)
+        }
+    }
+}
+
+pub mod reminders {
+    use crate::state::AppState;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::EntityTrait;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::reminder_settings::ActiveModel as ReminderSettingsActiveModel;
+    pub use entity::reminder_settings::Entity as ReminderSettingsEntity;
+    pub use entity::reminder_settings::Model as ReminderSettingsModel;
+
+    #[async_trait]
+    pub trait SerenityReminderDatabase {
+        async fn get_reminder_settings(
+            &self,
+            guild_id: u64,
+        ) -> DatabaseResult<Option<ReminderSettingsModel>>;
+
+        /// create or replace the reminder settings for a guild
+        async fn set_reminder_settings(
+            &self,
+            settings: ReminderSettingsModel,
+        ) -> DatabaseResult<ReminderSettingsModel>;
+    }
+
+    #[async_trait]
+    impl SerenityReminderDatabase for AppState {
+        async fn get_reminder_settings(
+            &self,
+            guild_id: u64,
+        ) -> DatabaseResult<Option<ReminderSettingsModel>> {
+            Ok(ReminderSettingsEntity::find_by_id(guild_id as i64)
+                .one(&*self.database)
+                .await?)
+        }
+
+        async fn set_reminder_settings(
+            &self,
+            settings: ReminderSettingsModel,
+        ) -> DatabaseResult<ReminderSettingsModel> {
+            let existing = ReminderSettingsEntity::find_by_id(settings.guild_id)
+                .one(&*self.database)
+                .await?;
+
+            let active = ReminderSettingsActiveModel {
+                guild_id: ActiveValue::Set(settings.guild_id),
+                enabled: ActiveValue::Set(settings.enabled),
+                unpaid_after_hours: ActiveValue::Set(settings.unpaid_after_hours),
+                interval_hours: ActiveValue::Set(settings.interval_hours),
+                quiet_start: ActiveValue::Set(settings.quiet_start),
+                quiet_end: ActiveValue::Set(settings.quiet_end),
+            };
+
+            Ok(match existing {
+                Some(_) => active.update(&*self.database).await?,
+                None => active.insert(&*self.database).await?,
+            })
+        }
+    }
+}
+
+pub mod statements {
+    use crate::state::AppState;
+    use chrono::Local;
+    use chrono::NaiveDate;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::QueryFilter;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::sent_statement::ActiveModel as SentStatementActiveModel;
+    pub use entity::sent_statement::Entity as SentStatementEntity;
+
+    #[async_trait]
+    pub trait SerenityStatementDatabase {
+        /// the flatmates who have already been sent their statement for a month
+        async fn get_sent_statement_users(
+            &self,
+            guild_id: Option<u64>,
+            month: NaiveDate,
+        ) -> DatabaseResult<Vec<u64>>;
+
+        /// record that a flatmate has been sent their statement for a month
+        async fn add_sent_statement(
+            &self,
+            guild_id: Option<u64>,
+            user: u64,
+            month: NaiveDate,
+        ) -> DatabaseResult<()>;
+    }
+
+    #[async_trait]
+    impl SerenityStatementDatabase for AppState {
+        async fn get_sent_statement_users(
+            &self,
+            guild_id: Option<u64>,
+            month: NaiveDate,
+        ) -> DatabaseResult<Vec<u64>> {
+            let column = <SentStatementEntity as EntityTrait>::Column::GuildId;
+            let sent = SentStatementEntity::find()
+                .filter(match guild_id {
+                    Some(guild_id) => column.eq(guild_id as i64),
+                    None => column.is_null(),
+                })
+                .filter(<SentStatementEntity as EntityTrait>::Column::Month.eq(month))
+                .all(&*self.database)
+                .await?;
+
+            Ok(sent.into_iter().map(|s| s.user_id as u64).collect())
+        }
+
+        async fn add_sent_statement(
+            &self,
+            guild_id: Option<u64>,
+            user: u64,
+            month: NaiveDate,
+        ) -> DatabaseResult<()> {
+            SentStatementActiveModel {
+                id: ActiveValue::NotSet,
+                guild_id: ActiveValue::Set(guild_id.map(|g| g as i64)),
+                user_id: ActiveValue::Set(user as i64),
+                month: ActiveValue::Set(month),
+                sent_at: ActiveValue::Set(Local::now().naive_local()),
+            }
+            .insert(&*self.database)
+            .await?;
+
+            Ok(())
+        }
     }
 }
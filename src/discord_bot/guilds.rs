@@ -11,24 +11,35 @@ use serenity::{
     builder::{CreateAutocompleteResponse, CreateInteractionResponse},
     client::Context,
     futures::{stream::FuturesUnordered, StreamExt},
-    model::{id::GuildId, prelude::Message},
+    model::{
+        id::{ChannelId, GuildId, MessageId},
+        prelude::Message,
+    },
 };
 use tokio::{
     select,
     sync::mpsc::{UnboundedReceiver, UnboundedSender},
     sync::RwLock,
     task::JoinHandle,
+    time::MissedTickBehavior,
 };
 
 use super::manager::{DiscordEvent, InternalSender};
 use crate::{
     discord_bot::{
-        commands::{application_command, autocomplete, command, interaction as handle_interaction},
+        commands::{
+            application_command, autocomplete, command, interaction as handle_interaction,
+            modal as handle_modal, rebuild_deleted_board,
+        },
         messages::non_command_message,
+        scheduler::run_scheduled_tasks,
     },
     state::AppState,
 };
 
+/// how often scheduled tasks (such as recurring bills) are checked for each guild
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(60);
+
 /// handle an interaction generated by slash command.
 /// matches over the type of interaction and then handles it appropriately, generating a response that can be sent to the user
 async fn handle_slash_command(interaction: Interaction, context: Context, app_state: AppState) {
@@ -60,8 +71,18 @@ async fn handle_slash_command(interaction: Interaction, context: Context, app_st
         }
         Interaction::Component(component) => {
             trace!("Received component interaction: {:?}", component);
-            if let Err(e) = handle_interaction(&component, &app_state, &context).await {
-                error!("Unable to handle component interaction: {:?}", e);
+            let response = match handle_interaction(&component, &app_state, &context).await {
+                Ok(response) => response,
+                Err(response) => {
+                    response.write_to_log();
+                    response
+                }
+            };
+
+            if let Some(resp) = response.generate_response() {
+                if let Err(e) = component.create_response(&context, resp).await {
+                    error!("Unable to send response: {:?}", e);
+                }
             }
         }
         Interaction::Autocomplete(interaction) => {
@@ -81,7 +102,20 @@ async fn handle_slash_command(interaction: Interaction, context: Context, app_st
             }
         }
         Interaction::Modal(submit) => {
-            error!("Received modal submit: {:?}", submit);
+            trace!("Received modal submit: {:?}", submit);
+            let response = match handle_modal(&submit, &app_state, &context).await {
+                Ok(response) => response,
+                Err(response) => {
+                    response.write_to_log();
+                    response
+                }
+            };
+
+            if let Some(resp) = response.generate_response() {
+                if let Err(e) = submit.create_response(&context, resp).await {
+                    error!("Unable to send response: {:?}", e);
+                }
+            }
         }
         // ping commands should not get here
         _ => unreachable!(),
@@ -95,6 +129,21 @@ async fn handle_sent_message(message: Message, context: Context, app_state: AppS
         .unwrap();
 }
 
+/// put back anything the bot keeps up to date which was in a deleted message
+async fn handle_deleted_message(
+    channel_id: ChannelId,
+    message_id: MessageId,
+    context: Context,
+    app_state: AppState,
+) {
+    trace!("Message deleted: {:?}", message_id);
+    if let Err(response) =
+        rebuild_deleted_board(&app_state, &context, channel_id, message_id.into()).await
+    {
+        response.write_to_log();
+    }
+}
+
 /// a handler which manages a guild, interacting with and responding to all events as required
 pub struct GuildHandler {
     /// the id of the guild being managed, generated by discord
@@ -186,6 +235,10 @@ impl GuildHandler {
 
                 let mut internal_rx = internal_rx.write().await;
                 let mut task_handles = FuturesUnordered::new();
+                let mut scheduler_interval = tokio::time::interval(SCHEDULER_INTERVAL);
+                scheduler_interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                // only one run of the scheduled tasks at a time, so nothing is posted or sent twice
+                let mut scheduler_run: Option<JoinHandle<()>> = None;
 
                 loop {
                     select! {
@@ -215,11 +268,30 @@ impl GuildHandler {
                                         handle_sent_message(*msg, t_ctx, t_app_state).await;
                                     }))
                                 }
+                                DiscordEvent::MessageDeleted { channel_id, message_id, .. } => {
+                                    let t_ctx = context.clone();
+                                    let t_app_state = app_state.clone();
+                                    task_handles.push(tokio::task::spawn(async move {
+                                        handle_deleted_message(channel_id, message_id, t_ctx, t_app_state).await;
+                                    }))
+                                }
                                 e => {
                                     error!("bot ignoring unexpected event: {:?}", e);
                                 }
                             }
                         },
+                        _ = scheduler_interval.tick() => {
+                            if scheduler_run.as_ref().is_some_and(|run| !run.is_finished()) {
+                                trace!("scheduled tasks for {} are still running, skipping", guild);
+                                continue;
+                            }
+
+                            let t_ctx = context.clone();
+                            let t_app_state = app_state.clone();
+                            scheduler_run = Some(tokio::task::spawn(async move {
+                                run_scheduled_tasks(guild, t_ctx, t_app_state).await;
+                            }));
+                        },
                         // drain task handles as they complete
                         _ = task_handles.next(), if !task_handles.is_empty() => {},
                         else => break,
@@ -231,6 +303,11 @@ impl GuildHandler {
                     //XXX: timeout is not implemented yet
                     while task_handles.next().await.is_some() {}
                 }
+                if let Some(run) = scheduler_run {
+                    if let Err(e) = run.await {
+                        error!("scheduled tasks for {} failed: {}", guild, e);
+                    }
+                }
 
                 println!("No longer monitoring server with id {:?}", guild);
 
@@ -10,7 +10,7 @@ use serenity::{
     model::{
         gateway::Ready,
         guild::{Guild, Member, UnavailableGuild},
-        prelude::Message,
+        prelude::{ChannelId, GuildId, Message, MessageId},
     },
 };
 
@@ -58,6 +58,38 @@ impl EventHandler for Handler {
         }
     }
 
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        guild_id: Option<GuildId>,
+    ) {
+        // only messages in guilds are of any interest, e.g. shopping list boards
+        let guild_id = match guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+
+        let reader = ctx.data.read().await;
+
+        let internal_sender = match reader.get::<InternalSender>() {
+            Some(internal_sender) => internal_sender,
+            None => {
+                error!("InternalSender not found in context");
+                return;
+            }
+        };
+
+        if let Err(e) = internal_sender.send(DiscordEvent::MessageDeleted {
+            guild_id,
+            channel_id,
+            message_id,
+        }) {
+            error!("Error sending deleted message to internal sender: {:?}", e);
+        }
+    }
+
     async fn guild_member_addition(&self, _ctx: Context, _new_member: Member) {
         warn!("New member joined, handler function not yet implemented");
         // todo!() //TODO: use this to readd a users roles if they have previously been verified
@@ -7,7 +7,7 @@ use log::{error, warn};
 use serenity::{
     all::Interaction,
     futures::{stream::FuturesUnordered, StreamExt},
-    model::prelude::Message,
+    model::prelude::{ChannelId, GuildId, Message, MessageId},
     prelude::{GatewayIntents, TypeMapKey},
     Client,
 };
@@ -30,6 +30,15 @@ pub enum DiscordEvent {
     Interaction(Box<Interaction>),
     /// a new message received from any guild
     Message(Box<Message>),
+    /// a message was deleted from a guild
+    MessageDeleted {
+        /// the guild the message was deleted from
+        guild_id: GuildId,
+        /// the channel the message was in
+        channel_id: ChannelId,
+        /// the message which was deleted
+        message_id: MessageId,
+    },
     /// a shutdown command to be sent to a guild, when received the guild should cease all activity and shut down
     Shutdown,
 }
@@ -55,6 +64,19 @@ impl DerefMut for InternalSender {
     }
 }
 
+/// Interactions with components sent in DMs have no guild id, so those components carry the guild they
+/// belong to as the second part of their custom id, e.g. `remind:<guild id>:<payment id>`. The same
+/// goes for modals opened from those components.
+fn dm_component_guild(interaction: &Interaction) -> Option<u64> {
+    let custom_id = match interaction {
+        Interaction::Component(c) => &c.data.custom_id,
+        Interaction::Modal(m) => &m.data.custom_id,
+        _ => return None,
+    };
+
+    custom_id.split(':').nth(1)?.parse().ok()
+}
+
 /// A builder for the discord bot
 pub struct DiscordBotBuilder<T> {
     /// the discord token to use for authentication with the discord api
@@ -190,8 +212,8 @@ impl<T: Send + Sync + 'static + Clone + TypeMapKey<Value = T>> DiscordBot<T> {
                                     _ => todo!("Currently, all branches are covered - more may become available in the future!"),
                                 };
 
-                                let guild_id: u64 = match guild_id {
-                                    Some(g_id) => g_id.0.into(),
+                                let guild_id: u64 = match guild_id.map(|g_id| g_id.0.into()).or_else(|| dm_component_guild(&interaction)) {
+                                    Some(g_id) => g_id,
                                     None => {
                                         error!("got interaction without guild id");
                                         continue;
@@ -230,6 +252,20 @@ impl<T: Send + Sync + 'static + Clone + TypeMapKey<Value = T>> DiscordBot<T> {
                                     error!("failed to send message to guild handler {}", e);
                                 }
                             }
+                            DiscordEvent::MessageDeleted { guild_id, channel_id, message_id } => {
+                                let key: u64 = guild_id.into();
+                                let g_h = match guild_handlers.get(&key) {
+                                    Some(s) => s.internal_tx.clone(),
+                                    None => {
+                                        error!("tried to handle deleted message for non-existant guild id {}", guild_id);
+                                        continue;
+                                    }
+                                };
+
+                                if let Err(e) = g_h.send(DiscordEvent::MessageDeleted { guild_id, channel_id, message_id }) {
+                                    error!("failed to send deleted message to guild handler {}", e);
+                                }
+                            }
                             e => error!("unexpected discord event received {:?}", e),
                         }
                     },
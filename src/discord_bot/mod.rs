@@ -9,6 +9,7 @@ mod guilds;
 mod handler;
 mod manager;
 mod messages;
+mod scheduler;
 mod utils;
 
 pub use manager::{DiscordBot, DiscordBotBuilder};
@@ -0,0 +1,344 @@
+//! Runs tasks for a guild on a timer, such as posting bills which recur on a fixed schedule,
+//! reminding flatmates about bills they haven't paid and sending monthly statements.
+
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use log::{error, info};
+use serenity::{client::Context, model::id::ChannelId, model::id::GuildId};
+
+use crate::{
+    finance::{money::Money, monthly},
+    state::{AppState, CONFIG},
+};
+
+use super::{
+    commands::{in_quiet_hours, open_dispute, post_bill, send_reminder, send_statement},
+    database::{
+        payments::{NewPayment, SerenityPaymentDatabase},
+        recurring::{RecurringBillModel, SerenityRecurringBillDatabase},
+        reminders::SerenityReminderDatabase,
+        statements::SerenityStatementDatabase,
+    },
+};
+
+/// the time of day that scheduled bills are posted
+const POST_TIME: (u32, u32) = (9, 0);
+
+/// How often a recurring bill is posted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    Weekly,
+    Fortnightly,
+    /// monthly on the given day, limited to the 28th so that every month has the day
+    Monthly {
+        day: u32,
+    },
+}
+
+impl Schedule {
+    /// the frequencies that can be chosen, as stored in the database
+    pub const FREQUENCIES: &'static [&'static str] = &["weekly", "fortnightly", "monthly"];
+
+    /// parse a schedule from the values stored in the database
+    pub fn from_parts(frequency: &str, day_of_month: Option<i32>) -> Option<Self> {
+        match (frequency, day_of_month) {
+            ("weekly", _) => Some(Schedule::Weekly),
+            ("fortnightly", _) => Some(Schedule::Fortnightly),
+            ("monthly", Some(day)) if (1..=28).contains(&day) => {
+                Some(Schedule::Monthly { day: day as u32 })
+            }
+            _ => None,
+        }
+    }
+
+    /// the frequency of this schedule, as stored in the database
+    pub fn frequency(&self) -> &'static str {
+        match self {
+            Schedule::Weekly => "weekly",
+            Schedule::Fortnightly => "fortnightly",
+            Schedule::Monthly { .. } => "monthly",
+        }
+    }
+
+    /// the day of the month for this schedule, as stored in the database
+    pub fn day_of_month(&self) -> Option<i32> {
+        match self {
+            Schedule::Monthly { day } => Some(*day as i32),
+            _ => None,
+        }
+    }
+
+    /// the first time this schedule should run, starting from the given date
+    pub fn first_run(&self, start: NaiveDate) -> NaiveDateTime {
+        let time = NaiveTime::from_hms_opt(POST_TIME.0, POST_TIME.1, 0).expect("valid time");
+
+        match self {
+            Schedule::Weekly | Schedule::Fortnightly => start.and_time(time),
+            Schedule::Monthly { day } => {
+                let this_month = start.with_day(*day).expect("day is at most 28");
+                if this_month >= start {
+                    this_month.and_time(time)
+                } else {
+                    (this_month + Months::new(1)).and_time(time)
+                }
+            }
+        }
+    }
+
+    /// the next time this schedule should run after the given run
+    pub fn next_after(&self, previous: NaiveDateTime) -> NaiveDateTime {
+        match self {
+            Schedule::Weekly => previous + Duration::weeks(1),
+            Schedule::Fortnightly => previous + Duration::weeks(2),
+            Schedule::Monthly { .. } => previous + Months::new(1),
+        }
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Schedule::Weekly => write!(f, "every week"),
+            Schedule::Fortnightly => write!(f, "every fortnight"),
+            Schedule::Monthly { day } => write!(
+                f,
+                "monthly on the {}{}",
+                day,
+                match day {
+                    1 | 21 => "st",
+                    2 | 22 => "nd",
+                    3 | 23 => "rd",
+                    _ => "th",
+                }
+            ),
+        }
+    }
+}
+
+/// move a single recurring bill on to its next run, and post it if nothing else got to it first
+async fn post_recurring_bill(
+    bill: RecurringBillModel,
+    now: NaiveDateTime,
+    context: &Context,
+    app_state: &AppState,
+) {
+    let schedule = match Schedule::from_parts(&bill.frequency, bill.day_of_month) {
+        Some(schedule) => schedule,
+        None => {
+            error!(
+                "recurring bill {} has an invalid schedule `{}` ({:?})",
+                bill.id, bill.frequency, bill.day_of_month
+            );
+            return;
+        }
+    };
+
+    // if the bot was offline for a while, skip the runs that were missed rather than posting them all at once
+    let mut next_run = schedule.next_after(bill.next_run);
+    while next_run <= now {
+        next_run = schedule.next_after(next_run);
+    }
+
+    // claim this run before posting, so a bill is never posted twice for the same run
+    match app_state
+        .set_recurring_bill_next_run(bill.id, bill.next_run, next_run)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            info!("recurring bill {} has already been posted", bill.id);
+            return;
+        }
+        Err(e) => {
+            error!(
+                "failed to schedule next run of recurring bill {}: {}",
+                bill.id, e
+            );
+            return;
+        }
+    }
+
+    let payment = NewPayment {
+        purpose: &bill.purpose,
+        account: &bill.account,
+        receipt_url: "",
+        image_path: "",
+        total_amount: Money::from_cents(bill.total_amount),
+        shares: bill
+            .individual_users
+            .iter()
+            .zip(bill.individual_amounts.iter())
+            .map(|(user, amount)| (*user as u64, Money::from_cents(*amount)))
+            .collect(),
+        references: Vec::new(),
+        period: None,
+    };
+
+    if let Err(e) = post_bill(
+        context,
+        app_state,
+        ChannelId::new(bill.channel_id as u64),
+        bill.guild_id.map(|g| g as u64),
+        bill.created_by as u64,
+        payment,
+    )
+    .await
+    {
+        error!("failed to post recurring bill {}: {}", bill.id, e);
+    }
+}
+
+/// post any recurring bills for this guild which have come due
+async fn post_recurring_bills(guild_id: GuildId, context: &Context, app_state: &AppState) {
+    let now = chrono::offset::Local::now().naive_local();
+
+    let bills = match app_state
+        .get_due_recurring_bills(Some(guild_id.0.into()), now)
+        .await
+    {
+        Ok(bills) => bills,
+        Err(e) => {
+            error!("failed to load recurring bills for {}: {}", guild_id, e);
+            return;
+        }
+    };
+
+    for bill in bills {
+        info!("posting recurring bill {} for {}", bill.id, guild_id);
+        post_recurring_bill(bill, now, context, app_state).await;
+    }
+}
+
+/// DM every flatmate whose share of a bill has been unpaid for too long, if reminders are turned on
+async fn send_reminders(guild_id: GuildId, context: &Context, app_state: &AppState) {
+    let settings = match app_state.get_reminder_settings(guild_id.0.into()).await {
+        Ok(Some(settings)) if settings.enabled => settings,
+        Ok(_) => return,
+        Err(e) => {
+            error!("failed to load reminder settings for {}: {}", guild_id, e);
+            return;
+        }
+    };
+
+    let now = chrono::offset::Local::now().naive_local();
+    if in_quiet_hours(now.hour(), &settings) {
+        return;
+    }
+
+    let payments = match app_state.get_guild_payments(Some(guild_id.0.into())).await {
+        Ok(payments) => payments,
+        Err(e) => {
+            error!("failed to load bills for {}: {}", guild_id, e);
+            return;
+        }
+    };
+
+    let unpaid_after = Duration::hours(settings.unpaid_after_hours as i64);
+    let interval = Duration::hours(settings.interval_hours as i64);
+
+    for payment in payments.iter() {
+        if now - payment.created_at < unpaid_after {
+            continue;
+        }
+
+        for (user, amount) in payment
+            .individual_users
+            .iter()
+            .zip(payment.individual_amounts.iter())
+        {
+            if *user == payment.created_by || *amount == 0 || payment.paid_users.contains(user) {
+                continue;
+            }
+            // they aren't asked to pay a share they've disputed until the creator has answered
+            if open_dispute(payment, *user as u64).is_some() {
+                continue;
+            }
+
+            let last_reminded = payment
+                .reminded_users
+                .iter()
+                .position(|u| u == user)
+                .and_then(|i| payment.reminded_at.get(i));
+            if matches!(last_reminded, Some(last) if now - *last < interval) {
+                continue;
+            }
+
+            // record the reminder before sending it, a missed reminder is sent again after the
+            // interval but one that's sent and not recorded would be sent again every run
+            if let Err(e) = app_state
+                .set_payment_share_reminded(payment.id, *user as u64)
+                .await
+            {
+                error!("failed to record reminder for bill {}: {}", payment.id, e);
+                continue;
+            }
+
+            info!("reminding {} about bill {}", user, payment.id);
+            if let Err(e) = send_reminder(context, guild_id, payment, *user as u64).await {
+                error!("failed to remind {} about bill {}: {}", user, payment.id, e);
+            }
+        }
+    }
+}
+
+/// DM every flatmate their statement for last month, once the first bills of this month would be
+/// posted. Flatmates with nothing to report are skipped, but recorded so they aren't checked again.
+async fn send_statements(guild_id: GuildId, context: &Context, app_state: &AppState) {
+    let now = chrono::offset::Local::now().naive_local();
+    if (now.hour(), now.minute()) < POST_TIME && now.day() == 1 {
+        return;
+    }
+
+    let guild: Option<u64> = Some(guild_id.0.into());
+    let month = monthly::first_of_month(now.date()) - Months::new(1);
+
+    let sent = match app_state.get_sent_statement_users(guild, month).await {
+        Ok(sent) => sent,
+        Err(e) => {
+            error!("failed to load sent statements for {}: {}", guild_id, e);
+            return;
+        }
+    };
+
+    let users: Vec<u64> = CONFIG
+        .flatmates
+        .iter()
+        .map(|f| f.discord_id)
+        .filter(|user| !sent.contains(user))
+        .collect();
+    if users.is_empty() {
+        return;
+    }
+
+    let payments = match app_state.get_guild_payment_history(guild).await {
+        Ok(payments) => payments,
+        Err(e) => {
+            error!("failed to load bills for {}: {}", guild_id, e);
+            return;
+        }
+    };
+
+    for user in users {
+        // record the statement before sending it, so it's never sent twice
+        if let Err(e) = app_state.add_sent_statement(guild, user, month).await {
+            error!("failed to record statement for {}: {}", user, e);
+            continue;
+        }
+
+        let statement = monthly::build(&payments, user, month);
+        if statement.is_empty() {
+            continue;
+        }
+
+        info!("sending {} their statement for {}", user, month);
+        if let Err(e) = send_statement(context, &statement).await {
+            error!("failed to send {} their statement: {}", user, e);
+        }
+    }
+}
+
+/// run every scheduled task for a guild, expected to be called regularly by the guild handler
+pub async fn run_scheduled_tasks(guild_id: GuildId, context: Context, app_state: AppState) {
+    post_recurring_bills(guild_id, &context, &app_state).await;
+    send_reminders(guild_id, &context, &app_state).await;
+    send_statements(guild_id, &context, &app_state).await;
+}
@@ -0,0 +1,349 @@
+//! NZ bank account numbers, checked with the bank, branch and check digit rules published by
+//! Payments NZ, so that a mistyped number is caught before anyone pays into it.
+
+use std::{fmt::Display, str::FromStr};
+
+/// the branches each bank may use, as inclusive ranges
+const BRANCHES: [(u8, &[(u16, u16)]); 33] = [
+    (1, &[(1, 999), (1100, 1199), (1800, 1899)]),
+    (2, &[(1, 999), (1200, 1299)]),
+    (
+        3,
+        &[
+            (1, 999),
+            (1300, 1399),
+            (1500, 1599),
+            (1700, 1799),
+            (1900, 1999),
+            (7350, 7399),
+        ],
+    ),
+    (4, &[(2020, 2024)]),
+    (5, &[(8884, 8889)]),
+    (6, &[(1, 999), (1400, 1499)]),
+    (8, &[(6500, 6599)]),
+    (9, &[(0, 0)]),
+    (10, &[(5165, 5169)]),
+    (11, &[(5000, 6499), (6600, 8999)]),
+    (12, &[(3000, 3299), (3400, 3499), (3600, 3699)]),
+    (13, &[(4900, 4999)]),
+    (14, &[(4700, 4799)]),
+    (15, &[(3900, 3999)]),
+    (16, &[(4400, 4499)]),
+    (17, &[(3300, 3399)]),
+    (18, &[(3500, 3599)]),
+    (19, &[(4600, 4649)]),
+    (20, &[(4100, 4199)]),
+    (21, &[(4800, 4899)]),
+    (22, &[(4000, 4049)]),
+    (23, &[(3700, 3799)]),
+    (24, &[(4300, 4399)]),
+    (25, &[(2500, 2599)]),
+    (26, &[(2600, 2699)]),
+    (27, &[(3800, 3849)]),
+    (28, &[(2100, 2149)]),
+    (29, &[(2150, 2299)]),
+    (30, &[(2900, 2949)]),
+    (31, &[(2800, 2849)]),
+    (33, &[(6700, 6799)]),
+    (35, &[(2400, 2499)]),
+    (38, &[(9000, 9499)]),
+];
+
+/// The weights applied to each of the 18 digits of an account number (2 bank, 4 branch, 8 account
+/// and 4 suffix), the modulus the weighted sum must divide by, and whether the digits of each
+/// product are summed before adding them up
+struct Algorithm {
+    weights: [u32; 18],
+    modulus: u32,
+    sum_digits: bool,
+}
+
+const ALGORITHM_A: Algorithm = Algorithm {
+    weights: [0, 0, 6, 3, 7, 9, 0, 0, 10, 5, 8, 4, 2, 1, 0, 0, 0, 0],
+    modulus: 11,
+    sum_digits: false,
+};
+const ALGORITHM_B: Algorithm = Algorithm {
+    weights: [0, 0, 0, 0, 0, 0, 0, 0, 10, 5, 8, 4, 2, 1, 0, 0, 0, 0],
+    modulus: 11,
+    sum_digits: false,
+};
+const ALGORITHM_D: Algorithm = Algorithm {
+    weights: [0, 0, 0, 0, 0, 0, 0, 7, 6, 5, 4, 3, 2, 1, 0, 0, 0, 0],
+    modulus: 11,
+    sum_digits: false,
+};
+const ALGORITHM_E: Algorithm = Algorithm {
+    weights: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5, 4, 3, 2, 0, 0, 0, 1],
+    modulus: 11,
+    sum_digits: true,
+};
+const ALGORITHM_F: Algorithm = Algorithm {
+    weights: [0, 0, 0, 0, 0, 0, 0, 1, 7, 3, 1, 7, 3, 1, 0, 0, 0, 0],
+    modulus: 10,
+    sum_digits: false,
+};
+const ALGORITHM_G: Algorithm = Algorithm {
+    weights: [0, 0, 0, 0, 0, 0, 0, 1, 3, 7, 1, 3, 7, 1, 0, 3, 7, 1],
+    modulus: 10,
+    sum_digits: true,
+};
+
+/// The reasons an account number isn't valid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountError {
+    /// not in the form `BB-bbbb-AAAAAAA-SS`
+    Format,
+    /// the bank number isn't one that is in use
+    UnknownBank(u8),
+    /// the branch doesn't belong to the bank
+    UnknownBranch(u8, u16),
+    /// the check digit is wrong, so some part of the number was mistyped
+    Checksum,
+}
+
+impl Display for AccountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountError::Format => write!(
+                f,
+                "An account number should look like `12-3456-7890123-00`, with the bank, branch, account and suffix."
+            ),
+            AccountError::UnknownBank(bank) => {
+                write!(f, "`{:02}` isn't the number of an NZ bank.", bank)
+            }
+            AccountError::UnknownBranch(bank, branch) => write!(
+                f,
+                "Bank `{:02}` doesn't have a branch `{:04}`.",
+                bank, branch
+            ),
+            AccountError::Checksum => write!(
+                f,
+                "That account number doesn't exist, please check it for typos."
+            ),
+        }
+    }
+}
+
+/// A valid NZ bank account number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountNumber {
+    bank: u8,
+    branch: u16,
+    account: u32,
+    suffix: u16,
+    /// how many digits the suffix was written with, from 2 to 4
+    suffix_width: usize,
+}
+
+impl AccountNumber {
+    /// the check digit algorithm used for an account, which depends on the bank and account
+    fn algorithm(&self) -> Option<&'static Algorithm> {
+        match self.bank {
+            8 => Some(&ALGORITHM_D),
+            9 => Some(&ALGORITHM_E),
+            25 | 33 => Some(&ALGORITHM_F),
+            26 | 28 | 29 => Some(&ALGORITHM_G),
+            // Citibank accounts don't have a check digit
+            31 => None,
+            _ if self.account < 990000 => Some(&ALGORITHM_A),
+            _ => Some(&ALGORITHM_B),
+        }
+    }
+
+    /// the 18 digits the check digit algorithms are applied to
+    fn digits(&self) -> [u32; 18] {
+        let mut digits = [0; 18];
+        let padded = format!(
+            "{:02}{:04}{:08}{:04}",
+            self.bank, self.branch, self.account, self.suffix
+        );
+        for (digit, c) in digits.iter_mut().zip(padded.chars()) {
+            *digit = c.to_digit(10).unwrap_or_default();
+        }
+        digits
+    }
+
+    fn validate(&self) -> Result<(), AccountError> {
+        let branches = BRANCHES
+            .iter()
+            .find(|(bank, _)| *bank == self.bank)
+            .map(|(_, branches)| *branches)
+            .ok_or(AccountError::UnknownBank(self.bank))?;
+        if !branches
+            .iter()
+            .any(|(start, end)| (*start..=*end).contains(&self.branch))
+        {
+            return Err(AccountError::UnknownBranch(self.bank, self.branch));
+        }
+
+        let algorithm = match self.algorithm() {
+            Some(algorithm) => algorithm,
+            None => return Ok(()),
+        };
+
+        let sum: u32 = self
+            .digits()
+            .iter()
+            .zip(algorithm.weights.iter())
+            .map(|(digit, weight)| {
+                let mut product = digit * weight;
+                while algorithm.sum_digits && product > 9 {
+                    product = product / 10 + product % 10;
+                }
+                product
+            })
+            .sum();
+
+        match sum % algorithm.modulus {
+            0 => Ok(()),
+            _ => Err(AccountError::Checksum),
+        }
+    }
+}
+
+impl FromStr for AccountNumber {
+    type Err = AccountError;
+
+    /// Read an account number written with dashes or spaces between each part, e.g.
+    /// `12-3456-7890123-00` or `12 3456 7890123 000`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s
+            .split(|c: char| c == '-' || c.is_whitespace())
+            .filter(|p| !p.is_empty())
+            .collect();
+
+        let (bank, branch, account, suffix) = match parts.as_slice() {
+            [bank, branch, account, suffix] => (*bank, *branch, *account, *suffix),
+            _ => return Err(AccountError::Format),
+        };
+        if !parts.iter().all(|p| p.chars().all(|c| c.is_ascii_digit()))
+            || bank.len() != 2
+            || !(3..=4).contains(&branch.len())
+            || !(7..=8).contains(&account.len())
+            || !(2..=4).contains(&suffix.len())
+        {
+            return Err(AccountError::Format);
+        }
+
+        let number = AccountNumber {
+            bank: bank.parse().map_err(|_| AccountError::Format)?,
+            branch: branch.parse().map_err(|_| AccountError::Format)?,
+            account: account.parse().map_err(|_| AccountError::Format)?,
+            suffix: suffix.parse().map_err(|_| AccountError::Format)?,
+            suffix_width: suffix.len(),
+        };
+        number.validate()?;
+        Ok(number)
+    }
+}
+
+impl Display for AccountNumber {
+    /// write the account number in the usual `12-3456-7890123-00` form
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02}-{:04}-{:07}-{:0width$}",
+            self.bank,
+            self.branch,
+            self.account,
+            self.suffix,
+            width = self.suffix_width
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(number: &str) -> Result<AccountNumber, AccountError> {
+        number.parse()
+    }
+
+    #[test]
+    fn algorithm_a() {
+        assert!(check("01-0902-0068389-00").is_ok());
+        assert_eq!(check("01-0902-0068388-00"), Err(AccountError::Checksum));
+    }
+
+    #[test]
+    fn algorithm_b() {
+        // base numbers from 00990000 up use algorithm B, where the first weighted digit counts ten
+        assert!(check("01-0902-0991000-00").is_ok());
+        assert_eq!(check("01-0902-0991001-00"), Err(AccountError::Checksum));
+    }
+
+    #[test]
+    fn algorithm_d() {
+        assert!(check("08-6523-1954512-001").is_ok());
+        assert_eq!(check("08-6523-1954513-001"), Err(AccountError::Checksum));
+    }
+
+    #[test]
+    fn algorithm_e() {
+        assert!(check("09-0000-0001000-0006").is_ok());
+        assert_eq!(check("09-0000-0001000-0005"), Err(AccountError::Checksum));
+    }
+
+    #[test]
+    fn algorithm_f() {
+        assert!(check("25-2500-0000103-00").is_ok());
+        assert_eq!(check("25-2500-0000104-00"), Err(AccountError::Checksum));
+    }
+
+    #[test]
+    fn algorithm_g() {
+        assert!(check("26-2600-0320871-032").is_ok());
+        assert_eq!(check("26-2600-0320872-032"), Err(AccountError::Checksum));
+    }
+
+    #[test]
+    fn hsbc() {
+        assert!(check("35-2401-0123457-00").is_ok());
+        assert_eq!(check("35-2401-0123458-00"), Err(AccountError::Checksum));
+        assert_eq!(
+            check("35-2500-0123457-00"),
+            Err(AccountError::UnknownBranch(35, 2500))
+        );
+    }
+
+    #[test]
+    fn citibank_has_no_check_digit() {
+        assert!(check("31-2800-0000001-00").is_ok());
+    }
+
+    #[test]
+    fn bank_and_branch() {
+        assert_eq!(
+            check("07-0001-0068389-00"),
+            Err(AccountError::UnknownBank(7))
+        );
+        assert_eq!(
+            check("01-2000-0068389-00"),
+            Err(AccountError::UnknownBranch(1, 2000))
+        );
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(check("01-0902-0068389"), Err(AccountError::Format));
+        assert_eq!(check("01-0902-006838a-00"), Err(AccountError::Format));
+        assert_eq!(
+            check("01 902 0068389 00").map(|n| n.to_string()),
+            Ok(String::from("01-0902-0068389-00"))
+        );
+    }
+
+    #[test]
+    fn suffix_keeps_its_width() {
+        for number in [
+            "01-0902-0068389-00",
+            "08-6523-1954512-001",
+            "09-0000-0001000-0006",
+        ] {
+            assert_eq!(check(number).map(|n| n.to_string()), Ok(number.to_string()));
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! Monthly budgets for categories of bills, so the flat notices a category creeping up before the
+//! bill that takes it over budget arrives.
+
+use chrono::{Datelike, NaiveDate};
+use entity::payment::Model as PaymentModel;
+
+use super::money::Money;
+
+/// the percentages of a budget which are warned about when spending goes past them
+pub const THRESHOLDS: [i64; 2] = [80, 100];
+
+/// whether a bill's purpose belongs to a category known by any of the given names
+pub fn in_category(purpose: &str, names: &[&str]) -> bool {
+    let purpose = purpose.trim().to_lowercase();
+    names
+        .iter()
+        .any(|name| name.trim().to_lowercase() == purpose)
+}
+
+/// The total of the bills in a category posted in the same month as `date`, leaving out voided bills
+pub fn spent(payments: &[PaymentModel], names: &[&str], date: NaiveDate) -> Money {
+    payments
+        .iter()
+        .filter(|payment| payment.voided_at.is_none())
+        .filter(|payment| {
+            let created = payment.created_at.date();
+            created.year() == date.year() && created.month() == date.month()
+        })
+        .filter(|payment| in_category(&payment.purpose, names))
+        .map(|payment| Money::from_cents(payment.total_amount))
+        .sum()
+}
+
+/// How much of a budget has been spent, as a whole percentage
+pub fn percent_used(budget: Money, spent: Money) -> i64 {
+    match budget.cents() {
+        0 => 100,
+        budget => spent.cents() * 100 / budget,
+    }
+}
+
+/// The highest threshold, as a percentage of the budget, which spending went past on going from
+/// `before` to `after`, if it went past one
+pub fn crossed(budget: Money, before: Money, after: Money) -> Option<i64> {
+    let reached = |spent: Money, threshold: i64| spent.cents() * 100 >= budget.cents() * threshold;
+
+    THRESHOLDS
+        .iter()
+        .rev()
+        .find(|threshold| reached(after, **threshold) && !reached(before, **threshold))
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, month, day).unwrap()
+    }
+
+    fn bill(purpose: &str, cents: i64, created_at: NaiveDateTime) -> PaymentModel {
+        PaymentModel {
+            id: 0,
+            total_amount: cents,
+            created_by: 1,
+            created_at,
+            originating_message_id: 0,
+            individual_users: vec![1],
+            individual_amounts: vec![cents],
+            image_path: String::new(),
+            purpose: purpose.to_string(),
+            account: String::new(),
+            receipt_url: String::new(),
+            channel_id: 0,
+            guild_id: None,
+            paid_users: vec![],
+            paid_at: vec![],
+            reminded_users: vec![],
+            reminded_at: vec![],
+            voided_at: None,
+            share_references: vec![],
+            period_start: None,
+            period_end: None,
+            disputed_users: vec![],
+            dispute_reasons: vec![],
+            dispute_outcomes: vec![],
+        }
+    }
+
+    #[test]
+    fn category_names_ignore_case_and_spaces() {
+        assert!(in_category(" Power ", &["power"]));
+        assert!(in_category("POWER", &["electricity", "Power"]));
+        assert!(!in_category("power bill", &["power"]));
+    }
+
+    #[test]
+    fn spent_in_the_month() {
+        let noon = |month, day| date(month, day).and_hms_opt(12, 0, 0).unwrap();
+        let mut voided = bill("power", 5000, noon(6, 10));
+        voided.voided_at = Some(noon(6, 11));
+        let payments = [
+            bill("Power", 12000, noon(6, 1)),
+            bill("power ", 3000, noon(6, 30)),
+            bill("power", 7000, noon(7, 1)),
+            bill("power", 7000, noon(5, 31)),
+            bill("water", 4000, noon(6, 15)),
+            voided,
+        ];
+
+        assert_eq!(
+            spent(&payments, &["power"], date(6, 15)),
+            Money::from_cents(15000)
+        );
+        assert_eq!(
+            spent(&payments, &["water", "power"], date(6, 15)),
+            Money::from_cents(19000)
+        );
+    }
+
+    #[test]
+    fn percent_of_budget() {
+        let budget = Money::from_cents(25000);
+        assert_eq!(percent_used(budget, Money::from_cents(20000)), 80);
+        assert_eq!(percent_used(budget, Money::from_cents(24999)), 99);
+        assert_eq!(percent_used(budget, Money::from_cents(30000)), 120);
+        assert_eq!(percent_used(Money::ZERO, Money::from_cents(100)), 100);
+    }
+
+    #[test]
+    fn thresholds_crossed() {
+        let budget = Money::from_cents(25000);
+        let crossed =
+            |before, after| crossed(budget, Money::from_cents(before), Money::from_cents(after));
+
+        assert_eq!(crossed(10000, 19999), None);
+        assert_eq!(crossed(10000, 20000), Some(80));
+        assert_eq!(crossed(19000, 25000), Some(100));
+        assert_eq!(crossed(0, 30000), Some(100));
+        assert_eq!(crossed(21000, 24000), None);
+        assert_eq!(crossed(26000, 27000), None);
+    }
+}
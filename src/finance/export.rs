@@ -0,0 +1,551 @@
+//! Exports the history of every bill and settle-up transfer as a file, for flatmates doing their
+//! budgets or keeping a record when they leave the flat.
+
+use std::io::{self, Cursor, Write};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use entity::{payment::Model as PaymentModel, settlement::Model as SettlementModel};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use super::money::Money;
+
+/// the column names, in the order they are written
+const HEADERS: [&str; 9] = [
+    "Date",
+    "Bill",
+    "Event",
+    "Purpose",
+    "Flatmate",
+    "Amount",
+    "Account",
+    "Reference",
+    "Note",
+];
+
+/// The file formats the history can be exported as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    /// an OpenDocument spreadsheet, which opens in Excel, LibreOffice and Google Sheets
+    Ods,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            "ods" => Some(ExportFormat::Ods),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ods => "ods",
+        }
+    }
+}
+
+/// A single event in the history of a bill, or a transfer made to settle up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportRow {
+    pub date: NaiveDateTime,
+    /// the bill the event is for, `None` for a settle-up transfer
+    pub bill: Option<i32>,
+    /// `bill` when it was posted, `share` for each flatmate's part of it, `paid` when a share was
+    /// paid, `disputed`, `dispute accepted` or `dispute rejected` for a dispute of a share,
+    /// `voided` if the bill was voided, and `settled` for a settle-up transfer
+    pub event: &'static str,
+    pub purpose: String,
+    pub flatmate: String,
+    pub amount: Money,
+    pub account: String,
+    pub reference: String,
+    /// the reason for a dispute, or who a transfer was made to
+    pub note: String,
+}
+
+impl ExportRow {
+    /// the columns of the row as text, in the same order as the headers
+    fn fields(&self) -> [String; 9] {
+        [
+            self.date.format("%Y-%m-%d %H:%M:%S").to_string(),
+            self.bill.map(|bill| bill.to_string()).unwrap_or_default(),
+            self.event.to_string(),
+            self.purpose.clone(),
+            self.flatmate.clone(),
+            self.amount.to_decimal(),
+            self.account.clone(),
+            self.reference.clone(),
+            self.note.clone(),
+        ]
+    }
+}
+
+/// Every event in the history of the given bills, along with every transfer made to settle up,
+/// between two dates, inclusive, oldest first. `name_of` gives the name to show for a flatmate's
+/// discord id.
+///
+/// Shares which are paid off by settling up are marked `paid` once the plan is complete, so the
+/// transfers only count towards balances until then, as noted on each of them.
+pub fn rows(
+    payments: &[PaymentModel],
+    settlements: &[SettlementModel],
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    name_of: impl Fn(u64) -> String,
+) -> Vec<ExportRow> {
+    let mut rows = Vec::new();
+
+    for payment in payments {
+        let row =
+            |date: NaiveDateTime, event, user: u64, amount, reference: Option<&String>| ExportRow {
+                date,
+                bill: Some(payment.id),
+                event,
+                purpose: payment.purpose.clone(),
+                flatmate: name_of(user),
+                amount,
+                account: payment.account.clone(),
+                reference: reference.cloned().unwrap_or_default(),
+                note: String::new(),
+            };
+
+        let total = Money::from_cents(payment.total_amount);
+        let created_by = payment.created_by as u64;
+        rows.push(row(payment.created_at, "bill", created_by, total, None));
+
+        for (i, (user, amount)) in payment
+            .individual_users
+            .iter()
+            .zip(payment.individual_amounts.iter())
+            .enumerate()
+        {
+            let (user, amount) = (*user as u64, Money::from_cents(*amount));
+            let reference = payment.share_references.get(i);
+            rows.push(row(payment.created_at, "share", user, amount, reference));
+
+            if let Some(paid_at) = payment
+                .paid_users
+                .iter()
+                .position(|paid| *paid as u64 == user)
+                .and_then(|i| payment.paid_at.get(i))
+            {
+                rows.push(row(*paid_at, "paid", user, amount, reference));
+            }
+        }
+
+        // disputes aren't dated, so they're listed with the bill. An accepted dispute has already
+        // changed the share, so the amount is always what the share is now
+        for ((user, reason), outcome) in payment
+            .disputed_users
+            .iter()
+            .zip(payment.dispute_reasons.iter())
+            .zip(payment.dispute_outcomes.iter())
+        {
+            let event = match outcome.as_str() {
+                "accepted" => "dispute accepted",
+                "rejected" => "dispute rejected",
+                _ => "disputed",
+            };
+            let i = payment.individual_users.iter().position(|u| u == user);
+            let amount = i
+                .and_then(|i| payment.individual_amounts.get(i))
+                .map(|amount| Money::from_cents(*amount))
+                .unwrap_or(Money::ZERO);
+            let reference = i.and_then(|i| payment.share_references.get(i));
+            rows.push(ExportRow {
+                note: reason.clone(),
+                ..row(payment.created_at, event, *user as u64, amount, reference)
+            });
+        }
+
+        if let Some(voided_at) = payment.voided_at {
+            rows.push(row(voided_at, "voided", created_by, total, None));
+        }
+    }
+
+    // transfers which were planned but never made didn't change anything
+    for settlement in settlements {
+        let settled_at = match settlement.settled_at {
+            Some(settled_at) => settled_at,
+            None => continue,
+        };
+
+        let mut note = format!("to {}", name_of(settlement.creditor as u64));
+        if settlement.applied {
+            note.push_str(", since applied to the bills it paid off");
+        }
+
+        rows.push(ExportRow {
+            date: settled_at,
+            bill: None,
+            event: "settled",
+            purpose: String::from("settling up"),
+            flatmate: name_of(settlement.debtor as u64),
+            amount: Money::from_cents(settlement.amount),
+            account: String::new(),
+            reference: String::new(),
+            note,
+        });
+    }
+
+    rows.retain(|row| {
+        let date = row.date.date();
+        from.iter().all(|from| date >= *from) && to.iter().all(|to| date <= *to)
+    });
+    rows.sort_by_key(|row| row.date);
+    rows
+}
+
+fn to_csv(rows: &[ExportRow]) -> io::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(HEADERS)?;
+    for row in rows {
+        writer.write_record(row.fields())?;
+    }
+
+    writer.into_inner().map_err(|e| e.into_error())
+}
+
+fn to_json(rows: &[ExportRow]) -> io::Result<Vec<u8>> {
+    let rows: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            serde_json::json!({
+                "date": row.date.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                "bill": row.bill,
+                "event": row.event,
+                "purpose": row.purpose,
+                "flatmate": row.flatmate,
+                // as text, so the amount is exactly what was recorded
+                "amount": row.amount.to_decimal(),
+                "account": row.account,
+                "reference": row.reference,
+                "note": row.note,
+            })
+        })
+        .collect();
+
+    Ok(serde_json::to_vec_pretty(&rows)?)
+}
+
+/// escape text to be put inside an XML element or attribute
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// a spreadsheet cell holding text
+fn text_cell(text: &str) -> String {
+    format!(
+        "<table:table-cell office:value-type=\"string\"><text:p>{}</text:p></table:table-cell>",
+        escape_xml(text)
+    )
+}
+
+/// the spreadsheet's single sheet, with dates and amounts stored as values so they can be summed
+fn ods_content(rows: &[ExportRow]) -> String {
+    let mut content = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>",
+        "<office:document-content",
+        " xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\"",
+        " xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\"",
+        " xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\"",
+        " office:version=\"1.2\">",
+        "<office:body><office:spreadsheet><table:table table:name=\"Payments\">",
+        "<table:table-row>",
+    ));
+    for header in HEADERS {
+        content.push_str(&text_cell(header));
+    }
+    content.push_str("</table:table-row>");
+
+    for row in rows {
+        content.push_str("<table:table-row>");
+        content.push_str(&format!(
+            "<table:table-cell office:value-type=\"date\" office:date-value=\"{}\"><text:p>{}</text:p></table:table-cell>",
+            row.date.format("%Y-%m-%dT%H:%M:%S"),
+            row.date.format("%Y-%m-%d %H:%M")
+        ));
+        match row.bill {
+            Some(bill) => content.push_str(&format!(
+                "<table:table-cell office:value-type=\"float\" office:value=\"{0}\"><text:p>{0}</text:p></table:table-cell>",
+                bill
+            )),
+            None => content.push_str(&text_cell("")),
+        }
+        content.push_str(&text_cell(row.event));
+        content.push_str(&text_cell(&row.purpose));
+        content.push_str(&text_cell(&row.flatmate));
+        content.push_str(&format!(
+            "<table:table-cell office:value-type=\"currency\" office:currency=\"NZD\" office:value=\"{0}\"><text:p>{0}</text:p></table:table-cell>",
+            row.amount.to_decimal()
+        ));
+        content.push_str(&text_cell(&row.account));
+        content.push_str(&text_cell(&row.reference));
+        content.push_str(&text_cell(&row.note));
+        content.push_str("</table:table-row>");
+    }
+
+    content.push_str("</table:table></office:spreadsheet></office:body></office:document-content>");
+    content
+}
+
+fn to_ods(rows: &[ExportRow]) -> io::Result<Vec<u8>> {
+    const MANIFEST: &str = concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>",
+        "<manifest:manifest xmlns:manifest=\"urn:oasis:names:tc:opendocument:xmlns:manifest:1.0\" manifest:version=\"1.2\">",
+        "<manifest:file-entry manifest:full-path=\"/\" manifest:media-type=\"application/vnd.oasis.opendocument.spreadsheet\"/>",
+        "<manifest:file-entry manifest:full-path=\"content.xml\" manifest:media-type=\"text/xml\"/>",
+        "</manifest:manifest>",
+    );
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+    // the mimetype must come first and be left uncompressed, so the format can be recognised
+    zip.start_file(
+        "mimetype",
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/vnd.oasis.opendocument.spreadsheet")?;
+
+    zip.start_file("META-INF/manifest.xml", FileOptions::default())?;
+    zip.write_all(MANIFEST.as_bytes())?;
+
+    zip.start_file("content.xml", FileOptions::default())?;
+    zip.write_all(ods_content(rows).as_bytes())?;
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Write the rows out in the given format
+pub fn write(format: ExportFormat, rows: &[ExportRow]) -> io::Result<Vec<u8>> {
+    match format {
+        ExportFormat::Csv => to_csv(rows),
+        ExportFormat::Json => to_json(rows),
+        ExportFormat::Ods => to_ods(rows),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, month, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn name_of(user: u64) -> String {
+        match user {
+            1 => "John",
+            2 => "Sam",
+            3 => "Alex",
+            _ => "someone",
+        }
+        .to_string()
+    }
+
+    /// a power bill split three ways, which Sam has paid and Alex has disputed
+    fn power_bill() -> PaymentModel {
+        PaymentModel {
+            id: 7,
+            total_amount: 9000,
+            created_by: 1,
+            created_at: at(6, 1, 10),
+            originating_message_id: 0,
+            individual_users: vec![1, 2, 3],
+            individual_amounts: vec![3000, 3000, 3000],
+            image_path: String::new(),
+            purpose: String::from("Power"),
+            account: String::from("38-9000-7654338-00"),
+            receipt_url: String::new(),
+            channel_id: 0,
+            guild_id: None,
+            paid_users: vec![2],
+            paid_at: vec![at(6, 3, 9)],
+            reminded_users: vec![],
+            reminded_at: vec![],
+            voided_at: None,
+            share_references: vec![
+                String::from("B7-1"),
+                String::from("B7-2"),
+                String::from("B7-3"),
+            ],
+            period_start: None,
+            period_end: None,
+            disputed_users: vec![3],
+            dispute_reasons: vec![String::from("I was away")],
+            dispute_outcomes: vec![String::from("rejected")],
+        }
+    }
+
+    fn transfer(settled_at: Option<NaiveDateTime>, applied: bool) -> SettlementModel {
+        SettlementModel {
+            id: 1,
+            guild_id: None,
+            channel_id: 0,
+            plan_message_id: 0,
+            created_by: 1,
+            created_at: at(6, 4, 12),
+            debtor: 3,
+            creditor: 1,
+            amount: 3000,
+            payment_ids: vec![7],
+            settled_at,
+            applied,
+        }
+    }
+
+    fn summary(rows: &[ExportRow]) -> Vec<(Option<i32>, &str, &str, String)> {
+        rows.iter()
+            .map(|row| {
+                (
+                    row.bill,
+                    row.event,
+                    row.flatmate.as_str(),
+                    row.amount.to_decimal(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn history_of_a_bill_and_its_transfers() {
+        let settlements = [transfer(Some(at(6, 5, 18)), true), transfer(None, false)];
+        let rows = rows(&[power_bill()], &settlements, None, None, name_of);
+
+        assert_eq!(
+            summary(&rows),
+            [
+                (Some(7), "bill", "John", String::from("90.00")),
+                (Some(7), "share", "John", String::from("30.00")),
+                (Some(7), "share", "Sam", String::from("30.00")),
+                (Some(7), "share", "Alex", String::from("30.00")),
+                (Some(7), "dispute rejected", "Alex", String::from("30.00")),
+                (Some(7), "paid", "Sam", String::from("30.00")),
+                (None, "settled", "Alex", String::from("30.00")),
+            ]
+        );
+        assert_eq!(rows[2].reference, "B7-2");
+        assert_eq!(rows[4].note, "I was away");
+        assert_eq!(rows[5].date, at(6, 3, 9));
+        assert_eq!(
+            rows[6].note,
+            "to John, since applied to the bills it paid off"
+        );
+    }
+
+    #[test]
+    fn accepted_dispute_and_void() {
+        let mut bill = power_bill();
+        bill.dispute_outcomes = vec![String::new()];
+        let open = rows(&[bill.clone()], &[], None, None, name_of);
+        assert!(open.iter().any(|row| row.event == "disputed"));
+
+        bill.individual_amounts = vec![4500, 4500, 0];
+        bill.dispute_outcomes = vec![String::from("accepted")];
+        bill.voided_at = Some(at(6, 20, 8));
+        let rows = rows(&[bill], &[], None, None, name_of);
+
+        let accepted = rows
+            .iter()
+            .find(|row| row.event == "dispute accepted")
+            .unwrap();
+        assert_eq!(accepted.amount, Money::ZERO);
+        assert_eq!(accepted.reference, "B7-3");
+        let voided = rows.last().unwrap();
+        assert_eq!(voided.event, "voided");
+        assert_eq!(voided.amount, Money::from_cents(9000));
+    }
+
+    #[test]
+    fn between_dates() {
+        let mut bill = power_bill();
+        bill.voided_at = Some(at(6, 20, 8));
+        let settlements = [transfer(Some(at(6, 5, 18)), false)];
+        let rows = rows(
+            &[bill],
+            &settlements,
+            NaiveDate::from_ymd_opt(2023, 6, 3),
+            NaiveDate::from_ymd_opt(2023, 6, 5),
+            name_of,
+        );
+
+        assert_eq!(
+            summary(&rows),
+            [
+                (Some(7), "paid", "Sam", String::from("30.00")),
+                (None, "settled", "Alex", String::from("30.00")),
+            ]
+        );
+        assert_eq!(rows[1].note, "to John");
+    }
+
+    #[test]
+    fn csv() {
+        let rows = rows(
+            &[],
+            &[transfer(Some(at(6, 5, 18)), false)],
+            None,
+            None,
+            name_of,
+        );
+        let csv = String::from_utf8(write(ExportFormat::Csv, &rows).unwrap()).unwrap();
+
+        assert_eq!(
+            csv,
+            concat!(
+                "Date,Bill,Event,Purpose,Flatmate,Amount,Account,Reference,Note\n",
+                "2023-06-05 18:00:00,,settled,settling up,Alex,30.00,,,to John\n",
+            )
+        );
+    }
+
+    #[test]
+    fn json_amounts_are_exact() {
+        let rows = rows(&[power_bill()], &[], None, None, name_of);
+        let json: serde_json::Value =
+            serde_json::from_slice(&write(ExportFormat::Json, &rows).unwrap()).unwrap();
+
+        assert_eq!(json[0]["date"], "2023-06-01T10:00:00");
+        assert_eq!(json[0]["bill"], 7);
+        assert_eq!(json[0]["amount"], "90.00");
+        assert_eq!(json[2]["reference"], "B7-2");
+    }
+
+    #[test]
+    fn ods_starts_with_its_mimetype() {
+        let ods = write(ExportFormat::Ods, &[]).unwrap();
+
+        // the name of the first file in the zip, followed by its uncompressed contents
+        assert_eq!(&ods[..2], b"PK");
+        assert_eq!(&ods[30..38], b"mimetype");
+        assert_eq!(
+            &ods[38..84],
+            b"application/vnd.oasis.opendocument.spreadsheet"
+        );
+    }
+
+    #[test]
+    fn ods_escapes_text() {
+        let mut bill = power_bill();
+        bill.purpose = String::from("Fish & <chips>");
+        let content = ods_content(&rows(&[bill], &[], None, None, name_of));
+
+        assert!(content.contains("<text:p>Fish &amp; &lt;chips&gt;</text:p>"));
+        assert!(content.contains(
+            "office:value-type=\"currency\" office:currency=\"NZD\" office:value=\"90.00\""
+        ));
+    }
+}
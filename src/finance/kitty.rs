@@ -0,0 +1,97 @@
+//! The flat's kitty, a shared fund which flatmates top up and pay small shared purchases such as
+//! cleaning supplies out of, rather than posting a bill for each of them.
+
+use entity::kitty_entry::Model as KittyEntryModel;
+
+use super::money::Money;
+
+/// How much is left in the kitty after all of its top ups and purchases
+pub fn balance(entries: &[KittyEntryModel]) -> Money {
+    entries
+        .iter()
+        .map(|entry| Money::from_cents(entry.amount))
+        .sum()
+}
+
+/// The amount each of `flatmates` would need to put in to bring the kitty from `balance` back up
+/// to `target`, rounded up to the cent so the kitty isn't left short. `None` if the kitty is already
+/// at its target, or there is nobody to top it up.
+pub fn even_topup(balance: Money, target: Money, flatmates: usize) -> Option<Money> {
+    let shortfall = (target - balance).cents();
+    if shortfall <= 0 || flatmates == 0 {
+        return None;
+    }
+
+    let flatmates = flatmates as i64;
+    Some(Money::from_cents((shortfall + flatmates - 1) / flatmates))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn entry(amount: i64) -> KittyEntryModel {
+        KittyEntryModel {
+            id: 0,
+            guild_id: None,
+            channel_id: 0,
+            user_id: 1,
+            amount,
+            description: String::new(),
+            receipt_url: String::new(),
+            image_path: String::new(),
+            created_at: NaiveDate::from_ymd_opt(2023, 6, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn balance_of_topups_and_purchases() {
+        assert_eq!(balance(&[]), Money::ZERO);
+        assert_eq!(
+            balance(&[entry(5000), entry(5000), entry(-1250), entry(-2250)]),
+            Money::from_cents(6500)
+        );
+        assert_eq!(
+            balance(&[entry(1000), entry(-1500)]),
+            Money::from_cents(-500)
+        );
+    }
+
+    #[test]
+    fn topup_rounds_up() {
+        let target = Money::from_cents(10000);
+        assert_eq!(
+            even_topup(Money::from_cents(1500), target, 3),
+            Some(Money::from_cents(2834))
+        );
+        assert_eq!(
+            even_topup(Money::ZERO, target, 3),
+            Some(Money::from_cents(3334))
+        );
+        assert_eq!(
+            even_topup(Money::from_cents(2000), target, 4),
+            Some(Money::from_cents(2000))
+        );
+    }
+
+    #[test]
+    fn topup_covers_an_overdrawn_kitty() {
+        assert_eq!(
+            even_topup(Money::from_cents(-500), Money::from_cents(10000), 4),
+            Some(Money::from_cents(2625))
+        );
+    }
+
+    #[test]
+    fn no_topup_needed_or_possible() {
+        let target = Money::from_cents(10000);
+        assert_eq!(even_topup(target, target, 3), None);
+        assert_eq!(even_topup(Money::from_cents(12000), target, 3), None);
+        assert_eq!(even_topup(Money::from_cents(1500), target, 0), None);
+    }
+}
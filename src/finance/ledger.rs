@@ -0,0 +1,251 @@
+//! A ledger of the outstanding debts between flatmates, built up from the unpaid shares of every bill.
+
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::NaiveDateTime;
+use entity::{payment::Model as PaymentModel, settlement::Model as SettlementModel};
+
+use super::money::Money;
+
+/// An amount owed by one flatmate to another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Debt {
+    /// the discord id of the flatmate who owes money
+    pub debtor: u64,
+    /// the discord id of the flatmate who is owed money
+    pub creditor: u64,
+    /// the amount owed
+    pub amount: Money,
+    /// when the oldest unpaid bill making up this debt was created
+    pub since: NaiveDateTime,
+}
+
+/// The net position of every flatmate, with debts in opposite directions cancelled out
+#[derive(Debug, Default)]
+pub struct Ledger {
+    debts: Vec<Debt>,
+}
+
+impl Ledger {
+    /// Build a ledger from a set of bills. The creator of each bill is treated as having paid it,
+    /// so every other flatmate with an unpaid share owes the creator that amount.
+    ///
+    /// Transfers made as part of a settle-up plan are taken off what is owed until the plan is
+    /// complete and has been applied to the bills it covers.
+    pub fn new(payments: &[PaymentModel], settlements: &[SettlementModel]) -> Self {
+        let mut totals: HashMap<(u64, u64), (Money, NaiveDateTime)> = HashMap::new();
+
+        for payment in payments {
+            let creditor = payment.created_by as u64;
+
+            for (user, amount) in payment
+                .individual_users
+                .iter()
+                .zip(payment.individual_amounts.iter())
+            {
+                let debtor = *user as u64;
+                if debtor == creditor || *amount == 0 || payment.paid_users.contains(user) {
+                    continue;
+                }
+
+                let entry = totals
+                    .entry((debtor, creditor))
+                    .or_insert((Money::ZERO, payment.created_at));
+                entry.0 += Money::from_cents(*amount);
+                entry.1 = entry.1.min(payment.created_at);
+            }
+        }
+
+        for settlement in settlements {
+            let settled_at = match settlement.settled_at {
+                Some(settled_at) if !settlement.applied => settled_at,
+                _ => continue,
+            };
+
+            // paying someone back is the same as them now owing you that amount
+            let entry = totals
+                .entry((settlement.creditor as u64, settlement.debtor as u64))
+                .or_insert((Money::ZERO, settled_at));
+            entry.0 += Money::from_cents(settlement.amount);
+            entry.1 = entry.1.min(settled_at);
+        }
+
+        Self::from_totals(totals)
+    }
+
+    /// Cancel out debts between each pair of flatmates, leaving at most one debt per pair
+    fn from_totals(totals: HashMap<(u64, u64), (Money, NaiveDateTime)>) -> Self {
+        let mut debts = Vec::new();
+
+        for (&(debtor, creditor), &(amount, since)) in totals.iter() {
+            let reverse = totals
+                .get(&(creditor, debtor))
+                .map(|(amount, _)| *amount)
+                .unwrap_or(Money::ZERO);
+
+            // each pair is visited twice, so only keep the direction that remains owing
+            if amount > reverse {
+                debts.push(Debt {
+                    debtor,
+                    creditor,
+                    amount: amount - reverse,
+                    since,
+                });
+            }
+        }
+
+        debts.sort_by_key(|d| d.since);
+        Ledger { debts }
+    }
+
+    /// Every outstanding debt, oldest first
+    pub fn debts(&self) -> &[Debt] {
+        &self.debts
+    }
+
+    /// Debts which the given flatmate has to pay
+    pub fn owed_by(&self, user: u64) -> impl Iterator<Item = &Debt> {
+        self.debts.iter().filter(move |d| d.debtor == user)
+    }
+
+    /// Debts which are owed to the given flatmate
+    pub fn owed_to(&self, user: u64) -> impl Iterator<Item = &Debt> {
+        self.debts.iter().filter(move |d| d.creditor == user)
+    }
+
+    /// The overall position of a flatmate. Positive if they are owed money, negative if they owe.
+    pub fn net(&self, user: u64) -> Money {
+        self.owed_to(user).map(|d| d.amount).sum::<Money>()
+            - self.owed_by(user).map(|d| d.amount).sum::<Money>()
+    }
+
+    /// The net position of every flatmate who appears in the ledger
+    pub fn balances(&self) -> Vec<(u64, Money)> {
+        self.users()
+            .into_iter()
+            .map(|user| (user, self.net(user)))
+            .collect()
+    }
+
+    /// Every flatmate who appears in the ledger
+    pub fn users(&self) -> BTreeSet<u64> {
+        self.debts
+            .iter()
+            .flat_map(|d| [d.debtor, d.creditor])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate};
+
+    use super::*;
+
+    fn time(day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 6, day)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    fn bill(created_by: i64, day: u32, shares: &[(i64, i64)], paid: &[i64]) -> PaymentModel {
+        PaymentModel {
+            id: 0,
+            total_amount: shares.iter().map(|(_, amount)| amount).sum(),
+            created_by,
+            created_at: time(day),
+            originating_message_id: 0,
+            individual_users: shares.iter().map(|(user, _)| *user).collect(),
+            individual_amounts: shares.iter().map(|(_, amount)| *amount).collect(),
+            image_path: String::new(),
+            purpose: String::from("Power"),
+            account: String::new(),
+            receipt_url: String::new(),
+            channel_id: 0,
+            guild_id: None,
+            paid_users: paid.to_vec(),
+            paid_at: paid.iter().map(|_| time(day) + Duration::days(1)).collect(),
+            reminded_users: vec![],
+            reminded_at: vec![],
+            voided_at: None,
+            share_references: vec![],
+            period_start: None,
+            period_end: None,
+            disputed_users: vec![],
+            dispute_reasons: vec![],
+            dispute_outcomes: vec![],
+        }
+    }
+
+    fn settlement(debtor: i64, creditor: i64, amount: i64, applied: bool) -> SettlementModel {
+        SettlementModel {
+            id: 0,
+            guild_id: None,
+            channel_id: 0,
+            plan_message_id: 0,
+            created_by: debtor,
+            created_at: time(20),
+            debtor,
+            creditor,
+            amount,
+            payment_ids: vec![],
+            settled_at: Some(time(20)),
+            applied,
+        }
+    }
+
+    #[test]
+    fn unpaid_shares_are_owed_to_the_creator() {
+        let ledger = Ledger::new(
+            &[
+                bill(1, 5, &[(1, 1000), (2, 1000), (3, 1000)], &[3]),
+                bill(1, 2, &[(2, 500)], &[]),
+            ],
+            &[],
+        );
+
+        assert_eq!(
+            ledger.debts(),
+            &[Debt {
+                debtor: 2,
+                creditor: 1,
+                amount: Money::from_cents(1500),
+                since: time(2),
+            }]
+        );
+        assert_eq!(ledger.net(1), Money::from_cents(1500));
+        assert_eq!(ledger.net(2), Money::from_cents(-1500));
+        assert_eq!(ledger.net(3), Money::ZERO);
+    }
+
+    #[test]
+    fn debts_in_opposite_directions_cancel() {
+        let ledger = Ledger::new(
+            &[bill(1, 1, &[(2, 1000)], &[]), bill(2, 3, &[(1, 400)], &[])],
+            &[],
+        );
+
+        assert_eq!(ledger.debts().len(), 1);
+        assert_eq!(ledger.debts()[0].debtor, 2);
+        assert_eq!(ledger.debts()[0].amount, Money::from_cents(600));
+        assert_eq!(
+            ledger.balances(),
+            vec![(1, Money::from_cents(600)), (2, Money::from_cents(-600))]
+        );
+    }
+
+    #[test]
+    fn only_unapplied_settlements_count() {
+        let bills = [bill(1, 1, &[(2, 1000)], &[])];
+
+        let ledger = Ledger::new(&bills, &[settlement(2, 1, 400, false)]);
+        assert_eq!(ledger.net(2), Money::from_cents(-600));
+
+        let ledger = Ledger::new(&bills, &[settlement(2, 1, 400, true)]);
+        assert_eq!(ledger.net(2), Money::from_cents(-1000));
+
+        let ledger = Ledger::new(&bills, &[settlement(2, 1, 1000, false)]);
+        assert!(ledger.debts().is_empty());
+    }
+}
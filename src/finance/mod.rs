@@ -0,0 +1,15 @@
+//! This module contains the money handling for the flat, working out who owes whom from the bills
+//! that have been recorded in the database.
+
+pub mod account;
+pub mod budget;
+pub mod export;
+pub mod kitty;
+pub mod ledger;
+pub mod money;
+pub mod monthly;
+pub mod reconcile;
+pub mod reference;
+pub mod settle;
+pub mod split;
+pub mod statement;
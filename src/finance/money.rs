@@ -0,0 +1,197 @@
+//! An amount of money, stored as a whole number of cents so that it never suffers from floating
+//! point rounding.
+
+use std::{
+    fmt::Display,
+    iter::Sum,
+    ops::{Add, AddAssign, Neg, Sub, SubAssign},
+    str::FromStr,
+};
+
+/// An amount of money in cents. May be negative, e.g. when it is a balance that is owed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub fn from_cents(cents: i64) -> Self {
+        Money(cents)
+    }
+
+    /// Convert a dollar amount entered by a user, rounding to the nearest cent
+    pub fn from_dollars(dollars: f64) -> Self {
+        Money((dollars * 100.0).round() as i64)
+    }
+
+    /// The amount in cents, as it is stored in the database
+    pub fn cents(self) -> i64 {
+        self.0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn abs(self) -> Self {
+        Money(self.0.abs())
+    }
+
+    /// The amount as a plain number of dollars without a currency sign, e.g. `-12.50`, for files
+    /// which are read by other programs
+    pub fn to_decimal(self) -> String {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let cents = self.0.unsigned_abs();
+        format!("{}{}.{:02}", sign, cents / 100, cents % 100)
+    }
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let cents = self.0.unsigned_abs();
+        write!(f, "{}${}.{:02}", sign, cents / 100, cents % 100)
+    }
+}
+
+impl FromStr for Money {
+    type Err = String;
+
+    /// Parse an amount as written in a bank statement, e.g. `-1,234.5` or `$12.00`, without going
+    /// through a float
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cleaned: String = s
+            .trim()
+            .chars()
+            .filter(|c| *c != ',' && *c != '$' && !c.is_whitespace())
+            .collect();
+        let (negative, digits) = match cleaned.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, cleaned.strip_prefix('+').unwrap_or(&cleaned)),
+        };
+
+        let invalid = || format!("`{}` isn't an amount of money", s.trim());
+        let (dollars, cents) = digits.split_once('.').unwrap_or((digits, ""));
+        if (dollars.is_empty() && cents.is_empty())
+            || cents.len() > 2
+            || !dollars
+                .chars()
+                .chain(cents.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let dollars: i64 = match dollars {
+            "" => 0,
+            d => d.parse().map_err(|_| invalid())?,
+        };
+        let cents: i64 = match cents.len() {
+            0 => 0,
+            1 => cents.parse::<i64>().map_err(|_| invalid())? * 10,
+            _ => cents.parse().map_err(|_| invalid())?,
+        };
+
+        let total = dollars * 100 + cents;
+        Ok(Money(if negative { -total } else { total }))
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Money(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Money(self.0 - rhs.0)
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Self::Output {
+        Money(-self.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_dollars_rounds_to_the_nearest_cent() {
+        assert_eq!(Money::from_dollars(12.5), Money::from_cents(1250));
+        assert_eq!(Money::from_dollars(0.1 + 0.2), Money::from_cents(30));
+        assert_eq!(Money::from_dollars(19.999), Money::from_cents(2000));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Money::from_cents(1250).to_string(), "$12.50");
+        assert_eq!(Money::from_cents(5).to_string(), "$0.05");
+        assert_eq!(Money::from_cents(-1250).to_string(), "-$12.50");
+        assert_eq!(Money::ZERO.to_string(), "$0.00");
+    }
+
+    #[test]
+    fn to_decimal() {
+        assert_eq!(Money::from_cents(123450).to_decimal(), "1234.50");
+        assert_eq!(Money::from_cents(-7).to_decimal(), "-0.07");
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("12.5".parse(), Ok(Money::from_cents(1250)));
+        assert_eq!("$12.00".parse(), Ok(Money::from_cents(1200)));
+        assert_eq!("-1,234.56".parse(), Ok(Money::from_cents(-123456)));
+        assert_eq!("+.5".parse(), Ok(Money::from_cents(50)));
+        assert_eq!(" 7 ".parse(), Ok(Money::from_cents(700)));
+    }
+
+    #[test]
+    fn parse_rejects_non_amounts() {
+        for s in ["", "-", ".", "1.234", "12a", "1.2.3", "--1"] {
+            assert!(s.parse::<Money>().is_err(), "`{}` parsed", s);
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut total: Money = [1, 2, 3].iter().map(|c| Money::from_cents(*c)).sum();
+        assert_eq!(total, Money::from_cents(6));
+        total -= Money::from_cents(10);
+        assert_eq!(total, Money::from_cents(-4));
+        assert_eq!(-total, total.abs());
+    }
+}
@@ -0,0 +1,241 @@
+//! A summary of one flatmate's bills over a calendar month, sent to them at the start of the next.
+
+use std::collections::HashMap;
+
+use chrono::{Datelike, Months, NaiveDate, NaiveDateTime};
+use entity::payment::Model as PaymentModel;
+
+use super::money::Money;
+
+/// A bill a flatmate had a share of
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementBill {
+    pub bill: i32,
+    pub created_at: NaiveDateTime,
+    pub purpose: String,
+    /// the flatmate's share of the bill
+    pub share: Money,
+    /// whether the flatmate paid for the bill, and is owed the other shares
+    pub paid_for: bool,
+    /// when the flatmate paid their share, if they have
+    pub paid_at: Option<NaiveDateTime>,
+}
+
+/// A flatmate's statement for a month
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonthlyStatement {
+    pub user: u64,
+    /// the first day of the month
+    pub month: NaiveDate,
+    /// the bills posted during the month which the flatmate had a share of, oldest first
+    pub bills: Vec<StatementBill>,
+    /// the total of the flatmate's shares paid off during the month
+    pub paid: Money,
+    /// the unpaid shares the flatmate owed others at the end of the month
+    pub owes: Money,
+    /// the unpaid shares others owed the flatmate at the end of the month
+    pub owed: Money,
+    /// the flatmate's shares of the month's bills added up by purpose, largest first
+    pub categories: Vec<(String, Money)>,
+}
+
+impl MonthlyStatement {
+    /// whether anything happened to the flatmate's money during the month
+    pub fn is_empty(&self) -> bool {
+        self.bills.is_empty() && self.paid.is_zero() && self.owes.is_zero() && self.owed.is_zero()
+    }
+}
+
+/// the first day of the month a date falls in
+pub fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// Build a flatmate's statement for the month starting on `month`, from every bill in the guild.
+/// Voided bills are left out.
+pub fn build(payments: &[PaymentModel], user: u64, month: NaiveDate) -> MonthlyStatement {
+    let month = first_of_month(month);
+    let end = month + Months::new(1);
+    let in_month = |date: NaiveDateTime| date.date() >= month && date.date() < end;
+    let before_end = |date: NaiveDateTime| date.date() < end;
+
+    let mut statement = MonthlyStatement {
+        user,
+        month,
+        bills: Vec::new(),
+        paid: Money::ZERO,
+        owes: Money::ZERO,
+        owed: Money::ZERO,
+        categories: Vec::new(),
+    };
+    let mut categories: HashMap<String, (String, Money)> = HashMap::new();
+
+    for payment in payments.iter().filter(|p| p.voided_at.is_none()) {
+        if !before_end(payment.created_at) {
+            continue;
+        }
+
+        let paid_at = |share_user: i64| {
+            payment
+                .paid_users
+                .iter()
+                .position(|u| *u == share_user)
+                .and_then(|i| payment.paid_at.get(i).copied())
+        };
+        let paid_for = payment.created_by as u64 == user;
+
+        for (share_user, amount) in payment
+            .individual_users
+            .iter()
+            .zip(payment.individual_amounts.iter())
+        {
+            let amount = Money::from_cents(*amount);
+            let share_paid_at = paid_at(*share_user);
+            let unpaid_at_end = !matches!(share_paid_at, Some(at) if before_end(at));
+
+            if *share_user as u64 == user {
+                if in_month(payment.created_at) {
+                    statement.bills.push(StatementBill {
+                        bill: payment.id,
+                        created_at: payment.created_at,
+                        purpose: payment.purpose.clone(),
+                        share: amount,
+                        paid_for,
+                        paid_at: share_paid_at,
+                    });
+
+                    let key = payment.purpose.trim().to_lowercase();
+                    let category = categories
+                        .entry(key)
+                        .or_insert_with(|| (payment.purpose.trim().to_string(), Money::ZERO));
+                    category.1 += amount;
+                }
+
+                if paid_for {
+                    continue;
+                }
+                if matches!(share_paid_at, Some(at) if in_month(at)) {
+                    statement.paid += amount;
+                }
+                if unpaid_at_end {
+                    statement.owes += amount;
+                }
+            } else if paid_for && *share_user != payment.created_by && unpaid_at_end {
+                statement.owed += amount;
+            }
+        }
+    }
+
+    statement.bills.sort_by_key(|bill| bill.created_at);
+    statement.categories = categories.into_values().collect();
+    statement
+        .categories
+        .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    statement
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn june(day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 6, day)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap()
+    }
+
+    fn bill(
+        id: i32,
+        created_by: i64,
+        created_at: NaiveDateTime,
+        purpose: &str,
+        paid: &[(i64, NaiveDateTime)],
+    ) -> PaymentModel {
+        PaymentModel {
+            id,
+            total_amount: 10001,
+            created_by,
+            created_at,
+            originating_message_id: 0,
+            individual_users: vec![1, 2],
+            individual_amounts: vec![5001, 5000],
+            image_path: String::new(),
+            purpose: purpose.to_string(),
+            account: String::new(),
+            receipt_url: String::new(),
+            channel_id: 0,
+            guild_id: None,
+            paid_users: paid.iter().map(|(user, _)| *user).collect(),
+            paid_at: paid.iter().map(|(_, at)| *at).collect(),
+            reminded_users: vec![],
+            reminded_at: vec![],
+            voided_at: None,
+            share_references: vec![],
+            period_start: None,
+            period_end: None,
+            disputed_users: vec![],
+            dispute_reasons: vec![],
+            dispute_outcomes: vec![],
+        }
+    }
+
+    fn bills() -> Vec<PaymentModel> {
+        vec![
+            // paid by 1, with 2 paying them back the month after
+            bill(1, 1, june(1), "Power", &[(2, june(1) + Duration::days(40))]),
+            bill(2, 2, june(3), "power ", &[]),
+            // from the month before, paid off by 1 during june
+            bill(
+                3,
+                2,
+                june(1) - Duration::days(10),
+                "Internet",
+                &[(1, june(1))],
+            ),
+        ]
+    }
+
+    #[test]
+    fn statement_for_the_month() {
+        let statement = build(&bills(), 1, NaiveDate::from_ymd_opt(2023, 6, 15).unwrap());
+
+        assert_eq!(
+            statement.month,
+            NaiveDate::from_ymd_opt(2023, 6, 1).unwrap()
+        );
+        assert_eq!(
+            statement.bills.iter().map(|b| b.bill).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(statement.bills[0].paid_for);
+        assert_eq!(statement.paid, Money::from_cents(5001));
+        assert_eq!(statement.owes, Money::from_cents(5001));
+        assert_eq!(statement.owed, Money::from_cents(5000));
+        assert_eq!(
+            statement.categories,
+            vec![(String::from("Power"), Money::from_cents(10002))]
+        );
+    }
+
+    #[test]
+    fn paid_after_the_month_is_still_owed() {
+        let statement = build(&bills(), 2, NaiveDate::from_ymd_opt(2023, 6, 1).unwrap());
+
+        assert_eq!(statement.paid, Money::ZERO);
+        assert_eq!(statement.owes, Money::from_cents(5000));
+        assert_eq!(statement.owed, Money::from_cents(5001));
+    }
+
+    #[test]
+    fn voided_bills_are_left_out() {
+        let mut bills = bills();
+        for bill in bills.iter_mut() {
+            bill.voided_at = Some(june(20));
+        }
+
+        assert!(build(&bills, 1, NaiveDate::from_ymd_opt(2023, 6, 1).unwrap()).is_empty());
+    }
+}
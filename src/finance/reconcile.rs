@@ -0,0 +1,371 @@
+//! Matches the money coming into an account against the unpaid shares of the bills that were paid
+//! into it, so they can be ticked off without going through the statement by hand.
+
+use entity::payment::Model as PaymentModel;
+
+use super::{money::Money, reference, statement::Transaction};
+
+/// A flatmate's unpaid share of a bill
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutstandingShare {
+    pub payment_id: i32,
+    /// the discord id of the flatmate who owes the share
+    pub user: u64,
+    pub amount: Money,
+    /// the reference the flatmate was asked to pay with, if the bill has them
+    pub reference: Option<String>,
+}
+
+/// The result of matching a statement against the outstanding shares
+#[derive(Debug, Default)]
+pub struct Reconciliation<'a> {
+    /// transactions that could only be paying the one share
+    pub matched: Vec<(&'a Transaction, OutstandingShare)>,
+    /// transactions that could be paying any of several shares, or only match a share by its amount,
+    /// which are left for someone to confirm
+    pub ambiguous: Vec<(&'a Transaction, Vec<OutstandingShare>)>,
+    /// money that came in which doesn't match any unpaid share
+    pub unmatched: Vec<&'a Transaction>,
+}
+
+/// The shares of the given bills which are still to be paid, leaving out the share of whoever paid
+/// the bill in the first place
+pub fn outstanding_shares(payments: &[PaymentModel]) -> Vec<OutstandingShare> {
+    payments
+        .iter()
+        .filter(|payment| payment.voided_at.is_none())
+        .flat_map(|payment| {
+            payment
+                .individual_users
+                .iter()
+                .zip(payment.individual_amounts.iter())
+                .enumerate()
+                .filter(|(_, (user, _))| {
+                    **user != payment.created_by && !payment.paid_users.contains(user)
+                })
+                .map(|(i, (user, amount))| OutstandingShare {
+                    payment_id: payment.id,
+                    user: *user as u64,
+                    amount: Money::from_cents(*amount),
+                    reference: payment.share_references.get(i).cloned(),
+                })
+        })
+        .collect()
+}
+
+/// whether the text of a transaction mentions the given bill by its number, as `#12`, `bill 12`,
+/// `bill #12` or `bill12`. A bare number is far more likely to be part of a date, an account or an
+/// amount, so it isn't taken as a mention
+fn mentions_bill(text: &str, payment_id: i32) -> bool {
+    let id = payment_id.to_string();
+    let tokens: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric() && c != '#')
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    tokens.iter().enumerate().any(|(i, token)| {
+        if token.strip_prefix('#') == Some(id.as_str()) {
+            return true;
+        }
+        match token.strip_prefix("bill") {
+            Some("") => tokens
+                .get(i + 1)
+                .is_some_and(|next| next.trim_start_matches('#') == id),
+            Some(rest) => rest.trim_start_matches('#') == id,
+            None => false,
+        }
+    })
+}
+
+/// whether a transaction was made with the reference given for a share, in any of the fields a
+/// reference can be typed into. Each field, or a word of it, has to be the reference exactly, so
+/// that `PWR-0412-JO` doesn't match a payment of `PWR-0412-JO2`
+fn mentions_reference(transaction: &Transaction, share: &OutstandingShare) -> bool {
+    let reference = match share.reference.as_deref().map(reference::normalise) {
+        Some(r) if !r.is_empty() => r,
+        _ => return false,
+    };
+
+    [
+        &transaction.reference,
+        &transaction.particulars,
+        &transaction.code,
+    ]
+    .iter()
+    .any(|field| {
+        reference::normalise(field) == reference
+            || field
+                .split_whitespace()
+                .any(|word| reference::normalise(word) == reference)
+    })
+}
+
+/// whether the words of a transaction mention any of the given names
+fn mentions_name(words: &[&str], names: &[String]) -> bool {
+    words.iter().any(|w| names.iter().any(|n| n == w))
+}
+
+/// Match each transaction paying money in against the outstanding shares.
+///
+/// A transaction can pay a share if it is for exactly the same amount. When several shares could
+/// be paid, the share's own reference, then a mention of the bill's number in the reference fields,
+/// then a mention of the flatmate's name, is used to pick between them. A transaction which matches
+/// a share by its amount alone is never taken as paying it, as any unrelated deposit of the same
+/// amount would do. `names` gives the lowercase names each flatmate may be known by on a
+/// statement. Each share is matched at most once.
+pub fn reconcile<'a>(
+    transactions: &'a [Transaction],
+    mut shares: Vec<OutstandingShare>,
+    names: &[(u64, Vec<String>)],
+) -> Reconciliation<'a> {
+    let mut result = Reconciliation::default();
+    let names_of = |user: u64| {
+        names
+            .iter()
+            .find(|(id, _)| *id == user)
+            .map(|(_, names)| names.as_slice())
+            .unwrap_or_default()
+    };
+
+    for transaction in transactions.iter().filter(|t| t.amount > Money::ZERO) {
+        let text = transaction.text();
+        let words: Vec<&str> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect();
+
+        let scored: Vec<(usize, u8)> = shares
+            .iter()
+            .enumerate()
+            .filter(|(_, share)| share.amount == transaction.amount)
+            .map(|(i, share)| {
+                let score = 4 * mentions_reference(transaction, share) as u8
+                    + 2 * mentions_bill(&text, share.payment_id) as u8
+                    + mentions_name(&words, names_of(share.user)) as u8;
+                (i, score)
+            })
+            .collect();
+
+        let best = match scored.iter().map(|(_, score)| *score).max() {
+            Some(best) => best,
+            None => {
+                result.unmatched.push(transaction);
+                continue;
+            }
+        };
+        let candidates: Vec<usize> = scored
+            .iter()
+            .filter(|(_, score)| *score == best)
+            .map(|(i, _)| *i)
+            .collect();
+
+        if candidates.len() == 1 && best > 0 {
+            let share = shares.remove(candidates[0]);
+            result.matched.push((transaction, share));
+        } else {
+            let candidates = candidates.into_iter().map(|i| shares[i].clone()).collect();
+            result.ambiguous.push((transaction, candidates));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn transaction(cents: i64, payee: &str, particulars: &str, reference: &str) -> Transaction {
+        Transaction {
+            date: NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(),
+            amount: Money::from_cents(cents),
+            payee: payee.to_string(),
+            particulars: particulars.to_string(),
+            code: String::new(),
+            reference: reference.to_string(),
+        }
+    }
+
+    fn share(payment_id: i32, user: u64, cents: i64, reference: &str) -> OutstandingShare {
+        OutstandingShare {
+            payment_id,
+            user,
+            amount: Money::from_cents(cents),
+            reference: Some(reference.to_string()),
+        }
+    }
+
+    fn names() -> Vec<(u64, Vec<String>)> {
+        vec![
+            (1, vec![String::from("jo"), String::from("smith")]),
+            (2, vec![String::from("sam")]),
+        ]
+    }
+
+    fn payment(id: i32, created_by: i64, shares: &[(i64, i64)], paid: &[i64]) -> PaymentModel {
+        PaymentModel {
+            id,
+            total_amount: shares.iter().map(|(_, amount)| amount).sum(),
+            created_by,
+            created_at: NaiveDate::from_ymd_opt(2023, 6, 1)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+            originating_message_id: 0,
+            individual_users: shares.iter().map(|(user, _)| *user).collect(),
+            individual_amounts: shares.iter().map(|(_, amount)| *amount).collect(),
+            image_path: String::new(),
+            purpose: String::from("Power"),
+            account: String::new(),
+            receipt_url: String::new(),
+            channel_id: 0,
+            guild_id: None,
+            paid_users: paid.to_vec(),
+            paid_at: vec![],
+            reminded_users: vec![],
+            reminded_at: vec![],
+            voided_at: None,
+            share_references: vec![String::from("PWR-0106-JO"), String::from("PWR-0106-SA")],
+            period_start: None,
+            period_end: None,
+            disputed_users: vec![],
+            dispute_reasons: vec![],
+            dispute_outcomes: vec![],
+        }
+    }
+
+    #[test]
+    fn outstanding_shares_leave_out_paid_and_creator() {
+        let mut voided = payment(3, 2, &[(1, 500)], &[]);
+        voided.voided_at = Some(voided.created_at);
+        let payments = [
+            payment(1, 3, &[(1, 1000), (2, 2000), (3, 3000)], &[2]),
+            voided,
+        ];
+
+        assert_eq!(
+            outstanding_shares(&payments),
+            vec![share(1, 1, 1000, "PWR-0106-JO")]
+        );
+    }
+
+    #[test]
+    fn bill_mentions() {
+        assert!(mentions_bill("power #12", 12));
+        assert!(mentions_bill("bill 12", 12));
+        assert!(mentions_bill("bill #12", 12));
+        assert!(mentions_bill("bill12", 12));
+        assert!(mentions_bill("bill#12 power", 12));
+        assert!(!mentions_bill("12/06 power", 12));
+        assert!(!mentions_bill("bill 120", 12));
+        assert!(!mentions_bill("#123", 12));
+        assert!(!mentions_bill("bills 12", 12));
+    }
+
+    #[test]
+    fn amount_alone_needs_review() {
+        let transactions = [transaction(4000, "", "", "")];
+        let result = reconcile(
+            &transactions,
+            vec![share(1, 1, 4000, "PWR-0106-JO")],
+            &names(),
+        );
+
+        assert!(result.matched.is_empty());
+        assert_eq!(result.ambiguous.len(), 1);
+    }
+
+    #[test]
+    fn reference_bill_or_name_picks_a_share() {
+        let shares = || {
+            vec![
+                share(1, 1, 4000, "PWR-0106-JO"),
+                share(2, 2, 4000, "INT-0206-SA"),
+            ]
+        };
+
+        for transaction in [
+            transaction(4000, "", "", "int-0206-sa"),
+            transaction(4000, "", "bill #2", ""),
+            transaction(4000, "S Sam", "", ""),
+        ] {
+            let transactions = [transaction];
+            let result = reconcile(&transactions, shares(), &names());
+            assert_eq!(result.matched.len(), 1);
+            assert_eq!(result.matched[0].1.payment_id, 2);
+        }
+
+        // a name on its own doesn't pick between two shares of the same flatmate
+        let transactions = [transaction(4000, "J Smith", "", "")];
+        let result = reconcile(
+            &transactions,
+            vec![
+                share(1, 1, 4000, "PWR-0106-JO"),
+                share(2, 1, 4000, "INT-0206-JO"),
+            ],
+            &names(),
+        );
+        assert_eq!(result.ambiguous[0].1.len(), 2);
+    }
+
+    #[test]
+    fn each_share_matched_once() {
+        let transactions = [
+            transaction(4000, "", "", "PWR-0106-JO"),
+            transaction(4000, "", "", "PWR-0106-JO"),
+            transaction(1234, "", "", "PWR-0106-JO"),
+            transaction(-4000, "", "", "PWR-0106-JO"),
+        ];
+        let result = reconcile(
+            &transactions,
+            vec![share(1, 1, 4000, "PWR-0106-JO")],
+            &names(),
+        );
+
+        assert_eq!(result.matched.len(), 1);
+        assert_eq!(result.unmatched.len(), 2);
+        assert!(result.ambiguous.is_empty());
+    }
+
+    #[test]
+    fn reference_must_match_exactly() {
+        let jo = share(1, 1, 3750, "PWR-0412-JO");
+
+        assert!(mentions_reference(
+            &transaction(3750, "", "", "PWR-0412-JO"),
+            &jo
+        ));
+        assert!(mentions_reference(
+            &transaction(3750, "", "pwr0412jo", ""),
+            &jo
+        ));
+        assert!(mentions_reference(
+            &transaction(3750, "", "rent", "PWR-0412-JO power"),
+            &jo
+        ));
+        assert!(!mentions_reference(
+            &transaction(3750, "", "", "PWR-0412-JO2"),
+            &jo
+        ));
+        // a reference can't be made up of the end of one field and the start of the next
+        assert!(!mentions_reference(
+            &transaction(3750, "", "PWR-0412", "JO"),
+            &jo
+        ));
+    }
+
+    #[test]
+    fn suffixed_reference_picks_its_own_share() {
+        let shares = vec![
+            share(1, 1, 3750, "PWR-0412-JO"),
+            share(1, 2, 3750, "PWR-0412-JO2"),
+        ];
+        let transactions = [transaction(3750, "", "", "PWR-0412-JO2")];
+
+        let result = reconcile(&transactions, shares, &[]);
+        assert_eq!(result.matched.len(), 1);
+        assert_eq!(result.matched[0].1.user, 2);
+    }
+}
@@ -0,0 +1,134 @@
+//! Short codes which flatmates put in the reference of a bank transfer, so that each transfer can
+//! be matched to the share of the bill it pays, e.g. `PWR-0412-JO` for Jo's share of the power
+//! bill posted on the 4th of December.
+
+use std::collections::HashSet;
+
+use chrono::NaiveDate;
+
+/// the most letters of the purpose used in a reference
+const PURPOSE_LENGTH: usize = 3;
+
+/// abbreviate the purpose of a bill, keeping the first letter and then consonants, e.g. `PWR`
+fn abbreviate_purpose(purpose: &str) -> String {
+    let letters: Vec<char> = purpose
+        .chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    let mut abbreviation: String = letters.first().into_iter().collect();
+    abbreviation.extend(
+        letters
+            .iter()
+            .skip(1)
+            .filter(|c| !"AEIOU".contains(**c))
+            .take(PURPOSE_LENGTH - 1),
+    );
+
+    // short purposes without many consonants use whatever letters they have
+    if abbreviation.len() < PURPOSE_LENGTH {
+        abbreviation = letters.iter().take(PURPOSE_LENGTH).collect();
+    }
+    if abbreviation.is_empty() {
+        abbreviation = String::from("BIL");
+    }
+    abbreviation
+}
+
+/// abbreviate a flatmate's name to two letters, e.g. `JO`
+fn abbreviate_name(name: &str) -> String {
+    let letters: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(2)
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    if letters.is_empty() {
+        String::from("XX")
+    } else {
+        letters
+    }
+}
+
+/// Strip a reference down to its letters and digits, lowercased, as banks often drop or replace
+/// the dashes when a reference is typed in
+pub fn normalise(reference: &str) -> String {
+    reference
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Create a reference for each flatmate's share of a bill, given their names, in the same order.
+///
+/// References are unique: if one is already in `taken`, or was just given to someone else on the
+/// same bill, a number is added to the end of it.
+pub fn generate(
+    purpose: &str,
+    date: NaiveDate,
+    names: &[&str],
+    taken: &HashSet<String>,
+) -> Vec<String> {
+    let prefix = format!("{}-{}", abbreviate_purpose(purpose), date.format("%d%m"));
+    let mut used: HashSet<String> = taken.iter().map(|r| normalise(r)).collect();
+
+    names
+        .iter()
+        .map(|name| {
+            let base = format!("{}-{}", prefix, abbreviate_name(name));
+            let reference = (1..)
+                .map(|n| match n {
+                    1 => base.clone(),
+                    n => format!("{}{}", base, n),
+                })
+                .find(|r| !used.contains(&normalise(r)))
+                .unwrap_or(base);
+            used.insert(normalise(&reference));
+            reference
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn december(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2022, 12, day).unwrap()
+    }
+
+    #[test]
+    fn abbreviations() {
+        assert_eq!(abbreviate_purpose("Power"), "PWR");
+        assert_eq!(abbreviate_purpose("internet bill"), "INT");
+        assert_eq!(abbreviate_purpose("Eau"), "EAU");
+        assert_eq!(abbreviate_purpose("$$"), "BIL");
+        assert_eq!(abbreviate_name("jo"), "JO");
+        assert_eq!(abbreviate_name("?"), "XX");
+    }
+
+    #[test]
+    fn generate_for_each_flatmate() {
+        let references = generate("Power", december(4), &["Jo", "Sam"], &HashSet::new());
+        assert_eq!(references, vec!["PWR-0412-JO", "PWR-0412-SA"]);
+    }
+
+    #[test]
+    fn generate_unique_references() {
+        let taken = HashSet::from([String::from("pwr0412jo")]);
+        let references = generate("Power", december(4), &["Jo", "Joe", "Jo"], &taken);
+        assert_eq!(
+            references,
+            vec!["PWR-0412-JO2", "PWR-0412-JO3", "PWR-0412-JO4"]
+        );
+    }
+
+    #[test]
+    fn normalise_ignores_case_and_punctuation() {
+        assert_eq!(normalise("PWR-0412-JO"), "pwr0412jo");
+        assert_eq!(normalise(" pwr 0412/jo "), "pwr0412jo");
+    }
+}
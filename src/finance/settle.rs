@@ -0,0 +1,140 @@
+//! Works out the fewest transfers needed to settle every outstanding debt in the flat.
+
+use super::money::Money;
+
+/// A single transfer of money between two flatmates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    /// the discord id of the flatmate sending money
+    pub from: u64,
+    /// the discord id of the flatmate receiving money
+    pub to: u64,
+    /// the amount to transfer
+    pub amount: Money,
+}
+
+/// Given the net position of each flatmate (positive if they are owed money, negative if they owe),
+/// produce a set of transfers which brings everyone back to zero.
+///
+/// Flatmates whose positions exactly cancel are paired off first, then the largest debtor pays the
+/// largest creditor until nobody is left owing. This never needs more than one fewer transfer than
+/// there are flatmates, and in practice is usually optimal for a flat-sized group.
+pub fn simplify(balances: &[(u64, Money)]) -> Vec<Transfer> {
+    let mut debtors: Vec<(u64, Money)> = balances
+        .iter()
+        .filter(|(_, amount)| *amount < Money::ZERO)
+        .map(|(user, amount)| (*user, -*amount))
+        .collect();
+    let mut creditors: Vec<(u64, Money)> = balances
+        .iter()
+        .filter(|(_, amount)| *amount > Money::ZERO)
+        .copied()
+        .collect();
+
+    let mut transfers = Vec::new();
+
+    // pair off anyone whose debt exactly matches what someone else is owed
+    debtors.retain(
+        |&(debtor, owes)| match creditors.iter().position(|&(_, owed)| owed == owes) {
+            Some(i) => {
+                let (creditor, _) = creditors.remove(i);
+                transfers.push(Transfer {
+                    from: debtor,
+                    to: creditor,
+                    amount: owes,
+                });
+                false
+            }
+            None => true,
+        },
+    );
+
+    loop {
+        // sort so that the largest amounts are last, ties broken by id to keep plans deterministic
+        debtors.sort_by_key(|&(user, amount)| (amount, user));
+        creditors.sort_by_key(|&(user, amount)| (amount, user));
+
+        let ((debtor, owes), (creditor, owed)) = match (debtors.pop(), creditors.pop()) {
+            (Some(debtor), Some(creditor)) => (debtor, creditor),
+            _ => break,
+        };
+
+        let amount = owes.min(owed);
+        transfers.push(Transfer {
+            from: debtor,
+            to: creditor,
+            amount,
+        });
+
+        if owes > amount {
+            debtors.push((debtor, owes - amount));
+        }
+        if owed > amount {
+            creditors.push((creditor, owed - amount));
+        }
+    }
+
+    transfers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer(from: u64, to: u64, cents: i64) -> Transfer {
+        Transfer {
+            from,
+            to,
+            amount: Money::from_cents(cents),
+        }
+    }
+
+    fn balances(cents: &[(u64, i64)]) -> Vec<(u64, Money)> {
+        cents
+            .iter()
+            .map(|(user, cents)| (*user, Money::from_cents(*cents)))
+            .collect()
+    }
+
+    #[test]
+    fn nothing_owed() {
+        assert!(simplify(&[]).is_empty());
+        assert!(simplify(&balances(&[(1, 0), (2, 0)])).is_empty());
+    }
+
+    #[test]
+    fn exact_matches_are_paired_off() {
+        let plan = simplify(&balances(&[(1, -500), (2, -300), (3, 300), (4, 500)]));
+        assert_eq!(plan, vec![transfer(1, 4, 500), transfer(2, 3, 300)]);
+    }
+
+    #[test]
+    fn largest_debtor_pays_largest_creditor() {
+        let plan = simplify(&balances(&[(1, -700), (2, -300), (3, 600), (4, 400)]));
+        assert_eq!(
+            plan,
+            vec![
+                transfer(1, 3, 600),
+                transfer(2, 4, 300),
+                transfer(1, 4, 100)
+            ]
+        );
+    }
+
+    #[test]
+    fn every_balance_is_brought_to_zero() {
+        let start = balances(&[(1, -1234), (2, 5678), (3, -4000), (4, -444), (5, 0)]);
+        let plan = simplify(&start);
+        assert!(plan.len() < start.len());
+
+        for (user, balance) in start {
+            let sent: Money = plan
+                .iter()
+                .filter(|t| t.from == user)
+                .map(|t| t.amount)
+                .sum();
+            let received: Money = plan.iter().filter(|t| t.to == user).map(|t| t.amount).sum();
+            assert_eq!(balance + sent - received, Money::ZERO);
+        }
+    }
+}
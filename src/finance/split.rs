@@ -0,0 +1,243 @@
+//! Divides the total of a bill between flatmates, either evenly, by weight or by the days each of
+//! them lived in the flat.
+
+use std::fmt::Display;
+
+use chrono::NaiveDate;
+
+use super::money::Money;
+
+/// The reasons a bill can't be split with the weights given
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplitError {
+    /// nobody has a share of the bill
+    NoShares,
+    /// one of the weights was negative, or not a number
+    InvalidWeight(u64),
+    /// the percentages given don't add up to 100
+    PercentTotal(f64),
+    /// nobody lived in the flat during the billing period
+    NobodyResident(NaiveDate, NaiveDate),
+}
+
+impl Display for SplitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SplitError::NoShares => write!(f, "Nobody has a share of this bill."),
+            SplitError::InvalidWeight(user) => {
+                write!(f, "The share given for <@{}> isn't valid.", user)
+            }
+            SplitError::PercentTotal(total) => {
+                write!(f, "The percentages add up to {}%, not 100%.", total)
+            }
+            SplitError::NobodyResident(start, end) => write!(
+                f,
+                "Nobody lived in the flat between {} and {}.",
+                start.format("%d/%m/%y"),
+                end.format("%d/%m/%y")
+            ),
+        }
+    }
+}
+
+/// Split `total` between flatmates in proportion to their weights.
+///
+/// Each share is rounded down to the cent, and whatever is left over from rounding is given to
+/// `remainder_to`. If they don't have a share of the bill, the first flatmate with a nonzero weight
+/// takes the remainder instead. The returned shares always add up to exactly `total`.
+pub fn by_weight(
+    total: Money,
+    weights: &[(u64, f64)],
+    remainder_to: u64,
+) -> Result<Vec<(u64, Money)>, SplitError> {
+    if let Some((user, _)) = weights.iter().find(|(_, w)| !w.is_finite() || *w < 0.0) {
+        return Err(SplitError::InvalidWeight(*user));
+    }
+
+    let sum: f64 = weights.iter().map(|(_, w)| w).sum();
+    if sum <= 0.0 {
+        return Err(SplitError::NoShares);
+    }
+
+    let mut shares: Vec<(u64, Money)> = weights
+        .iter()
+        .map(|(user, w)| {
+            let cents = (total.cents() as f64 * w / sum).floor() as i64;
+            (*user, Money::from_cents(cents))
+        })
+        .collect();
+
+    let remainder = total - shares.iter().map(|(_, amount)| amount).sum::<Money>();
+    let taker = weights
+        .iter()
+        .position(|(user, w)| *user == remainder_to && *w > 0.0)
+        .or_else(|| weights.iter().position(|(_, w)| *w > 0.0));
+    if let Some(i) = taker {
+        shares[i].1 += remainder;
+    }
+
+    Ok(shares)
+}
+
+/// Split `total` by percentage, which must add up to 100.
+pub fn by_percent(
+    total: Money,
+    percents: &[(u64, f64)],
+    remainder_to: u64,
+) -> Result<Vec<(u64, Money)>, SplitError> {
+    let sum: f64 = percents.iter().map(|(_, p)| p).sum();
+    if (sum - 100.0).abs() > 0.001 {
+        return Err(SplitError::PercentTotal(sum));
+    }
+
+    by_weight(total, percents, remainder_to)
+}
+
+/// Split `total` evenly, giving any leftover cents to `remainder_to`.
+pub fn evenly(
+    total: Money,
+    users: &[u64],
+    remainder_to: u64,
+) -> Result<Vec<(u64, Money)>, SplitError> {
+    let weights: Vec<(u64, f64)> = users.iter().map(|user| (*user, 1.0)).collect();
+    by_weight(total, &weights, remainder_to)
+}
+
+/// How many days of the billing period from `start` to `end` a flatmate lived in the flat, counting
+/// both ends of the period and both the day they moved in and the day they moved out.
+pub fn days_in_residence(
+    move_in: Option<NaiveDate>,
+    move_out: Option<NaiveDate>,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> i64 {
+    let from = move_in.map_or(start, |move_in| move_in.max(start));
+    let to = move_out.map_or(end, |move_out| move_out.min(end));
+
+    ((to - from).num_days() + 1).max(0)
+}
+
+/// Split `total` in proportion to the days each flatmate lived in the flat during the billing period
+/// from `start` to `end`. `tenures` gives each flatmate's move in and move out dates, if they have
+/// them. Flatmates who didn't live in the flat at all during the period are left out of the split.
+pub fn by_days(
+    total: Money,
+    tenures: &[(u64, Option<NaiveDate>, Option<NaiveDate>)],
+    start: NaiveDate,
+    end: NaiveDate,
+    remainder_to: u64,
+) -> Result<Vec<(u64, Money)>, SplitError> {
+    let weights: Vec<(u64, f64)> = tenures
+        .iter()
+        .map(|(user, move_in, move_out)| {
+            let days = days_in_residence(*move_in, *move_out, start, end);
+            (*user, days as f64)
+        })
+        .filter(|(_, days)| *days > 0.0)
+        .collect();
+
+    if weights.is_empty() {
+        return Err(SplitError::NobodyResident(start, end));
+    }
+    by_weight(total, &weights, remainder_to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cents(shares: &[(u64, Money)]) -> Vec<(u64, i64)> {
+        shares
+            .iter()
+            .map(|(user, amount)| (*user, amount.cents()))
+            .collect()
+    }
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, month, day).unwrap()
+    }
+
+    #[test]
+    fn evenly_gives_the_remainder_to_one_flatmate() {
+        let shares = evenly(Money::from_cents(10001), &[1, 2, 3], 2).unwrap();
+        assert_eq!(cents(&shares), vec![(1, 3333), (2, 3335), (3, 3333)]);
+        assert_eq!(shares.iter().map(|(_, a)| a).sum::<Money>().cents(), 10001);
+    }
+
+    #[test]
+    fn remainder_falls_back_to_the_first_with_a_share() {
+        let shares = by_weight(Money::from_cents(100), &[(1, 0.0), (2, 1.0), (3, 2.0)], 1).unwrap();
+        assert_eq!(cents(&shares), vec![(1, 0), (2, 34), (3, 66)]);
+    }
+
+    #[test]
+    fn by_weight_rejects_bad_weights() {
+        assert_eq!(
+            by_weight(Money::from_cents(100), &[(1, 1.0), (2, -1.0)], 1),
+            Err(SplitError::InvalidWeight(2))
+        );
+        assert_eq!(
+            by_weight(Money::from_cents(100), &[(1, f64::NAN)], 1),
+            Err(SplitError::InvalidWeight(1))
+        );
+        assert_eq!(
+            by_weight(Money::from_cents(100), &[(1, 0.0)], 1),
+            Err(SplitError::NoShares)
+        );
+        assert_eq!(
+            evenly(Money::from_cents(100), &[], 1),
+            Err(SplitError::NoShares)
+        );
+    }
+
+    #[test]
+    fn by_percent_must_add_up_to_100() {
+        let shares = by_percent(Money::from_cents(999), &[(1, 50.0), (2, 50.0)], 1).unwrap();
+        assert_eq!(cents(&shares), vec![(1, 500), (2, 499)]);
+        assert_eq!(
+            by_percent(Money::from_cents(999), &[(1, 50.0), (2, 40.0)], 1),
+            Err(SplitError::PercentTotal(90.0))
+        );
+    }
+
+    #[test]
+    fn days_in_residence_counts_both_ends() {
+        let (start, end) = (date(6, 1), date(6, 30));
+        assert_eq!(days_in_residence(None, None, start, end), 30);
+        assert_eq!(days_in_residence(Some(date(6, 16)), None, start, end), 15);
+        assert_eq!(days_in_residence(None, Some(date(6, 10)), start, end), 10);
+        assert_eq!(days_in_residence(Some(date(7, 1)), None, start, end), 0);
+        assert_eq!(days_in_residence(None, Some(date(5, 31)), start, end), 0);
+    }
+
+    #[test]
+    fn by_days_leaves_out_flatmates_who_werent_there() {
+        let tenures = [
+            (1, None, None),
+            (2, Some(date(6, 16)), None),
+            (3, None, Some(date(6, 10))),
+            (4, Some(date(7, 1)), None),
+        ];
+        let shares = by_days(
+            Money::from_cents(30000),
+            &tenures,
+            date(6, 1),
+            date(6, 30),
+            1,
+        )
+        .unwrap();
+        assert_eq!(cents(&shares), vec![(1, 16365), (2, 8181), (3, 5454)]);
+        assert_eq!(shares.iter().map(|(_, a)| a).sum::<Money>().cents(), 30000);
+
+        assert_eq!(
+            by_days(
+                Money::from_cents(30000),
+                &tenures[3..],
+                date(6, 1),
+                date(6, 30),
+                1
+            ),
+            Err(SplitError::NobodyResident(date(6, 1), date(6, 30)))
+        );
+    }
+}
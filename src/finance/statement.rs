@@ -0,0 +1,242 @@
+//! Reads the CSV statements exported by NZ banks. Every bank names and orders its columns a little
+//! differently, and most put a few lines about the account above the header, so the header row is
+//! found by looking for the date and amount columns.
+
+use std::fmt::Display;
+
+use chrono::NaiveDate;
+
+use super::money::Money;
+
+/// the date formats used by the banks, tried in order. Two digit years are tried first, as `%Y`
+/// would happily read `23` as the year 23.
+const DATE_FORMATS: [&str; 6] = [
+    "%d/%m/%y", "%d/%m/%Y", "%Y/%m/%d", "%d-%m-%y", "%d-%m-%Y", "%Y-%m-%d",
+];
+
+/// the names each bank uses for the columns we read, lowercased
+const DATE_COLUMNS: [&str; 2] = ["date", "transaction date"];
+const AMOUNT_COLUMNS: [&str; 1] = ["amount"];
+const PAYEE_COLUMNS: [&str; 4] = ["payee", "other party", "op name", "details"];
+const PARTICULARS_COLUMNS: [&str; 2] = ["particulars", "tp part"];
+const CODE_COLUMNS: [&str; 3] = ["code", "tp code", "analysis code"];
+const REFERENCE_COLUMNS: [&str; 4] = ["reference", "tp ref", "memo", "memo/description"];
+
+/// The reasons a statement can't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatementError {
+    /// the file isn't valid CSV
+    Csv(String),
+    /// no row of the file has both a date and an amount column
+    NoHeader,
+    /// a transaction couldn't be read, with the line it is on
+    InvalidRow(u64, String),
+}
+
+impl Display for StatementError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatementError::Csv(e) => write!(f, "The statement isn't a valid CSV file: {}", e),
+            StatementError::NoHeader => write!(
+                f,
+                "Couldn't find the date and amount columns in the statement."
+            ),
+            StatementError::InvalidRow(line, reason) => {
+                write!(f, "Line {} of the statement is invalid: {}", line, reason)
+            }
+        }
+    }
+}
+
+impl From<csv::Error> for StatementError {
+    fn from(e: csv::Error) -> Self {
+        StatementError::Csv(e.to_string())
+    }
+}
+
+/// A single transaction from a bank statement. Money coming into the account is positive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub date: NaiveDate,
+    pub amount: Money,
+    pub payee: String,
+    pub particulars: String,
+    pub code: String,
+    pub reference: String,
+}
+
+impl Transaction {
+    /// the free text fields the payer may have filled in, lowercased
+    pub fn text(&self) -> String {
+        [&self.payee, &self.particulars, &self.code, &self.reference]
+            .iter()
+            .map(|s| s.to_lowercase())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// the positions of the columns we read in a statement
+struct Columns {
+    date: usize,
+    amount: usize,
+    payee: Option<usize>,
+    particulars: Option<usize>,
+    code: Option<usize>,
+    reference: Option<usize>,
+}
+
+impl Columns {
+    /// find the columns in a row, if it is the header row
+    fn find(row: &csv::StringRecord) -> Option<Self> {
+        let names: Vec<String> = row.iter().map(|c| c.trim().to_lowercase()).collect();
+        let position = |aliases: &[&str]| names.iter().position(|n| aliases.contains(&n.as_str()));
+
+        Some(Columns {
+            date: position(&DATE_COLUMNS)?,
+            amount: position(&AMOUNT_COLUMNS)?,
+            payee: position(&PAYEE_COLUMNS),
+            particulars: position(&PARTICULARS_COLUMNS),
+            code: position(&CODE_COLUMNS),
+            reference: position(&REFERENCE_COLUMNS),
+        })
+    }
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(date.trim(), format).ok())
+}
+
+/// Read every transaction in a statement, in the order they appear
+pub fn parse(contents: &[u8]) -> Result<Vec<Transaction>, StatementError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(contents);
+
+    let mut columns: Option<Columns> = None;
+    let mut transactions = Vec::new();
+
+    for row in reader.records() {
+        let row = row?;
+        let line = row.position().map(|p| p.line()).unwrap_or_default();
+
+        let cols = match &columns {
+            Some(cols) => cols,
+            None => {
+                columns = Columns::find(&row);
+                continue;
+            }
+        };
+
+        let field = |i: Option<usize>| i.and_then(|i| row.get(i)).unwrap_or("").trim();
+        let (date, amount) = (field(Some(cols.date)), field(Some(cols.amount)));
+        // blank lines and trailing totals have no date
+        if date.is_empty() || amount.is_empty() {
+            continue;
+        }
+
+        let date = parse_date(date)
+            .ok_or_else(|| StatementError::InvalidRow(line, format!("`{}` isn't a date", date)))?;
+        let amount: Money = amount
+            .parse()
+            .map_err(|e| StatementError::InvalidRow(line, e))?;
+
+        transactions.push(Transaction {
+            date,
+            amount,
+            payee: field(cols.payee).to_string(),
+            particulars: field(cols.particulars).to_string(),
+            code: field(cols.code).to_string(),
+            reference: field(cols.reference).to_string(),
+        });
+    }
+
+    match columns {
+        Some(_) => Ok(transactions),
+        None => Err(StatementError::NoHeader),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn dates_in_each_format() {
+        assert_eq!(parse_date("04/12/22"), Some(date(2022, 12, 4)));
+        assert_eq!(parse_date("04/12/2022"), Some(date(2022, 12, 4)));
+        assert_eq!(parse_date("2022/12/04"), Some(date(2022, 12, 4)));
+        assert_eq!(parse_date(" 04-12-22 "), Some(date(2022, 12, 4)));
+        assert_eq!(parse_date("2022-12-04"), Some(date(2022, 12, 4)));
+        assert_eq!(parse_date("4 Dec 2022"), None);
+    }
+
+    #[test]
+    fn finds_the_header_below_account_details() {
+        let statement = b"Account,01-0902-0068389-00\n\
+            From,01/06/2023\n\
+            \n\
+            Type,Details,Particulars,Code,Reference,Amount,Date\n\
+            Bank Transfer,J Smith,Power,,PWR-0106-JO,\"1,050.00\",02/06/2023\n\
+            Eft-Pos,Countdown,,,,-84.2,03/06/2023\n\
+            ,,,,,,\n\
+            Total,,,,,965.80,\n";
+
+        let transactions = parse(statement).unwrap();
+        assert_eq!(
+            transactions,
+            vec![
+                Transaction {
+                    date: date(2023, 6, 2),
+                    amount: Money::from_cents(105000),
+                    payee: String::from("J Smith"),
+                    particulars: String::from("Power"),
+                    code: String::new(),
+                    reference: String::from("PWR-0106-JO"),
+                },
+                Transaction {
+                    date: date(2023, 6, 3),
+                    amount: Money::from_cents(-8420),
+                    payee: String::from("Countdown"),
+                    particulars: String::new(),
+                    code: String::new(),
+                    reference: String::new(),
+                },
+            ]
+        );
+        assert_eq!(transactions[0].text(), "j smith power  pwr-0106-jo");
+    }
+
+    #[test]
+    fn other_column_names() {
+        let statement = b"Transaction Date,Amount,Other Party,TP Ref\n2023-06-02,40,SAM,int\n";
+
+        let transactions = parse(statement).unwrap();
+        assert_eq!(transactions[0].payee, "SAM");
+        assert_eq!(transactions[0].reference, "int");
+        assert_eq!(transactions[0].amount, Money::from_cents(4000));
+    }
+
+    #[test]
+    fn invalid_statements() {
+        assert_eq!(
+            parse(b"Details,Amount\nJ Smith,40\n"),
+            Err(StatementError::NoHeader)
+        );
+        assert!(matches!(
+            parse(b"Date,Amount\n2023-06-02,forty\n"),
+            Err(StatementError::InvalidRow(2, _))
+        ));
+        assert!(matches!(
+            parse(b"Date,Amount\nyesterday,40\n"),
+            Err(StatementError::InvalidRow(2, _))
+        ));
+    }
+}
@@ -35,6 +35,7 @@ impl Healthcheck {
         // create a simple warp webserver on port 3000
         // that returns a 200 if the state is healthy
         // and a 500 if the state is unhealthy
+        // it also serves the receipt archive under /receipts
 
         let start_time = self.state.start_time;
         let num_connected = self.state.num_connected.clone();
@@ -55,7 +56,11 @@ impl Healthcheck {
             warp::reply::with_status("OK", warp::http::StatusCode::OK)
         });
 
-        let server = warp::serve(healthcheck);
+        let receipts = warp::path("receipts")
+            .and(warp::get())
+            .and(warp::fs::dir(self.state.receipts.root().to_path_buf()));
+
+        let server = warp::serve(healthcheck.or(receipts));
 
         server.bind(([0, 0, 0, 0], 3000)).await;
     }
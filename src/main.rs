@@ -1,23 +1,37 @@
 mod discord_bot;
+mod finance;
 mod google_api;
 
 mod healthcheck;
 
 mod logging;
+mod receipts;
 mod state;
 
 use log::{error, info};
 use std::process::exit;
 
 use crate::{
-    discord_bot::DiscordBot, google_api::maps::GoogleMapsApi, logging::configure_logger,
-    state::AppState,
+    discord_bot::DiscordBot,
+    finance::account::AccountNumber,
+    google_api::maps::GoogleMapsApi,
+    logging::configure_logger,
+    receipts::ReceiptStore,
+    state::{AppState, CONFIG},
 };
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     configure_logger()?;
 
+    // bills default to these accounts, so a typo in the config would send money astray
+    for (owner, account) in CONFIG.accounts() {
+        if let Err(e) = account.parse::<AccountNumber>() {
+            error!("the account configured for {} is invalid: {}", owner, e);
+            exit(1);
+        }
+    }
+
     let database_url = format!(
         "postgres://{}:{}@{}:{}/{}",
         std::env::var("POSTGRES_USER").expect("POSTGRES_USER must be set"),
@@ -29,6 +43,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let discord_token = std::env::var("DISCORD_TOKEN").expect("DISCORD_TOKEN must be set");
     let google_maps_token =
         std::env::var("GOOGLE_MAPS_TOKEN").expect("GOOGLE_MAPS_TOKEN must be set");
+    let receipts = ReceiptStore::new(
+        std::env::var("RECEIPTS_PATH").unwrap_or_else(|_| String::from("receipts")),
+        std::env::var("RECEIPTS_URL").ok(),
+    )?;
 
     info!("spawning google maps handler");
     let mut google_maps_api_handler = GoogleMapsApi::builder().key(google_maps_token).build();
@@ -37,7 +55,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         google_maps_api_handler.run().await;
     });
 
-    let state = AppState::new(database_url, google_maps_api_handle).await?;
+    let state = AppState::new(database_url, google_maps_api_handle, receipts).await?;
 
     info!("spawning discord handler");
     let discord_state = state.clone();
@@ -0,0 +1,80 @@
+//! A content-addressed archive of bill receipts on local disk.
+//!
+//! Discord CDN links expire, so every receipt is downloaded when its bill is created and stored
+//! under the sha256 of its contents. The stored name is kept in `payment.image_path`, and the
+//! archive is served back through the healthcheck server at `/receipts/<name>`.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+#[derive(Debug)]
+pub struct ReceiptStore {
+    root: PathBuf,
+    public_url: Option<String>,
+}
+
+impl ReceiptStore {
+    /// Create a store in the given directory. If `public_url` is set, it is the address the
+    /// `/receipts` route of the healthcheck server can be reached at from outside.
+    pub fn new(root: impl Into<PathBuf>, public_url: Option<String>) -> io::Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)?;
+
+        Ok(Self {
+            root,
+            public_url: public_url.map(|url| url.trim_end_matches('/').to_string()),
+        })
+    }
+
+    /// the directory receipts are stored in
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Store a receipt, returning the name it was stored under. Storing the same file twice
+    /// returns the same name without writing it again.
+    pub async fn store(&self, contents: &[u8], filename: &str) -> io::Result<String> {
+        let name = match extension(filename) {
+            Some(ext) => format!("{:x}.{}", Sha256::digest(contents), ext),
+            None => format!("{:x}", Sha256::digest(contents)),
+        };
+
+        let path = self.root.join(&name);
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(name);
+        }
+
+        // write to a temporary file first, so a half written receipt is never served
+        let temp = self.root.join(format!(".{}.tmp", name));
+        tokio::fs::write(&temp, contents).await?;
+        tokio::fs::rename(&temp, &path).await?;
+
+        Ok(name)
+    }
+
+    /// A link to a stored receipt, if the archive is reachable from outside
+    pub fn url(&self, name: &str) -> Option<String> {
+        if name.is_empty() {
+            return None;
+        }
+
+        self.public_url
+            .as_ref()
+            .map(|url| format!("{}/receipts/{}", url, name))
+    }
+}
+
+/// the lowercased extension of a filename, if it is a sensible one to keep
+fn extension(filename: &str) -> Option<String> {
+    let (_, ext) = filename.rsplit_once('.')?;
+
+    if ext.is_empty() || ext.len() > 5 || !ext.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+
+    Some(ext.to_ascii_lowercase())
+}
@@ -4,6 +4,7 @@ use std::{
     time::Duration,
 };
 
+use chrono::NaiveDate;
 use lazy_static::lazy_static;
 use log::info;
 use migration::{Migrator, MigratorTrait};
@@ -11,22 +12,103 @@ use sea_orm::{ConnectOptions, Database, DatabaseConnection};
 use serde::Deserialize;
 use serenity::prelude::TypeMapKey;
 
-use crate::google_api::maps::GoogleMapsApiHandle;
+use crate::{google_api::maps::GoogleMapsApiHandle, receipts::ReceiptStore};
 
 #[derive(Deserialize)]
 pub struct TomlConfig {
     pub head_tennant_acc_number: String,
     pub destinations: Vec<Destination>,
     pub flatmates: Vec<Flatmate>,
+    #[serde(default = "default_categories")]
+    pub categories: Vec<Category>,
+    #[serde(default)]
+    pub kitty: KittyConfig,
     pub phrases: Vec<String>,
     pub powered_by: Vec<String>,
 }
 
+impl TomlConfig {
+    /// every account number in the config, along with who it belongs to
+    pub fn accounts(&self) -> impl Iterator<Item = (&str, &str)> {
+        std::iter::once(("the head tenant", self.head_tennant_acc_number.as_str())).chain(
+            self.flatmates
+                .iter()
+                .filter_map(|f| f.account.as_deref().map(|a| (f.name.as_str(), a))),
+        )
+    }
+}
+
 #[derive(Deserialize)]
 pub struct Flatmate {
     pub discord_id: u64,
     pub name: String,
     pub display_name: String,
+    /// how many shares of a bill this flatmate pays by default, e.g. 1.2 for the ensuite room
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    /// the account this flatmate is paid back into for bills they cover, instead of the head tenant's
+    #[serde(default)]
+    pub account: Option<String>,
+    /// the day this flatmate moved in, if they moved in after the flat's earliest bills
+    #[serde(default)]
+    pub move_in: Option<NaiveDate>,
+    /// the last day this flatmate lived in the flat, if they have moved out
+    #[serde(default)]
+    pub move_out: Option<NaiveDate>,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+/// A kind of bill, offered when choosing the purpose of a bill
+#[derive(Deserialize)]
+pub struct Category {
+    /// the purpose stored for bills in this category
+    pub name: String,
+    /// the name shown for this category
+    pub label: String,
+    /// how much the flat means to spend on this category each month, in dollars
+    #[serde(default)]
+    pub budget: Option<f64>,
+}
+
+impl Category {
+    fn new(name: &str, label: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            label: label.to_string(),
+            budget: None,
+        }
+    }
+}
+
+/// the categories used if none are configured, without any budgets
+fn default_categories() -> Vec<Category> {
+    vec![
+        Category::new("food", "Food"),
+        Category::new("power", "Power"),
+        Category::new("water", "Water"),
+        Category::new("internet", "Internet/Wifi"),
+    ]
+}
+
+/// When the channel is warned that the kitty is running low, and how far it should be topped up
+#[derive(Deserialize)]
+pub struct KittyConfig {
+    /// the balance, in dollars, below which the kitty is low
+    pub low_balance: f64,
+    /// the balance, in dollars, the suggested top up brings the kitty back up to
+    pub target: f64,
+}
+
+impl Default for KittyConfig {
+    fn default() -> Self {
+        Self {
+            low_balance: 20.0,
+            target: 100.0,
+        }
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -48,6 +130,8 @@ pub struct AppState {
 
     pub database: Arc<DatabaseConnection>,
 
+    pub receipts: Arc<ReceiptStore>,
+
     pub start_time: std::time::Instant,
     pub num_connected: Arc<AtomicU64>,
 }
@@ -56,6 +140,7 @@ impl AppState {
     pub async fn new(
         database_url: String,
         google_api: GoogleMapsApiHandle,
+        receipts: ReceiptStore,
     ) -> Result<Self, Box<dyn Error>> {
         let mut opt = ConnectOptions::new(database_url);
         opt.max_connections(100)
@@ -82,6 +167,8 @@ impl AppState {
 
             database: Arc::new(connection),
 
+            receipts: Arc::new(receipts),
+
             start_time: std::time::Instant::now(),
             num_connected: Arc::new(AtomicU64::new(0)),
         })
@@ -105,6 +192,8 @@ impl Clone for AppState {
 
             database: self.database.clone(),
 
+            receipts: self.receipts.clone(),
+
             start_time: self.start_time,
             num_connected: self.num_connected.clone(),
         }
@@ -114,3 +203,22 @@ impl Clone for AppState {
 impl TypeMapKey for AppState {
     type Value = AppState;
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::finance::account::AccountNumber;
+
+    use super::*;
+
+    #[test]
+    fn example_config_accounts_are_valid() {
+        let config: TomlConfig = toml::from_str(include_str!("../config.example.toml"))
+            .expect("config.example.toml should parse");
+
+        for (owner, account) in config.accounts() {
+            if let Err(e) = account.parse::<AccountNumber>() {
+                panic!("the example account for {} is invalid: {}", owner, e);
+            }
+        }
+    }
+}